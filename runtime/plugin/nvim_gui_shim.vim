@@ -54,3 +54,30 @@ function s:GuiFontCommand(fname, bang) abort
 endfunction
 command! -nargs=? -bang Guifont call s:GuiFontCommand("<args>", "<bang>")
 command! -nargs=? -bang GuiFont call s:GuiFontCommand("<args>", "<bang>")
+
+" Zoom GUI font by given number of points, default is 1
+function s:GuiFontZoomCommand(step) abort
+	let l:step = a:step ==# '' ? 1 : str2nr(a:step)
+	call rpcnotify(1, 'Gui', 'FontZoom', l:step)
+endfunction
+command! -nargs=? GuiFontZoom call s:GuiFontZoomCommand("<args>")
+command! -nargs=0 GuiFontReset call rpcnotify(1, 'Gui', 'FontReset')
+
+" Animate cursor movement for given duration in milliseconds, 0 disables animation
+function s:GuiCursorAnimationCommand(duration, ...) abort
+	let l:easing = a:0 > 0 ? a:1 : 'ease-out'
+	let l:trail = a:0 > 1 ? str2nr(a:2) : 0
+	call rpcnotify(1, 'Gui', 'Option', 'CursorAnimation', str2nr(a:duration), l:easing, l:trail)
+endfunction
+command! -nargs=+ GuiCursorAnimation call s:GuiCursorAnimationCommand(<f-args>)
+
+" Enable (1) or disable (0) GUI tabline, 'tabs' or 'buffers' select what it shows
+function s:GuiTablineCommand(arg) abort
+	if a:arg ==# 'tabs' || a:arg ==# 'buffers'
+		call rpcnotify(1, 'Gui', 'Option', 'Tabline', 1)
+		call rpcnotify(1, 'Gui', 'Option', 'TablineMode', a:arg)
+	else
+		call rpcnotify(1, 'Gui', 'Option', 'Tabline', str2nr(a:arg))
+	endif
+endfunction
+command! -nargs=1 GuiTabline call s:GuiTablineCommand(<q-args>)
@@ -0,0 +1,385 @@
+//! Command palette to find and run GUI actions, nvim user commands and keymaps.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use gdk;
+use gio::prelude::*;
+use gtk;
+use gtk::prelude::*;
+use htmlescape::encode_minimal;
+use pango;
+
+use neovim_lib::{Neovim, NeovimApi, Value};
+
+use file_list;
+use fuzzy;
+use nvim::ErrorReport;
+use shell;
+use ui::UiMutex;
+use value::ValueMapExt;
+
+const MAX_RESULTS: usize = 200;
+
+/// GUI actions registered in `ui.rs` and `main.rs`: action, label, description.
+const GUI_ACTIONS: &[(&str, &str, &str)] = &[
+    ("win.quick-open", "Quick Open", "Find files by name"),
+    ("app.new-window", "New Window", "Open a new window"),
+    ("app.show-sidebar", "Toggle Sidebar", "Show or hide the file browser"),
+    ("app.buffer-tabline", "Toggle Buffer Tabline", "Show buffers instead of tab pages"),
+    ("win.new-tab", "New Tab", "Open a new tab page"),
+    ("win.save-all", "Save All", "Write all changed buffers"),
+    ("win.paste", "Paste", "Paste from the clipboard"),
+    ("win.zoom-in", "Zoom In", "Increase the font size"),
+    ("win.zoom-out", "Zoom Out", "Decrease the font size"),
+    ("win.zoom-reset", "Reset Zoom", "Restore the configured font size"),
+    ("app.Plugins", "Plugins", "Manage plugins"),
+    ("app.HelpAbout", "About", "Show version and authors"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// GTK action, like `app.new-window` or `win.paste`
+    Action(String),
+    /// User command with the number of arguments it takes
+    Command { name: String, nargs: String },
+    /// Normal mode mapping
+    Keymap(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    label: String,
+    description: String,
+    target: Target,
+}
+
+impl Item {
+    fn new(label: &str, description: &str, target: Target) -> Item {
+        Item {
+            label: label.to_owned(),
+            description: description.to_owned(),
+            target,
+        }
+    }
+
+    fn markup(&self, positions: &[usize]) -> String {
+        format!(
+            "{}  <small>{}</small>",
+            fuzzy::highlight_markup(&self.label, positions),
+            encode_minimal(&self.description)
+        )
+    }
+}
+
+/// Parses the result of `nvim_get_commands()`.
+fn parse_commands(commands: &Value) -> Vec<Item> {
+    let mut items: Vec<_> = commands
+        .as_map()
+        .into_iter()
+        .flat_map(|commands| commands.iter())
+        .filter_map(|&(_, ref command)| command.as_map())
+        .filter_map(|command| command.to_attrs_map_report())
+        .filter_map(|command| {
+            let name = command.get("name").and_then(|v| v.as_str())?;
+            let definition = command.get("definition").and_then(|v| v.as_str());
+            let nargs = command.get("nargs").and_then(|v| v.as_str());
+            Some(Item::new(
+                &format!(":{}", name),
+                definition.unwrap_or(""),
+                Target::Command {
+                    name: name.to_owned(),
+                    nargs: nargs.unwrap_or("0").to_owned(),
+                },
+            ))
+        })
+        .collect();
+    items.sort_by(|lhs, rhs| lhs.label.cmp(&rhs.label));
+    items
+}
+
+/// Parses the result of `nvim_get_keymap('n')`.
+fn parse_keymaps(keymaps: &Value) -> Vec<Item> {
+    keymaps
+        .as_array()
+        .into_iter()
+        .flat_map(|keymaps| keymaps.iter())
+        .filter_map(Value::as_map)
+        .filter_map(|keymap| keymap.to_attrs_map_report())
+        .filter_map(|keymap| {
+            let lhs = keymap.get("lhs").and_then(|v| v.as_str())?;
+            // mappings defined with a lua callback have a description instead of rhs
+            let description = keymap
+                .get("desc")
+                .or_else(|| keymap.get("rhs"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            Some(Item::new(lhs, description, Target::Keymap(lhs.to_owned())))
+        })
+        .collect()
+}
+
+fn load_items(nvim: &mut Neovim, window: &gtk::ApplicationWindow) -> Vec<Item> {
+    let app = window.get_application();
+    let mut items: Vec<_> = GUI_ACTIONS
+        .iter()
+        .filter(|&&(action, _, _)| {
+            if action.starts_with("app.") {
+                app.as_ref()
+                    .map_or(false, |app| app.lookup_action(&action[4..]).is_some())
+            } else {
+                window.lookup_action(&action[4..]).is_some()
+            }
+        })
+        .map(|&(action, label, description)| {
+            Item::new(label, description, Target::Action(action.to_owned()))
+        })
+        .collect();
+
+    if let Some(commands) = nvim.eval("nvim_get_commands({})").ok_and_report() {
+        items.extend(parse_commands(&commands));
+    }
+    if let Some(keymaps) = nvim.eval("nvim_get_keymap('n')").ok_and_report() {
+        items.extend(parse_keymaps(&keymaps));
+    }
+    items
+}
+
+/// Shows the command palette.
+pub fn show(shell_state_ref: &Arc<UiMutex<shell::State>>) {
+    let (window, items) = {
+        let state = shell_state_ref.borrow();
+        let window = match state
+            .window()
+            .and_then(|window| window.downcast::<gtk::ApplicationWindow>().ok())
+        {
+            Some(window) => window,
+            None => return,
+        };
+        let mut nvim = match state.nvim() {
+            Some(nvim) => nvim,
+            None => return,
+        };
+        let items = load_items(&mut nvim, &window);
+        (window, Rc::new(items))
+    };
+
+    let dlg = gtk::Dialog::new();
+    dlg.set_title("Command Palette");
+    dlg.set_transient_for(Some(&window));
+    dlg.set_modal(true);
+    dlg.set_destroy_with_parent(true);
+    dlg.set_default_size(600, 400);
+
+    let search_entry = gtk::SearchEntry::new();
+    search_entry.set_placeholder_text("Run action, command or mapping");
+
+    let store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::U32]);
+    let view = gtk::TreeView::new_with_model(&store);
+    view.set_headers_visible(false);
+    view.set_can_focus(false);
+
+    let renderer = gtk::CellRendererText::new();
+    renderer.set_property_ellipsize(pango::EllipsizeMode::End);
+    let column = gtk::TreeViewColumn::new();
+    column.pack_start(&renderer, true);
+    column.add_attribute(&renderer, "markup", 0);
+    view.append_column(&column);
+
+    let scroll = gtk::ScrolledWindow::new(None, None);
+    scroll.set_vexpand(true);
+    scroll.add(&view);
+
+    let content = dlg.get_content_area();
+    content.set_border_width(6);
+    content.set_spacing(6);
+    content.pack_start(&search_entry, false, true, 0);
+    content.pack_start(&scroll, true, true, 0);
+
+    search_entry.connect_search_changed(clone!(items, store, view => move |entry| {
+        update_results(&items, entry, &store, &view);
+    }));
+
+    search_entry.connect_key_press_event(clone!(store, view => move |_, ev| {
+        let offset = match ev.get_keyval() {
+            gdk::enums::key::Down => 1,
+            gdk::enums::key::Up => -1,
+            _ => return Inhibit(false),
+        };
+        file_list::move_selection(&view, &store, offset);
+        Inhibit(true)
+    }));
+
+    search_entry.connect_activate(
+        clone!(shell_state_ref, window, dlg, items, store, view => move |_| {
+            run_selected(&shell_state_ref, &window, &dlg, &items, &view, &store);
+        }),
+    );
+
+    view.connect_row_activated(
+        clone!(shell_state_ref, window, dlg, items, store => move |view, path, _| {
+            view.get_selection().select_path(path);
+            run_selected(&shell_state_ref, &window, &dlg, &items, view, &store);
+        }),
+    );
+
+    search_entry.connect_stop_search(clone!(dlg => move |_| dlg.destroy()));
+    dlg.connect_response(|dlg, _| dlg.destroy());
+
+    update_results(&items, &search_entry, &store, &view);
+    dlg.show_all();
+}
+
+fn update_results(
+    items: &[Item],
+    entry: &gtk::SearchEntry,
+    store: &gtk::ListStore,
+    view: &gtk::TreeView,
+) {
+    store.clear();
+    let pattern = entry.get_text().unwrap_or_default();
+    let ranked = fuzzy::rank_by(&pattern, items, MAX_RESULTS, |item| item.label.as_str(), |_| 0);
+    for (idx, m) in ranked {
+        let markup = items[idx].markup(&m.positions);
+        store.insert_with_values(None, &[0, 1], &[&markup, &(idx as u32)]);
+    }
+    file_list::move_selection(view, store, 0);
+}
+
+fn run_selected(
+    shell_state_ref: &Arc<UiMutex<shell::State>>,
+    window: &gtk::ApplicationWindow,
+    dlg: &gtk::Dialog,
+    items: &[Item],
+    view: &gtk::TreeView,
+    store: &gtk::ListStore,
+) {
+    let idx = view.get_selection()
+        .get_selected()
+        .map(|(_, iter)| iter)
+        .or_else(|| store.get_iter_first())
+        .and_then(|iter| store.get_value(&iter, 1).get::<u32>());
+    let item = match idx.and_then(|idx| items.get(idx as usize)) {
+        Some(item) => item,
+        None => return,
+    };
+
+    // Close first, so actions that show dialogs aren't blocked by the modal palette.
+    dlg.destroy();
+
+    match item.target {
+        Target::Action(ref action) => {
+            if action.starts_with("app.") {
+                if let Some(app) = window.get_application() {
+                    app.activate_action(&action[4..], None);
+                }
+            } else {
+                window.activate_action(&action[4..], None);
+            }
+        }
+        Target::Command { .. } | Target::Keymap(_) => {
+            if let Some(mut nvim) = shell_state_ref.borrow().nvim() {
+                nvim.input(&nvim_input(&item.target)).report_err();
+            }
+        }
+    }
+}
+
+/// Keys to type into nvim to run a command or mapping from normal mode.
+///
+/// Commands that take arguments are left on the command line to complete them.
+fn nvim_input(target: &Target) -> String {
+    match *target {
+        Target::Command {
+            ref name,
+            ref nargs,
+        } => if nargs == "0" {
+            format!("<C-\\><C-n>:{}<CR>", name)
+        } else {
+            format!("<C-\\><C-n>:{}<Space>", name)
+        },
+        Target::Keymap(ref lhs) => format!("<C-\\><C-n>{}", lhs),
+        Target::Action(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(attrs: &[(&str, &str)]) -> Value {
+        Value::Map(
+            attrs
+                .iter()
+                .map(|&(key, value)| (Value::from(key), Value::from(value)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let commands = Value::Map(vec![
+            (
+                Value::from("Rg"),
+                map(&[("name", "Rg"), ("definition", "call Rg(<q-args>)"), ("nargs", "*")]),
+            ),
+            (
+                Value::from("Format"),
+                map(&[("name", "Format"), ("definition", "call Format()"), ("nargs", "0")]),
+            ),
+        ]);
+        assert_eq!(
+            vec![
+                Item::new(
+                    ":Format",
+                    "call Format()",
+                    Target::Command {
+                        name: "Format".to_owned(),
+                        nargs: "0".to_owned(),
+                    },
+                ),
+                Item::new(
+                    ":Rg",
+                    "call Rg(<q-args>)",
+                    Target::Command {
+                        name: "Rg".to_owned(),
+                        nargs: "*".to_owned(),
+                    },
+                ),
+            ],
+            parse_commands(&commands)
+        );
+    }
+
+    #[test]
+    fn test_parse_keymaps() {
+        let keymaps = Value::Array(vec![
+            map(&[("lhs", " ff"), ("rhs", ":Files<CR>"), ("mode", "n")]),
+            map(&[("lhs", "gd"), ("rhs", ""), ("desc", "Go to definition")]),
+        ]);
+        let items = parse_keymaps(&keymaps);
+        assert_eq!(2, items.len());
+        assert_eq!(":Files<CR>", items[0].description);
+        assert_eq!("Go to definition", items[1].description);
+        assert_eq!(Target::Keymap("gd".to_owned()), items[1].target);
+    }
+
+    #[test]
+    fn test_nvim_input() {
+        assert_eq!(
+            "<C-\\><C-n>:Format<CR>",
+            nvim_input(&Target::Command {
+                name: "Format".to_owned(),
+                nargs: "0".to_owned(),
+            })
+        );
+        assert_eq!(
+            "<C-\\><C-n>:Rg<Space>",
+            nvim_input(&Target::Command {
+                name: "Rg".to_owned(),
+                nargs: "*".to_owned(),
+            })
+        );
+        assert_eq!("<C-\\><C-n>gd", nvim_input(&Target::Keymap("gd".to_owned())));
+    }
+}
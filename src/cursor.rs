@@ -1,5 +1,5 @@
 use cairo;
-use color::Color;
+use color::{Color, ColorModel};
 use ui::UiMutex;
 use shell;
 use mode;
@@ -9,7 +9,17 @@ use std::sync::{Arc, Weak};
 use render;
 use render::CellMetrics;
 
+use gdk;
+use gdk::prelude::*;
 use glib;
+use glib::signal;
+use gtk;
+use gtk::prelude::*;
+
+/// Number of cursor copies left behind by trail
+const TRAIL_LENGTH: usize = 4;
+/// Movement progress distance between trail copies
+const TRAIL_STEP: f64 = 0.08;
 
 struct Alpha(f64);
 
@@ -44,10 +54,123 @@ enum AnimPhase {
     Busy,
 }
 
+/// Cursor blink timings in milliseconds, see `:help guicursor`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blink {
+    wait: u32,
+    on: u32,
+    off: u32,
+}
+
+impl Blink {
+    /// Blink timings for given mode, `None` if cursor must not blink
+    pub fn from_mode(mode: &mode::Mode) -> Option<Blink> {
+        match mode.mode_info() {
+            // cursor style disabled, so keep old default blinking
+            None => Some(Blink {
+                wait: 500,
+                on: 500,
+                off: 300,
+            }),
+            Some(mode_info) => match mode_info.blink() {
+                Some((wait, on, off)) if wait > 0 && on > 0 && off > 0 => Some(Blink {
+                    wait: wait as u32,
+                    on: on as u32,
+                    off: off as u32,
+                }),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Easing function for cursor movement animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease-out" => Some(Easing::EaseOut),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            _ => None,
+        }
+    }
+
+    /// Map linear animation time `t` in range 0..1 to movement progress
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.max(0.0).min(1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+            },
+        }
+    }
+}
+
+/// Cursor movement animation settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    /// duration in milliseconds
+    duration: u32,
+    easing: Easing,
+    trail: bool,
+}
+
+impl Animation {
+    /// Animation settings from `GuiCursorAnimation` arguments, zero duration disables animation
+    pub fn new(duration: u64, easing: Option<&str>, trail: bool) -> Result<Option<Animation>, String> {
+        if duration == 0 {
+            return Ok(None);
+        }
+
+        let easing = match easing {
+            Some(name) => {
+                Easing::from_name(name).ok_or_else(|| format!("Unknown easing {}", name))?
+            }
+            None => Easing::EaseOut,
+        };
+
+        Ok(Some(Animation {
+            duration: duration as u32,
+            easing,
+            trail,
+        }))
+    }
+}
+
+struct Movement {
+    from: (f64, f64),
+    to: (f64, f64),
+    start_time: Option<i64>,
+    progress: f64,
+
+    clock: gdk::FrameClock,
+    update_handler: glib::SignalHandlerId,
+}
+
+impl Movement {
+    fn point(&self) -> (f64, f64) {
+        movement_point(self.from, self.to, self.progress)
+    }
+}
+
 struct State {
     alpha: Alpha,
     anim_phase: AnimPhase,
     shell: Weak<UiMutex<shell::State>>,
+    blink: Option<Blink>,
+    animation: Option<Animation>,
+    movement: Option<Movement>,
 
     timer: Option<glib::SourceId>,
 }
@@ -58,10 +181,37 @@ impl State {
             alpha: Alpha(1.0),
             anim_phase: AnimPhase::Shown,
             shell: shell,
+            blink: None,
+            animation: None,
+            movement: None,
             timer: None,
         }
     }
 
+    /// Update movement progress for given frame time in microseconds
+    fn movement_step(&mut self, frame_time: i64) {
+        let finished = match (self.animation.as_ref(), self.movement.as_mut()) {
+            (Some(animation), Some(movement)) => {
+                let start_time = *movement.start_time.get_or_insert(frame_time);
+                let t = (frame_time - start_time) as f64 / (animation.duration as f64 * 1000.0);
+                movement.progress = animation.easing.apply(t);
+                t >= 1.0
+            }
+            _ => true,
+        };
+
+        if finished {
+            self.stop_movement();
+        }
+    }
+
+    fn stop_movement(&mut self) {
+        if let Some(movement) = self.movement.take() {
+            signal::signal_handler_disconnect(&movement.clock, movement.update_handler);
+            movement.clock.end_updating();
+        }
+    }
+
     fn reset_to(&mut self, phase: AnimPhase) {
         self.alpha = Alpha(1.0);
         self.anim_phase = phase;
@@ -84,7 +234,114 @@ impl Cursor {
         let state = self.state.clone();
         let mut mut_state = self.state.borrow_mut();
         mut_state.reset_to(AnimPhase::Shown);
-        mut_state.timer = Some(glib::timeout_add(500, move || anim_step(&state)));
+        if let Some(wait) = mut_state.blink.as_ref().map(|blink| blink.wait) {
+            mut_state.timer = Some(glib::timeout_add(wait, move || anim_step(&state)));
+        }
+    }
+
+    /// Update blink timings, restarts blinking if they are changed
+    pub fn set_blink(&mut self, blink: Option<Blink>) {
+        let restart = {
+            let mut state = self.state.borrow_mut();
+            if state.blink == blink {
+                return;
+            }
+            state.blink = blink;
+
+            state.anim_phase != AnimPhase::Busy && state.anim_phase != AnimPhase::NoFocus
+        };
+
+        if restart {
+            self.start();
+        }
+    }
+
+    /// Enable or disable (for `None`) cursor movement animation
+    pub fn set_animation(&mut self, animation: Option<Animation>) {
+        let mut state = self.state.borrow_mut();
+        state.stop_movement();
+        state.animation = animation;
+    }
+
+    /// Animate cursor movement between given points when animation is enabled
+    pub fn move_to(&mut self, from: (f64, f64), to: (f64, f64), drawing_area: &gtk::DrawingArea) {
+        let mut state = self.state.borrow_mut();
+        if state.animation.is_none() {
+            return;
+        }
+
+        let clock = match drawing_area.get_frame_clock() {
+            Some(clock) => clock,
+            None => return,
+        };
+
+        // continue from current point if previous movement is not finished yet
+        let from = state.movement.as_ref().map_or(from, |movement| movement.point());
+        state.stop_movement();
+
+        let cursor_state = self.state.clone();
+        let drawing_area = drawing_area.clone();
+        let update_handler = clock.connect_update(move |clock| {
+            cursor_state.borrow_mut().movement_step(clock.get_frame_time());
+            drawing_area.queue_draw();
+        });
+        clock.begin_updating();
+
+        state.movement = Some(Movement {
+            from,
+            to,
+            start_time: None,
+            progress: 0.0,
+            clock,
+            update_handler,
+        });
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.state.borrow().movement.is_some()
+    }
+
+    /// Draw moving cursor and its trail at current animation point
+    pub fn draw_movement(
+        &self,
+        ctx: &cairo::Context,
+        font_ctx: &render::Context,
+        mode: &mode::Mode,
+        color_model: &ColorModel,
+    ) {
+        let (point, trail) = {
+            let state = self.state.borrow();
+            match (state.animation.as_ref(), state.movement.as_ref()) {
+                (Some(animation), Some(movement)) => {
+                    let trail = if animation.trail {
+                        trail_points(movement.from, movement.to, movement.progress)
+                    } else {
+                        Vec::new()
+                    };
+                    (movement.point(), trail)
+                }
+                _ => return,
+            }
+        };
+
+        let bg = &color_model.bg_color;
+        let color = color_model
+            .theme
+            .cursor(mode)
+            .bg
+            .clone()
+            .unwrap_or_else(|| Color(1.0 - bg.0, 1.0 - bg.1, 1.0 - bg.2));
+        let cell_metrics = font_ctx.cell_metrics();
+
+        for ((x, line_y), alpha) in trail {
+            let (y, width, height) = cursor_rect(mode, cell_metrics, line_y, false);
+            ctx.set_source_rgba(color.0, color.1, color.2, alpha);
+            ctx.rectangle(x, y, width, height);
+            ctx.fill();
+        }
+
+        ctx.move_to(point.0, point.1);
+        self.draw(ctx, font_ctx, mode, point.1, false, color_model, bg);
     }
 
     pub fn reset_state(&mut self) {
@@ -113,6 +370,10 @@ impl Cursor {
         self.start();
     }
 
+    /// Draw cursor at current point.
+    ///
+    /// Returns color of the text under cursor when cursor highlight group defines it
+    /// and the whole cell is covered by cursor.
     pub fn draw(
         &self,
         ctx: &cairo::Context,
@@ -120,25 +381,40 @@ impl Cursor {
         mode: &mode::Mode,
         line_y: f64,
         double_width: bool,
+        color_model: &ColorModel,
         bg: &Color,
-    ) {
+    ) -> Option<Color> {
 
         let state = self.state.borrow();
 
         if state.anim_phase == AnimPhase::Busy {
-            return;
+            return None;
         }
 
+        let hl = color_model.theme.cursor(mode);
+
         let current_point = ctx.get_current_point();
-        ctx.set_source_rgba(1.0 - bg.0, 1.0 - bg.1, 1.0 - bg.2, 0.6 * state.alpha.0);
+        if let Some(ref cursor_bg) = hl.bg {
+            ctx.set_source_rgba(cursor_bg.0, cursor_bg.1, cursor_bg.2, state.alpha.0);
+        } else {
+            ctx.set_source_rgba(1.0 - bg.0, 1.0 - bg.1, 1.0 - bg.2, 0.6 * state.alpha.0);
+        }
 
-        let (y, width, height) = cursor_rect(mode, font_ctx.cell_metrics(), line_y, double_width);
+        let cell_metrics = font_ctx.cell_metrics();
+        let (y, width, height) = cursor_rect(mode, cell_metrics, line_y, double_width);
 
         ctx.rectangle(current_point.0, y, width, height);
         if state.anim_phase == AnimPhase::NoFocus {
             ctx.stroke();
+            return None;
+        }
+        ctx.fill();
+
+        let is_block = height >= cell_metrics.line_height && width >= cell_metrics.char_width;
+        if is_block && hl.bg.is_some() && state.alpha.0 > 0.5 {
+            hl.fg.clone()
         } else {
-            ctx.fill();
+            None
         }
     }
 }
@@ -204,6 +480,27 @@ fn cursor_rect(
         (line_y, cursor_width, line_height)
     }
 }
+
+/// Point between `from` and `to` for given movement progress
+fn movement_point(from: (f64, f64), to: (f64, f64), progress: f64) -> (f64, f64) {
+    (
+        from.0 + (to.0 - from.0) * progress,
+        from.1 + (to.1 - from.1) * progress,
+    )
+}
+
+/// Trail points behind moving cursor with their opacity, nearest first
+fn trail_points(from: (f64, f64), to: (f64, f64), progress: f64) -> Vec<((f64, f64), f64)> {
+    (1..TRAIL_LENGTH + 1)
+        .map(|i| (i, progress - i as f64 * TRAIL_STEP))
+        .take_while(|&(_, trail_progress)| trail_progress > 0.0)
+        .map(|(i, trail_progress)| {
+            let alpha = 0.5 * (1.0 - i as f64 / (TRAIL_LENGTH + 1) as f64);
+            (movement_point(from, to, trail_progress), alpha)
+        })
+        .collect()
+}
+
 fn anim_step(state: &Arc<UiMutex<State>>) -> glib::Continue {
     let mut mut_state = state.borrow_mut();
 
@@ -216,7 +513,7 @@ fn anim_step(state: &Arc<UiMutex<State>>) -> glib::Continue {
             if !mut_state.alpha.hide(0.3) {
                 mut_state.anim_phase = AnimPhase::Hidden;
 
-                Some(300)
+                mut_state.blink.as_ref().map(|blink| blink.off)
             } else {
                 None
             }
@@ -230,7 +527,7 @@ fn anim_step(state: &Arc<UiMutex<State>>) -> glib::Continue {
             if !mut_state.alpha.show(0.3) {
                 mut_state.anim_phase = AnimPhase::Shown;
 
-                Some(500)
+                mut_state.blink.as_ref().map(|blink| blink.on)
             } else {
                 None
             }
@@ -258,9 +555,11 @@ fn anim_step(state: &Arc<UiMutex<State>>) -> glib::Continue {
 
 impl Drop for Cursor {
     fn drop(&mut self) {
-        if let Some(timer_id) = self.state.borrow_mut().timer.take() {
+        let mut state = self.state.borrow_mut();
+        if let Some(timer_id) = state.timer.take() {
             glib::source_remove(timer_id);
         }
+        state.stop_movement();
     }
 }
 
@@ -316,6 +615,95 @@ mod tests {
         assert_eq!(line_height / 4.0, height);
     }
 
+    #[test]
+    fn test_blink_from_mode() {
+        let mut mode = mode::Mode::new();
+        assert_eq!(
+            Some(Blink {
+                wait: 500,
+                on: 500,
+                off: 300,
+            }),
+            Blink::from_mode(&mode)
+        );
+
+        let mode_info = nvim::ModeInfo::new(&vec![
+            (From::from("blinkwait"), From::from(700)),
+            (From::from("blinkon"), From::from(400)),
+            (From::from("blinkoff"), From::from(250)),
+        ]);
+        mode.set_info(true, vec![mode_info.unwrap()]);
+        assert_eq!(
+            Some(Blink {
+                wait: 700,
+                on: 400,
+                off: 250,
+            }),
+            Blink::from_mode(&mode)
+        );
+
+        let mode_info = nvim::ModeInfo::new(&vec![
+            (From::from("blinkwait"), From::from(700)),
+            (From::from("blinkon"), From::from(0)),
+            (From::from("blinkoff"), From::from(250)),
+        ]);
+        mode.set_info(true, vec![mode_info.unwrap()]);
+        assert_eq!(None, Blink::from_mode(&mode));
+    }
+
+    #[test]
+    fn test_easing() {
+        for easing in &[Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(0.0, easing.apply(0.0));
+            assert_eq!(1.0, easing.apply(1.0));
+            assert_eq!(1.0, easing.apply(1.5));
+        }
+        assert_eq!(0.5, Easing::EaseInOut.apply(0.5));
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert_eq!(None, Easing::from_name("bounce"));
+    }
+
+    #[test]
+    fn test_animation_new() {
+        assert_eq!(Ok(None), Animation::new(0, None, true));
+        assert_eq!(
+            Ok(Some(Animation {
+                duration: 150,
+                easing: Easing::Linear,
+                trail: true,
+            })),
+            Animation::new(150, Some("linear"), true)
+        );
+        assert!(Animation::new(150, Some("bounce"), false).is_err());
+    }
+
+    #[test]
+    fn test_movement_cursor_rect() {
+        let mode = mode::Mode::new();
+        let cell_metrics = CellMetrics::new_hw(30.0, 10.0);
+
+        let (x, line_y) = movement_point((0.0, 0.0), (100.0, 300.0), 0.5);
+        assert_eq!(50.0, x);
+
+        let (y, width, height) = cursor_rect(&mode, &cell_metrics, line_y, false);
+        assert_eq!(150.0, y);
+        assert_eq!(10.0, width);
+        assert_eq!(30.0, height);
+    }
+
+    #[test]
+    fn test_trail_points() {
+        assert!(trail_points((0.0, 0.0), (100.0, 0.0), 0.0).is_empty());
+
+        let trail = trail_points((0.0, 0.0), (100.0, 0.0), 1.0);
+        assert_eq!(TRAIL_LENGTH, trail.len());
+        assert!((trail[0].0).0 > (trail[1].0).0);
+        assert!(trail[0].1 > trail[1].1);
+
+        let trail = trail_points((0.0, 0.0), (100.0, 0.0), 0.1);
+        assert_eq!(1, trail.len());
+    }
+
     #[test]
     fn test_cursor_rect_vertical() {
         let mut mode = mode::Mode::new();
@@ -30,3 +30,55 @@ fn get_xdg_config_dir() -> Result<PathBuf, String> {
     home_dir.push(".config");
     Ok(home_dir)
 }
+
+/// Cache directory of NeovimGtk, for data that can be downloaded again.
+pub fn get_app_cache_dir() -> Result<PathBuf, String> {
+    let mut cache_dir = get_xdg_cache_dir()?;
+
+    cache_dir.push("nvim-gtk");
+
+    Ok(cache_dir)
+}
+
+fn get_xdg_cache_dir() -> Result<PathBuf, String> {
+    if let Ok(cache_path) = std::env::var("XDG_CACHE_HOME") {
+        return Ok(PathBuf::from(cache_path));
+    }
+
+    let mut home_dir = std::env::home_dir().ok_or(
+        "Impossible to get your home dir!",
+    )?;
+    home_dir.push(".cache");
+    Ok(home_dir)
+}
+
+/// Default config directory of nvim, `stdpath('config')` of a running nvim can differ.
+pub fn get_nvim_config_dir() -> Result<PathBuf, String> {
+    let mut config_dir = get_xdg_config_dir()?;
+
+    config_dir.push("nvim");
+
+    Ok(config_dir)
+}
+
+/// Default data directory of nvim, `stdpath('data')` of a running nvim can differ.
+pub fn get_nvim_data_dir() -> Result<PathBuf, String> {
+    let mut data_dir = get_xdg_data_dir()?;
+
+    data_dir.push("nvim");
+
+    Ok(data_dir)
+}
+
+fn get_xdg_data_dir() -> Result<PathBuf, String> {
+    if let Ok(data_path) = std::env::var("XDG_DATA_HOME") {
+        return Ok(PathBuf::from(data_path));
+    }
+
+    let mut home_dir = std::env::home_dir().ok_or(
+        "Impossible to get your home dir!",
+    )?;
+    home_dir.push(".local");
+    home_dir.push("share");
+    Ok(home_dir)
+}
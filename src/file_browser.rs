@@ -1,40 +1,67 @@
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::io;
 use std::fs;
-use std::fs::DirEntry;
 use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::ops::Deref;
+use std::sync::Arc;
+use std::thread;
 
+use gdk;
 use gio;
+use glib;
 use gio::prelude::*;
 use gtk;
 use gtk::MenuExt;
 use gtk::prelude::*;
 
-use neovim_lib::{NeovimApi, NeovimApiAsync};
+use neovim_lib::{CallError, NeovimApi, NeovimApiAsync, Value};
 
-use misc::escape_filename;
+use file_list::{self, is_visible};
+use fuzzy;
+use git;
+use misc::{decode_uri, escape_filename};
 use nvim::{ErrorReport, NeovimClient, NeovimRef};
 use shell;
+use ui::UiMutex;
 
 const ICON_FOLDER_CLOSED: &str = "folder-symbolic";
 const ICON_FOLDER_OPEN: &str = "folder-open-symbolic";
 const ICON_FILE: &str = "text-x-generic-symbolic";
 
+const MAX_SEARCH_RESULTS: usize = 200;
+
 struct Components {
     dir_list_model: gtk::TreeStore,
     dir_list: gtk::ComboBox,
     context_menu: gtk::Menu,
     show_hidden_checkbox: gtk::CheckMenuItem,
+    hide_ignored_checkbox: gtk::CheckMenuItem,
+    stack: gtk::Stack,
+    search_entry: gtk::SearchEntry,
+    search_view: gtk::TreeView,
+    search_store: gtk::ListStore,
     cd_action: gio::SimpleAction,
+    /// Actions that need a file to be clicked.
+    open_actions: Vec<(OpenMode, gio::SimpleAction)>,
+    /// Actions that need a file or directory to be clicked.
+    path_actions: Vec<gio::SimpleAction>,
 }
 
 struct State {
     current_dir: String,
     show_hidden: bool,
+    hide_ignored: bool,
     selected_path: Option<String>,
+    git_status: Option<git::Status>,
+    git_timer: Option<glib::SourceId>,
+    /// Files of the whole tree relative to `current_dir`, read when searching.
+    search_files: Option<Arc<Vec<String>>>,
+    search_loading: bool,
+    /// Incremented for each search, so results of outdated searches are dropped.
+    search_id: usize,
 }
 
 pub struct FileBrowserWidget {
@@ -44,6 +71,7 @@ pub struct FileBrowserWidget {
     nvim: Option<Rc<NeovimClient>>,
     comps: Components,
     state: Rc<RefCell<State>>,
+    monitors: Rc<DirMonitors>,
 }
 
 impl Deref for FileBrowserWidget {
@@ -54,18 +82,59 @@ impl Deref for FileBrowserWidget {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum FileType {
     File,
     Dir,
 }
 
+/// How a file is opened in Neovim.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OpenMode {
+    Edit,
+    Split,
+    VSplit,
+    Tab,
+    /// Opens the file in the preview window, keeping the focus in the current window.
+    Preview,
+}
+
+impl OpenMode {
+    /// Mode for clicking a file with the given modifiers held.
+    fn from_modifiers(state: gdk::ModifierType) -> Self {
+        if state.contains(gdk::ModifierType::MOD1_MASK) {
+            OpenMode::Preview
+        } else if state.contains(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK) {
+            OpenMode::Tab
+        } else if state.contains(gdk::ModifierType::CONTROL_MASK) {
+            OpenMode::VSplit
+        } else if state.contains(gdk::ModifierType::SHIFT_MASK) {
+            OpenMode::Split
+        } else {
+            OpenMode::Edit
+        }
+    }
+
+    pub fn command(&self, file_path: &str) -> String {
+        let cmd = match *self {
+            OpenMode::Edit => "e",
+            OpenMode::Split => "split",
+            OpenMode::VSplit => "vsplit",
+            OpenMode::Tab => "tabedit",
+            OpenMode::Preview => "pedit",
+        };
+        format!(":{} {}", cmd, escape_filename(file_path))
+    }
+}
+
 #[allow(dead_code)]
 enum Column {
     Filename,
     Path,
     FileType,
     IconName,
+    StatusIcon,
+    StatusColor,
 }
 
 impl FileBrowserWidget {
@@ -80,6 +149,13 @@ impl FileBrowserWidget {
         let show_hidden_checkbox: gtk::CheckMenuItem = builder
             .get_object("file_browser_show_hidden_checkbox")
             .unwrap();
+        let hide_ignored_checkbox: gtk::CheckMenuItem = builder
+            .get_object("file_browser_hide_ignored_checkbox")
+            .unwrap();
+        let stack: gtk::Stack = builder.get_object("file_browser_stack").unwrap();
+        let search_entry: gtk::SearchEntry = builder.get_object("file_browser_search_entry").unwrap();
+        let search_view: gtk::TreeView = builder.get_object("file_browser_search_view").unwrap();
+        let search_store: gtk::ListStore = builder.get_object("file_browser_search_store").unwrap();
 
         let file_browser = FileBrowserWidget {
             store,
@@ -91,13 +167,37 @@ impl FileBrowserWidget {
                 dir_list,
                 context_menu,
                 show_hidden_checkbox,
+                hide_ignored_checkbox,
+                stack,
+                search_entry,
+                search_view,
+                search_store,
                 cd_action: gio::SimpleAction::new("cd", None),
+                open_actions: vec![
+                    (OpenMode::Split, gio::SimpleAction::new("open-split", None)),
+                    (OpenMode::VSplit, gio::SimpleAction::new("open-vsplit", None)),
+                    (OpenMode::Tab, gio::SimpleAction::new("open-tab", None)),
+                    (OpenMode::Preview, gio::SimpleAction::new("preview", None)),
+                ],
+                path_actions: vec![
+                    gio::SimpleAction::new("rename", None),
+                    gio::SimpleAction::new("duplicate", None),
+                    gio::SimpleAction::new("trash", None),
+                    gio::SimpleAction::new("copy-path", None),
+                ],
             },
             state: Rc::new(RefCell::new(State {
                 current_dir: "".to_owned(),
                 show_hidden: false,
+                hide_ignored: false,
                 selected_path: None,
+                git_status: None,
+                git_timer: None,
+                search_files: None,
+                search_loading: false,
+                search_id: 0,
             })),
+            monitors: Rc::new(DirMonitors::new()),
         };
         file_browser
     }
@@ -106,7 +206,8 @@ impl FileBrowserWidget {
         self.nvim.as_ref().unwrap().nvim()
     }
 
-    pub fn init(&mut self, shell_state: &shell::State) {
+    pub fn init(&mut self, shell_state_ref: &Arc<UiMutex<shell::State>>) {
+        let shell_state = shell_state_ref.borrow();
         // Initialize values.
         let nvim = shell_state.nvim_clone();
         self.nvim = Some(nvim);
@@ -116,11 +217,12 @@ impl FileBrowserWidget {
         }
 
         // Populate tree.
-        tree_reload(&self.store, &self.state.borrow());
+        tree_reload(&self.store, &self.state, &self.monitors);
 
         let store = &self.store;
         let state_ref = &self.state;
-        self.tree.connect_test_expand_row(clone!(store, state_ref => move |_, iter, _| {
+        let monitors = &self.monitors;
+        self.tree.connect_test_expand_row(clone!(store, state_ref, monitors => move |_, iter, _| {
             store.set(&iter, &[Column::IconName as u32], &[&ICON_FOLDER_OPEN]);
             // We cannot recursively populate all directories. Instead, we have prepared a single
             // empty child entry for all non-empty directories, so the row will be expandable. Now,
@@ -133,6 +235,7 @@ impl FileBrowserWidget {
                     let dir_value = store.get_value(&iter, Column::Path as i32);
                     if let Some(dir) = dir_value.get() {
                         populate_tree_nodes(&store, &state, dir, Some(iter));
+                        monitors.watch(&store, &state_ref, dir);
                     }
                 } else {
                     // This directory is already populated, i.e. it has been expanded and collapsed
@@ -159,8 +262,9 @@ impl FileBrowserWidget {
 
         // Further initialization.
         self.init_actions();
-        self.init_subscriptions(shell_state);
+        self.init_subscriptions(&shell_state);
         self.connect_events();
+        self.connect_search(shell_state_ref);
     }
 
     fn init_actions(&self) {
@@ -168,11 +272,12 @@ impl FileBrowserWidget {
 
         let store = &self.store;
         let state_ref = &self.state;
+        let monitors = &self.monitors;
         let nvim_ref = self.nvim.as_ref().unwrap();
 
         let reload_action = gio::SimpleAction::new("reload", None);
-        reload_action.connect_activate(clone!(store, state_ref => move |_, _| {
-            tree_reload(&store, &state_ref.borrow());
+        reload_action.connect_activate(clone!(store, state_ref, monitors => move |_, _| {
+            tree_reload(&store, &state_ref, &monitors);
         }));
         actions.add_action(&reload_action);
 
@@ -187,6 +292,94 @@ impl FileBrowserWidget {
         }));
         actions.add_action(cd_action);
 
+        for &(mode, ref action) in &self.comps.open_actions {
+            action.connect_activate(clone!(state_ref, nvim_ref => move |_, _| {
+                let state = state_ref.borrow();
+                if let Some(ref path) = state.selected_path {
+                    open_path(&nvim_ref, &state.current_dir, path, mode);
+                }
+            }));
+            actions.add_action(action);
+        }
+
+        let tree = &self.tree;
+
+        let new_file_action = gio::SimpleAction::new("new-file", None);
+        new_file_action.connect_activate(clone!(tree, state_ref, nvim_ref => move |_, _| {
+            let dir = target_dir(&state_ref.borrow());
+            if let Some(name) = ask_name(&tree, "New File", "") {
+                let path = dir.join(&name);
+                let created = fs::OpenOptions::new().write(true).create_new(true).open(&path);
+                match created {
+                    Ok(_) => {
+                        let path = path.to_string_lossy();
+                        nvim_ref.nvim().unwrap()
+                            .command_async(&format!(":e {}", escape_filename(&path)))
+                            .cb(|r| r.report_err())
+                            .call();
+                    }
+                    Err(err) => show_error(&tree, &format!("Couldn't create {}: {}", name, err)),
+                }
+            }
+        }));
+        actions.add_action(&new_file_action);
+
+        let new_folder_action = gio::SimpleAction::new("new-folder", None);
+        new_folder_action.connect_activate(clone!(tree, state_ref => move |_, _| {
+            let dir = target_dir(&state_ref.borrow());
+            if let Some(name) = ask_name(&tree, "New Folder", "") {
+                if let Err(err) = fs::create_dir(dir.join(&name)) {
+                    show_error(&tree, &format!("Couldn't create {}: {}", name, err));
+                }
+            }
+        }));
+        actions.add_action(&new_folder_action);
+
+        let rename_action = &self.comps.path_actions[0];
+        rename_action.connect_activate(clone!(tree, state_ref, nvim_ref => move |_, _| {
+            let path = match state_ref.borrow().selected_path {
+                Some(ref path) => PathBuf::from(path),
+                None => return,
+            };
+            let old_name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+            if let Some(name) = ask_name(&tree, "Rename", &old_name.unwrap_or_default()) {
+                if let Err(err) = move_path(&nvim_ref, &path, &path.with_file_name(&name)) {
+                    show_error(&tree, &err);
+                }
+            }
+        }));
+
+        let duplicate_action = &self.comps.path_actions[1];
+        duplicate_action.connect_activate(clone!(tree, state_ref => move |_, _| {
+            if let Some(ref path) = state_ref.borrow().selected_path {
+                let path = Path::new(path);
+                let copy_path = duplicate_path(path);
+                if let Err(err) = copy_recursive(path, &copy_path) {
+                    show_error(&tree, &format!("Couldn't duplicate {}: {}", path.display(), err));
+                }
+            }
+        }));
+
+        let trash_action = &self.comps.path_actions[2];
+        trash_action.connect_activate(clone!(tree, state_ref => move |_, _| {
+            if let Some(ref path) = state_ref.borrow().selected_path {
+                if let Err(err) = gio::File::new_for_path(path).trash(None) {
+                    show_error(&tree, &format!("Couldn't move {} to trash: {}", path, err));
+                }
+            }
+        }));
+
+        let copy_path_action = &self.comps.path_actions[3];
+        copy_path_action.connect_activate(clone!(state_ref => move |_, _| {
+            if let Some(ref path) = state_ref.borrow().selected_path {
+                gtk::Clipboard::get(&gdk::Atom::intern("CLIPBOARD")).set_text(path);
+            }
+        }));
+
+        for action in &self.comps.path_actions {
+            actions.add_action(action);
+        }
+
         self.comps
             .context_menu
             .insert_action_group("filebrowser", &actions);
@@ -196,17 +389,18 @@ impl FileBrowserWidget {
         // Always set the current working directory as the root of the file browser.
         let store = &self.store;
         let state_ref = &self.state;
+        let monitors = &self.monitors;
         let dir_list_model = &self.comps.dir_list_model;
         let dir_list = &self.comps.dir_list;
         shell_state.subscribe(
             "DirChanged",
             &["getcwd()"],
-            clone!(store, state_ref, dir_list_model, dir_list => move |args| {
+            clone!(store, state_ref, monitors, dir_list_model, dir_list => move |args| {
                 let dir = args.into_iter().next().unwrap();
                 if dir != state_ref.borrow().current_dir {
                     state_ref.borrow_mut().current_dir = dir.to_owned();
                     update_dir_list(&dir, &dir_list_model, &dir_list);
-                    tree_reload(&store, &state_ref.borrow());
+                    tree_reload(&store, &state_ref, &monitors);
                 }
             }),
         );
@@ -232,6 +426,15 @@ impl FileBrowserWidget {
             }),
         );
         shell_state.run_now(&subscription);
+
+        // Written buffers change the git status of their files.
+        shell_state.subscribe(
+            "BufWritePost",
+            &[],
+            clone!(store, state_ref => move |_| {
+                schedule_git_status(&store, &state_ref);
+            }),
+        );
     }
 
     fn connect_events(&self) {
@@ -258,21 +461,9 @@ impl FileBrowserWidget {
                 }
             } else {
                 // FileType::File
-                let cwd = &state_ref.borrow().current_dir;
-                let cwd = Path::new(cwd);
-                let file_path = if let Some(rel_path) = Path::new(&file_path)
-                    .strip_prefix(&cwd)
-                    .ok()
-                    .and_then(|p| p.to_str())
-                {
-                    rel_path
-                } else {
-                    &file_path
-                };
-                let file_path = escape_filename(file_path);
-                nvim_ref.nvim().unwrap().command_async(&format!(":e {}", file_path))
-                    .cb(|r| r.report_err())
-                    .call();
+                let mode = gtk::get_current_event_state()
+                    .map_or(OpenMode::Edit, OpenMode::from_modifiers);
+                open_path(&nvim_ref, &state_ref.borrow().current_dir, &file_path, mode);
             }
         }));
 
@@ -294,8 +485,10 @@ impl FileBrowserWidget {
 
         let context_menu = &self.comps.context_menu;
         let cd_action = &self.comps.cd_action;
+        let path_actions = &self.comps.path_actions;
+        let open_actions = &self.comps.open_actions;
         self.tree.connect_button_press_event(
-            clone!(store, state_ref, context_menu, cd_action => move |tree, ev_btn| {
+            clone!(store, state_ref, context_menu, cd_action, path_actions, open_actions => move |tree, ev_btn| {
                 // Open context menu on right click.
                 if ev_btn.get_button() == 3 {
                     context_menu.popup_at_pointer(&**ev_btn);
@@ -313,52 +506,611 @@ impl FileBrowserWidget {
                         });
                     // Enable the "Go To Directory" action only if the user clicked on a folder.
                     cd_action.set_enabled(file_type == Some(FileType::Dir as u8));
+                    for &(_, ref action) in &open_actions {
+                        action.set_enabled(file_type == Some(FileType::File as u8));
+                    }
                     let path = iter
                         .and_then(|iter| {
                             store
                                 .get_value(&iter, Column::Path as i32)
                                 .get::<String>()
                         });
+                    for action in &path_actions {
+                        action.set_enabled(path.is_some());
+                    }
                     state_ref.borrow_mut().selected_path = path;
                 }
                 Inhibit(false)
             }),
         );
 
+        // Move files by dragging them onto a folder.
+        // Files can also be dropped onto the editor, which opens them there (see `Shell::init`).
+        let source_targets = vec![
+            gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::SAME_APP, 0),
+        ];
+        let dest_targets = vec![
+            gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::SAME_WIDGET, 0),
+        ];
+        self.tree.drag_source_set(
+            gdk::ModifierType::BUTTON1_MASK,
+            &source_targets,
+            gdk::DragAction::MOVE | gdk::DragAction::COPY,
+        );
+        self.tree
+            .drag_dest_set(gtk::DestDefaults::ALL, &dest_targets, gdk::DragAction::MOVE);
+        self.tree.connect_drag_data_get(clone!(store => move |tree, _, selection, _, _| {
+            if let Some((_, iter)) = tree.get_selection().get_selected() {
+                if let Some(path) = store.get_value(&iter, Column::Path as i32).get::<String>() {
+                    let uri = gio::File::new_for_path(&path).get_uri();
+                    selection.set_uris(&[uri.as_str()]);
+                }
+            }
+        }));
+        self.tree.connect_drag_data_received(
+            clone!(store, state_ref, nvim_ref => move |tree, _, x, y, selection, _, _| {
+                let dest = tree
+                    .get_dest_row_at_pos(x, y)
+                    .and_then(|(path, position)| path.map(|path| (path, position)))
+                    .and_then(|(path, position)| store.get_iter(&path).map(|iter| (iter, position)));
+                let dest_dir = match dest {
+                    Some((iter, position)) => {
+                        let path = store.get_value(&iter, Column::Path as i32).get::<String>();
+                        let file_type = store.get_value(&iter, Column::FileType as i32).get::<u8>();
+                        let into = position == gtk::TreeViewDropPosition::IntoOrBefore
+                            || position == gtk::TreeViewDropPosition::IntoOrAfter;
+                        match path {
+                            Some(ref path) if into && file_type == Some(FileType::Dir as u8) => {
+                                PathBuf::from(path)
+                            }
+                            Some(ref path) => Path::new(path)
+                                .parent()
+                                .map(Path::to_path_buf)
+                                .unwrap_or_else(|| PathBuf::from(&state_ref.borrow().current_dir)),
+                            None => PathBuf::from(&state_ref.borrow().current_dir),
+                        }
+                    }
+                    None => PathBuf::from(&state_ref.borrow().current_dir),
+                };
+
+                for source in selection.get_uris().iter().filter_map(|uri| decode_uri(uri)) {
+                    let source = PathBuf::from(source);
+                    if let Some(name) = source.file_name() {
+                        let target = dest_dir.join(name);
+                        if target == source || dest_dir.starts_with(&source) {
+                            continue;
+                        }
+                        if let Err(err) = move_path(&nvim_ref, &source, &target) {
+                            show_error(tree, &err);
+                        }
+                    }
+                }
+            }),
+        );
+
         // Show / hide hidden files when corresponding menu item is toggled.
-        self.comps.show_hidden_checkbox.connect_toggled(clone!(state_ref, store => move |ev| {
-            let mut state = state_ref.borrow_mut();
-            state.show_hidden = ev.get_active();
-            tree_reload(&store, &state);
+        let monitors = &self.monitors;
+        self.comps.show_hidden_checkbox.connect_toggled(clone!(state_ref, store, monitors => move |ev| {
+            state_ref.borrow_mut().show_hidden = ev.get_active();
+            tree_reload(&store, &state_ref, &monitors);
+        }));
+
+        // Show / hide files ignored by git when corresponding menu item is toggled.
+        self.comps.hide_ignored_checkbox.connect_toggled(clone!(state_ref, store, monitors => move |ev| {
+            state_ref.borrow_mut().hide_ignored = ev.get_active();
+            tree_reload(&store, &state_ref, &monitors);
+        }));
+    }
+
+    fn connect_search(&self, shell_state_ref: &Arc<UiMutex<shell::State>>) {
+        let state_ref = &self.state;
+        let stack = &self.comps.stack;
+        let search_entry = &self.comps.search_entry;
+        let search_view = &self.comps.search_view;
+        let search_store = &self.comps.search_store;
+
+        search_entry.connect_search_changed(clone!(state_ref, stack, search_store => move |entry| {
+            update_search(&state_ref, entry, &stack, &search_store);
+        }));
+
+        // Select results with the arrow keys while typing.
+        search_entry.connect_key_press_event(clone!(search_view, search_store => move |_, ev| {
+            let offset = match ev.get_keyval() {
+                gdk::enums::key::Down => 1,
+                gdk::enums::key::Up => -1,
+                _ => return Inhibit(false),
+            };
+            file_list::move_selection(&search_view, &search_store, offset);
+            Inhibit(true)
+        }));
+
+        // Open the selected or the best result on Enter.
+        search_entry.connect_activate(clone!(shell_state_ref, search_view, search_store => move |entry| {
+            let iter = search_view
+                .get_selection()
+                .get_selected()
+                .map(|(_, iter)| iter)
+                .or_else(|| search_store.get_iter_first());
+            if let Some(iter) = iter {
+                open_search_result(&shell_state_ref, &search_store, &iter);
+                entry.set_text("");
+            }
         }));
+
+        search_view.connect_row_activated(
+            clone!(shell_state_ref, search_entry, search_store => move |_, path, _| {
+                if let Some(iter) = search_store.get_iter(path) {
+                    open_search_result(&shell_state_ref, &search_store, &iter);
+                    search_entry.set_text("");
+                }
+            }),
+        );
+
+        search_entry.connect_stop_search(|entry| entry.set_text(""));
+    }
+}
+
+/// Shows the search results for the text of `entry`, or the tree if it is empty.
+///
+/// The files are read in background on first use.
+fn update_search(
+    state_ref: &Rc<RefCell<State>>,
+    entry: &gtk::SearchEntry,
+    stack: &gtk::Stack,
+    search_store: &gtk::ListStore,
+) {
+    let pattern = entry.get_text().unwrap_or_default();
+    if pattern.is_empty() {
+        state_ref.borrow_mut().search_id += 1;
+        stack.set_visible_child_name("tree");
+        search_store.clear();
+        return;
+    }
+    stack.set_visible_child_name("search");
+
+    let files = state_ref.borrow().search_files.clone();
+    match files {
+        Some(files) => rank_search_results(state_ref, files, pattern, search_store),
+        None => {
+            state_ref.borrow_mut().search_id += 1;
+            load_search_files(state_ref, entry, stack, search_store);
+        }
+    }
+}
+
+/// Ranks the files in background, as it takes a while in large trees.
+fn rank_search_results(
+    state_ref: &Rc<RefCell<State>>,
+    files: Arc<Vec<String>>,
+    pattern: String,
+    search_store: &gtk::ListStore,
+) {
+    let search_id = {
+        let mut state = state_ref.borrow_mut();
+        state.search_id += 1;
+        state.search_id
+    };
+
+    let state_ref = UiMutex::new(state_ref.clone());
+    let search_store = UiMutex::new(search_store.clone());
+    thread::spawn(move || {
+        let results: Vec<_> = fuzzy::rank(&pattern, &files, MAX_SEARCH_RESULTS)
+            .into_iter()
+            .map(|(file, m)| (fuzzy::highlight_markup(file, &m.positions), file.to_owned()))
+            .collect();
+        let mut results = Some(results);
+
+        glib::idle_add(move || {
+            // Otherwise the pattern changed meanwhile.
+            if state_ref.borrow().borrow().search_id == search_id {
+                show_search_results(&search_store.borrow(), results.take().unwrap_or_default());
+            }
+            glib::Continue(false)
+        });
+    });
+}
+
+fn show_search_results(search_store: &gtk::ListStore, results: Vec<(String, String)>) {
+    search_store.clear();
+    for (markup, file) in results {
+        search_store.insert_with_values(None, &[0, 1], &[&markup, &file]);
+    }
+}
+
+fn load_search_files(
+    state_ref: &Rc<RefCell<State>>,
+    entry: &gtk::SearchEntry,
+    stack: &gtk::Stack,
+    search_store: &gtk::ListStore,
+) {
+    let (dir, show_hidden) = {
+        let mut state = state_ref.borrow_mut();
+        if state.search_loading {
+            return;
+        }
+        state.search_loading = true;
+        (state.current_dir.clone(), state.show_hidden)
+    };
+
+    let state_ref = UiMutex::new(state_ref.clone());
+    let widgets = UiMutex::new((entry.clone(), stack.clone(), search_store.clone()));
+    thread::spawn(move || {
+        let mut files = Some(file_list::project_files(Path::new(&dir), show_hidden));
+
+        glib::idle_add(move || {
+            let state_ref = state_ref.borrow();
+            {
+                let mut state = state_ref.borrow_mut();
+                state.search_loading = false;
+                // Otherwise the root has changed while reading and the files are outdated.
+                if state.current_dir == dir {
+                    state.search_files = files.take().map(Arc::new);
+                }
+            }
+            let widgets = widgets.borrow();
+            let (ref entry, ref stack, ref search_store) = *widgets;
+            update_search(&state_ref, entry, stack, search_store);
+            glib::Continue(false)
+        });
+    });
+}
+
+fn open_search_result(
+    shell_state_ref: &Arc<UiMutex<shell::State>>,
+    search_store: &gtk::ListStore,
+    iter: &gtk::TreeIter,
+) {
+    if let Some(file) = search_store.get_value(iter, 1).get::<String>() {
+        shell_state_ref.borrow().open_file(&escape_filename(&file));
+    }
+}
+
+/// Opens a file, relative to the current directory if possible.
+fn open_path(nvim_ref: &NeovimClient, cwd: &str, file_path: &str, mode: OpenMode) {
+    let file_path = Path::new(file_path)
+        .strip_prefix(cwd)
+        .ok()
+        .and_then(Path::to_str)
+        .unwrap_or(file_path);
+    if let Some(mut nvim) = nvim_ref.nvim() {
+        nvim.command_async(&mode.command(file_path))
+            .cb(|r| r.report_err())
+            .call();
     }
 }
 
 /// Compare function for dir entries.
 ///
 /// Sorts directories above files.
-fn cmp_dirs_first(lhs: &DirEntry, rhs: &DirEntry) -> io::Result<Ordering> {
-    let lhs_metadata = fs::metadata(lhs.path())?;
-    let rhs_metadata = fs::metadata(rhs.path())?;
-    if lhs_metadata.is_dir() == rhs_metadata.is_dir() {
-        Ok(lhs.path()
-            .to_string_lossy()
-            .to_lowercase()
-            .cmp(&rhs.path().to_string_lossy().to_lowercase()))
+fn cmp_dirs_first(lhs: &Entry, rhs: &Entry) -> Ordering {
+    if lhs.file_type == rhs.file_type {
+        lhs.filename.to_lowercase().cmp(&rhs.filename.to_lowercase())
+    } else if lhs.file_type == FileType::Dir {
+        Ordering::Less
     } else {
-        if lhs_metadata.is_dir() {
-            Ok(Ordering::Less)
-        } else {
-            Ok(Ordering::Greater)
-        }
+        Ordering::Greater
     }
 }
 
 /// Clears an repopulate the entire tree.
-fn tree_reload(store: &gtk::TreeStore, state: &State) {
-    let dir = &state.current_dir;
-    store.clear();
-    populate_tree_nodes(store, state, dir, None);
+///
+/// Directory monitors are reset, so only the root directory is watched afterwards.
+fn tree_reload(store: &gtk::TreeStore, state_ref: &Rc<RefCell<State>>, monitors: &DirMonitors) {
+    state_ref.borrow_mut().search_files = None;
+    {
+        let state = state_ref.borrow();
+        let dir = &state.current_dir;
+        store.clear();
+        populate_tree_nodes(store, &state, dir, None);
+
+        monitors.clear();
+        monitors.watch(store, state_ref, dir);
+    }
+    schedule_git_status(store, state_ref);
+}
+
+/// Refreshes the git status of the tree shortly, coalescing bursts of changes.
+fn schedule_git_status(store: &gtk::TreeStore, state_ref: &Rc<RefCell<State>>) {
+    let mut state = state_ref.borrow_mut();
+    if let Some(timer) = state.git_timer.take() {
+        glib::source_remove(timer);
+    }
+
+    let store = store.clone();
+    let timer_state_ref = state_ref.clone();
+    state.git_timer = Some(gtk::timeout_add(300, move || {
+        let state_ref = &timer_state_ref;
+        state_ref.borrow_mut().git_timer = None;
+
+        let dir = state_ref.borrow().current_dir.clone();
+        let store = UiMutex::new(store.clone());
+        let state_ref = UiMutex::new(state_ref.clone());
+        git::status(dir.clone(), move |status| {
+            let state_ref = state_ref.borrow();
+            let mut state = state_ref.borrow_mut();
+            // The root might have changed while git was running.
+            if state.current_dir == dir {
+                state.git_status = status;
+                apply_git_status(&store.borrow(), &state);
+            }
+        });
+        glib::Continue(false)
+    }));
+}
+
+/// Updates the git status of all rows, removing ignored ones if they are hidden.
+fn apply_git_status(store: &gtk::TreeStore, state: &State) {
+    let mut ignored = Vec::new();
+    store.foreach(|_, tree_path, iter| {
+        if let Some(path) = store.get_value(iter, Column::Path as i32).get::<String>() {
+            let status = git_status_of(state, Path::new(&path));
+            if state.hide_ignored && status == Some(git::FileStatus::Ignored) {
+                ignored.push(tree_path.clone());
+            } else {
+                set_git_status(store, iter, status);
+            }
+        }
+        false
+    });
+
+    // Remove from the bottom, so the paths of remaining rows stay valid.
+    for tree_path in ignored.iter().rev() {
+        if let Some(iter) = store.get_iter(tree_path) {
+            store.remove(&iter);
+        }
+    }
+}
+
+fn git_status_of(state: &State, path: &Path) -> Option<git::FileStatus> {
+    state.git_status.as_ref().and_then(|status| status.get(path))
+}
+
+/// Whether the path is hidden because git ignores it.
+fn is_ignored_hidden(state: &State, path: &Path) -> bool {
+    state.hide_ignored && git_status_of(state, path) == Some(git::FileStatus::Ignored)
+}
+
+fn set_git_status(store: &gtk::TreeStore, iter: &gtk::TreeIter, status: Option<git::FileStatus>) {
+    let icon = status.and_then(|status| status.icon_name());
+    let color = status.map(|status| status.color());
+    store.set(
+        iter,
+        &[Column::StatusIcon as u32, Column::StatusColor as u32],
+        &[&icon, &color],
+    );
+}
+
+/// Whether the path is inside of a `.git` directory.
+///
+/// Changes there are caused by git itself, e.g. when refreshing the index, and must not trigger
+/// another status update.
+fn is_git_internal(path: &Path) -> bool {
+    path.components()
+        .any(|component| component == Component::Normal(".git".as_ref()))
+}
+
+/// Directory monitors that keep the tree in sync with file system changes.
+///
+/// The root directory and all directories that were populated by expanding them are watched.
+struct DirMonitors(Rc<RefCell<HashMap<String, gio::FileMonitor>>>);
+
+impl DirMonitors {
+    fn new() -> Self {
+        DirMonitors(Rc::new(RefCell::new(HashMap::new())))
+    }
+
+    /// Starts watching the given directory, if it is not watched yet.
+    fn watch(&self, store: &gtk::TreeStore, state_ref: &Rc<RefCell<State>>, dir: &str) {
+        let mut monitors = self.0.borrow_mut();
+        if monitors.contains_key(dir) {
+            return;
+        }
+
+        let monitor = match gio::File::new_for_path(dir)
+            .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, None)
+        {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                error!("Couldn't watch directory {}: {}", dir, err);
+                return;
+            }
+        };
+
+        // The monitor is owned by the file browser, so don't keep the state alive from it.
+        let state_ref = Rc::downgrade(state_ref);
+        let monitors_ref = Rc::downgrade(&self.0);
+        monitor.connect_changed(clone!(store => move |_, file, other_file, event| {
+            if let (Some(state_ref), Some(monitors)) = (state_ref.upgrade(), monitors_ref.upgrade()) {
+                let monitors = DirMonitors(monitors);
+                on_dir_changed(
+                    &store,
+                    &state_ref.borrow(),
+                    &monitors,
+                    file,
+                    other_file.as_ref(),
+                    event,
+                );
+                if event != gio::FileMonitorEvent::Changed {
+                    state_ref.borrow_mut().search_files = None;
+                }
+                if !file.get_path().map_or(false, |path| is_git_internal(&path)) {
+                    schedule_git_status(&store, &state_ref);
+                }
+            }
+        }));
+        monitors.insert(dir.to_owned(), monitor);
+    }
+
+    /// Stops watching `path` and all directories below it.
+    fn unwatch_tree(&self, path: &Path) {
+        self.0.borrow_mut().retain(|dir, monitor| {
+            if Path::new(dir).starts_with(path) {
+                monitor.cancel();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn clear(&self) {
+        for (_, monitor) in self.0.borrow_mut().drain() {
+            monitor.cancel();
+        }
+    }
+}
+
+/// Applies a single file system change to the tree.
+fn on_dir_changed(
+    store: &gtk::TreeStore,
+    state: &State,
+    monitors: &DirMonitors,
+    file: &gio::File,
+    other_file: Option<&gio::File>,
+    event: gio::FileMonitorEvent,
+) {
+    match event {
+        gio::FileMonitorEvent::Created | gio::FileMonitorEvent::MovedIn => {
+            if let Some(path) = file.get_path() {
+                tree_insert(store, state, &path);
+            }
+        }
+        gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => {
+            if let Some(path) = file.get_path() {
+                tree_remove(store, state, monitors, &path);
+            }
+        }
+        gio::FileMonitorEvent::Renamed => {
+            if let Some(path) = file.get_path() {
+                tree_remove(store, state, monitors, &path);
+            }
+            if let Some(path) = other_file.and_then(|file| file.get_path()) {
+                tree_insert(store, state, &path);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Finds the child row of `parent` with the given file name.
+fn find_child(
+    store: &gtk::TreeStore,
+    parent: Option<&gtk::TreeIter>,
+    filename: &str,
+) -> Option<gtk::TreeIter> {
+    let iter = store.iter_children(parent)?;
+    loop {
+        if store.get_value(&iter, Column::Filename as i32).get::<&str>() == Some(filename) {
+            return Some(iter);
+        }
+        if !store.iter_next(&iter) {
+            return None;
+        }
+    }
+}
+
+/// Finds the row of the given path.
+///
+/// Returns `Some(None)` for the root directory and `None` if the path is not shown in the tree.
+fn find_tree_node(
+    store: &gtk::TreeStore,
+    state: &State,
+    path: &Path,
+) -> Option<Option<gtk::TreeIter>> {
+    let rel_path = path.strip_prefix(&state.current_dir).ok()?;
+    let mut node = None;
+    for component in rel_path.components() {
+        match component {
+            Component::Normal(name) => {
+                let child = find_child(store, node.as_ref(), name.to_str()?)?;
+                node = Some(child);
+            }
+            _ => return None,
+        }
+    }
+    Some(node)
+}
+
+/// Inserts a newly created file or directory at its sorted position.
+///
+/// Directories that were not populated yet are left alone, they are read when expanded.
+fn tree_insert(store: &gtk::TreeStore, state: &State, path: &Path) {
+    let entry = match read_entry(path, state.show_hidden) {
+        Some(entry) => entry,
+        None => return,
+    };
+    if is_ignored_hidden(state, path) {
+        return;
+    }
+    let parent = match path.parent().and_then(|dir| find_tree_node(store, state, dir)) {
+        Some(parent) => parent,
+        None => return,
+    };
+
+    if let Some(ref parent) = parent {
+        match store.iter_children(parent) {
+            None => {
+                // The directory was empty, make it expandable.
+                let iter = store.append(parent);
+                store.set(&iter, &[], &[]);
+                return;
+            }
+            Some(child) => {
+                if store.get_value(&child, Column::Filename as i32).get::<&str>().is_none() {
+                    return;
+                }
+            }
+        }
+    }
+
+    if find_child(store, parent.as_ref(), &entry.filename).is_some() {
+        return;
+    }
+
+    let mut siblings = Vec::new();
+    if let Some(iter) = store.iter_children(parent.as_ref()) {
+        loop {
+            let filename = store.get_value(&iter, Column::Filename as i32).get::<String>();
+            let file_type = store.get_value(&iter, Column::FileType as i32).get::<u8>();
+            if let (Some(filename), Some(file_type)) = (filename, file_type) {
+                siblings.push((filename, file_type == FileType::Dir as u8));
+            }
+            if !store.iter_next(&iter) {
+                break;
+            }
+        }
+    }
+
+    let position = insert_position(&siblings, &entry);
+    let iter = store.insert(parent.as_ref(), position as i32);
+    set_tree_node(store, &iter, &entry);
+    set_git_status(store, &iter, git_status_of(state, path));
+}
+
+/// Removes the row of a deleted file or directory.
+///
+/// Monitors of the directory and its subdirectories are dropped, so they are watched again
+/// once a directory with the same path is created and expanded.
+fn tree_remove(store: &gtk::TreeStore, state: &State, monitors: &DirMonitors, path: &Path) {
+    monitors.unwatch_tree(path);
+    if let Some(Some(iter)) = find_tree_node(store, state, path) {
+        store.remove(&iter);
+    }
+}
+
+/// Position of `entry` among sorted siblings, given as file name and whether it is a directory.
+fn insert_position(siblings: &[(String, bool)], entry: &Entry) -> usize {
+    siblings
+        .iter()
+        .take_while(|&&(ref filename, is_dir)| {
+            let sibling = Entry {
+                filename: filename.clone(),
+                path: String::new(),
+                file_type: if is_dir { FileType::Dir } else { FileType::File },
+            };
+            cmp_dirs_first(&sibling, entry) == Ordering::Less
+        })
+        .count()
 }
 
 /// Updates the dirctory list on top of the file browser.
@@ -444,6 +1196,78 @@ fn update_dir_list(dir: &str, dir_list_model: &gtk::TreeStore, dir_list: &gtk::C
     }
 }
 
+/// A file or directory shown in the file browser tree.
+#[derive(Debug)]
+struct Entry {
+    filename: String,
+    path: String,
+    file_type: FileType,
+}
+
+/// Reads a single entry.
+///
+/// Returns `None` for hidden files, paths that contain invalid unicode, invalid symlinks and
+/// special files.
+fn read_entry(path: &Path, show_hidden: bool) -> Option<Entry> {
+    let filename = path.file_name()?.to_str()?.to_owned();
+    if !is_visible(&filename, show_hidden) {
+        return None;
+    }
+    let file_type = {
+        // In case of invalid symlinks, we cannot obtain metadata.
+        let file_type = fs::metadata(path).ok()?.file_type();
+        if file_type.is_dir() {
+            FileType::Dir
+        } else if file_type.is_file() {
+            FileType::File
+        } else {
+            return None;
+        }
+    };
+    Some(Entry {
+        filename,
+        path: path.to_str()?.to_owned(),
+        file_type,
+    })
+}
+
+/// Reads visible entries of a directory, sorted directories first.
+fn read_dir_entries(dir: &Path, show_hidden: bool) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = dir.read_dir()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| read_entry(&entry.path(), show_hidden))
+        .collect();
+    entries.sort_unstable_by(cmp_dirs_first);
+    Ok(entries)
+}
+
+/// Sets the values of a tree row.
+fn set_tree_node(store: &gtk::TreeStore, iter: &gtk::TreeIter, entry: &Entry) {
+    let icon = match entry.file_type {
+        FileType::Dir => ICON_FOLDER_CLOSED,
+        FileType::File => ICON_FILE,
+    };
+    store.set(
+        iter,
+        &[0, 1, 2, 3],
+        &[&entry.filename, &entry.path, &(entry.file_type as u8), &icon],
+    );
+    // For directories, check whether the directory is empty. If not, append a single empty
+    // entry, so the expand arrow is shown. Its contents are dynamically populated when
+    // expanded (see `init`).
+    if let FileType::Dir = entry.file_type {
+        let not_empty = if let Ok(mut dir) = Path::new(&entry.path).read_dir() {
+            dir.next().is_some()
+        } else {
+            false
+        };
+        if not_empty {
+            let iter = store.append(iter);
+            store.set(&iter, &[], &[]);
+        }
+    }
+}
+
 /// Populates one level, i.e. one directory of the file browser tree.
 fn populate_tree_nodes(
     store: &gtk::TreeStore,
@@ -451,70 +1275,200 @@ fn populate_tree_nodes(
     dir: &str,
     parent: Option<&gtk::TreeIter>,
 ) {
-    let path = Path::new(dir);
-    let read_dir = match path.read_dir() {
-        Ok(read_dir) => read_dir,
+    let entries = match read_dir_entries(Path::new(dir), state.show_hidden) {
+        Ok(entries) => entries,
         Err(err) => {
             error!("Couldn't populate tree: {}", err);
             return;
         }
     };
-    let iter = read_dir.filter_map(Result::ok);
-    let mut entries: Vec<DirEntry> = if state.show_hidden {
-        iter.collect()
-    } else {
-        iter.filter(|entry| !entry.file_name().to_string_lossy().starts_with("."))
-            .filter(|entry| !entry.file_name().to_string_lossy().ends_with("~"))
-            .collect()
-    };
-    entries.sort_unstable_by(|lhs, rhs| cmp_dirs_first(lhs, rhs).unwrap_or(Ordering::Equal));
     for entry in entries {
-        let path = if let Some(path) = entry.path().to_str() {
-            path.to_owned()
-        } else {
-            // Skip paths that contain invalid unicode.
+        let path = Path::new(&entry.path);
+        if is_ignored_hidden(state, path) {
             continue;
-        };
-        let filename = entry.file_name().to_str().unwrap().to_owned();
-        let file_type = if let Ok(metadata) = fs::metadata(entry.path()) {
-            let file_type = metadata.file_type();
-            if file_type.is_dir() {
-                FileType::Dir
-            } else if file_type.is_file() {
-                FileType::File
+        }
+        // When we get until here, we want to show the entry. Append it to the tree.
+        let iter = store.append(parent);
+        set_tree_node(store, &iter, &entry);
+        set_git_status(store, &iter, git_status_of(state, path));
+    }
+}
+
+/// Directory for new files: the clicked folder, the folder of the clicked file or the root.
+fn target_dir(state: &State) -> PathBuf {
+    match state.selected_path {
+        Some(ref path) if Path::new(path).is_dir() => PathBuf::from(path),
+        Some(ref path) => Path::new(path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(&state.current_dir)),
+        None => PathBuf::from(&state.current_dir),
+    }
+}
+
+/// Asks the user for a file name.
+///
+/// Returns `None` if the dialog was cancelled or the name is empty or contains a path separator.
+fn ask_name(tree: &gtk::TreeView, title: &str, initial: &str) -> Option<String> {
+    let window = tree.get_toplevel().and_then(|w| w.downcast::<gtk::Window>().ok());
+    let dlg = gtk::Dialog::new_with_buttons(
+        Some(title),
+        window.as_ref(),
+        gtk::DialogFlags::USE_HEADER_BAR | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Cancel", gtk::ResponseType::Cancel.into()),
+            ("Ok", gtk::ResponseType::Ok.into()),
+        ],
+    );
+    dlg.set_default_response(gtk::ResponseType::Ok.into());
+
+    let entry = gtk::Entry::new();
+    entry.set_text(initial);
+    entry.set_activates_default(true);
+    entry.set_width_chars(40);
+    let content = dlg.get_content_area();
+    content.set_border_width(12);
+    content.add(&entry);
+    content.show_all();
+
+    let ok: i32 = gtk::ResponseType::Ok.into();
+    let res = if dlg.run() == ok {
+        entry.get_text().and_then(|name| {
+            let name = name.trim();
+            if name.is_empty() || name.contains('/') || name.contains(::std::path::MAIN_SEPARATOR) {
+                None
             } else {
-                continue;
+                Some(name.to_owned())
             }
-        } else {
-            // In case of invalid symlinks, we cannot obtain metadata.
-            continue;
-        };
-        let icon = match file_type {
-            FileType::Dir => ICON_FOLDER_CLOSED,
-            FileType::File => ICON_FILE,
+        })
+    } else {
+        None
+    };
+
+    dlg.destroy();
+
+    res
+}
+
+fn show_error(tree: &gtk::TreeView, msg: &str) {
+    let window = tree.get_toplevel().and_then(|w| w.downcast::<gtk::Window>().ok());
+    let dlg = gtk::MessageDialog::new(
+        window.as_ref(),
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Ok,
+        msg,
+    );
+    dlg.run();
+    dlg.destroy();
+}
+
+/// Moves or renames a file or directory and renames buffers of files that were moved.
+fn move_path(nvim: &Rc<NeovimClient>, from: &Path, to: &Path) -> Result<(), String> {
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    fs::rename(from, to)
+        .map_err(|err| format!("Couldn't move {}: {}", from.display(), err))?;
+
+    if let Some(mut nvim) = nvim.nvim() {
+        rename_buffers(&mut nvim, from, to)
+            .map_err(|err| format!("Couldn't rename buffers: {}", err))?;
+    }
+    Ok(())
+}
+
+/// Points buffers of moved files to their new location.
+///
+/// Unmodified buffers are written, so nvim does not consider them as new files. Buffers with
+/// unsaved changes keep them.
+fn rename_buffers(nvim: &mut NeovimRef, from: &Path, to: &Path) -> Result<(), CallError> {
+    let current_buf = nvim.get_current_buf()?.get_number(nvim)?;
+    for buf in nvim.list_bufs()? {
+        let name = buf.get_name(nvim)?;
+        let new_name = match renamed_path(Path::new(&name), from, to) {
+            Some(new_name) => new_name,
+            None => continue,
         };
-        // When we get until here, we want to show the entry. Append it to the tree.
-        let iter = store.append(parent);
-        store.set(
-            &iter,
-            &[0, 1, 2, 3],
-            &[&filename, &path, &(file_type as u8), &icon],
-        );
-        // For directories, check whether the directory is empty. If not, append a single empty
-        // entry, so the expand arrow is shown. Its contents are dynamically populated when
-        // expanded (see `init`).
-        if let FileType::Dir = file_type {
-            let not_empty = if let Ok(mut dir) = entry.path().read_dir() {
-                dir.next().is_some()
+
+        buf.set_name(nvim, &new_name.to_string_lossy())?;
+
+        if let Value::Boolean(false) = buf.get_option(nvim, "modified")? {
+            let buf_nr = buf.get_number(nvim)?;
+            if buf_nr == current_buf {
+                nvim.command("silent write!")?;
             } else {
-                false
-            };
-            if not_empty {
-                let iter = store.append(&iter);
-                store.set(&iter, &[], &[]);
+                nvim.command(&format!(
+                    "keepalt keepjumps hide buffer {} | silent write! | \
+                     keepalt keepjumps hide buffer {}",
+                    buf_nr, current_buf
+                ))?;
             }
         }
     }
+    Ok(())
+}
+
+/// New location of `path` after `from` was moved to `to`.
+fn renamed_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| if rest.as_os_str().is_empty() {
+        to.to_path_buf()
+    } else {
+        to.join(rest)
+    })
+}
+
+/// Free path for a copy of `path`: `name copy.ext`, `name copy 2.ext`, ...
+fn duplicate_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path.extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut copy_path = path.with_file_name(format!("{} copy{}", stem, ext));
+    let mut n = 2;
+    while copy_path.exists() {
+        copy_path = path.with_file_name(format!("{} copy {}{}", stem, n, ext));
+        n += 1;
+    }
+    copy_path
+}
+
+/// Copies a file or a whole directory.
+///
+/// Symlinks are recreated instead of followed, so linked directories aren't copied and
+/// cycles don't recurse forever.
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        copy_symlink(from, to)
+    } else if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in from.read_dir()? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    if fs::metadata(from).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+        ::std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        ::std::os::windows::fs::symlink_file(target, to)
+    }
 }
 
 fn get_current_dir(nvim: &mut NeovimRef) -> Option<String> {
@@ -555,3 +1509,130 @@ fn reveal_path_in_tree(store: &gtk::TreeStore, tree: &gtk::TreeView, rel_file_pa
     tree.set_cursor(&tree_path, None, false);
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_util::TempDir;
+
+    fn filenames(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.filename.as_str()).collect()
+    }
+
+    #[test]
+    fn test_read_dir_entries() {
+        let dir = TempDir::new("read-dir");
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("b.txt"), "").unwrap();
+        fs::write(dir.join("A.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        fs::write(dir.join("backup~"), "").unwrap();
+
+        let entries = read_dir_entries(&dir, false).unwrap();
+        assert_eq!(vec!["src", "A.txt", "b.txt"], filenames(&entries));
+        assert_eq!(FileType::Dir, entries[0].file_type);
+
+        let entries = read_dir_entries(&dir, true).unwrap();
+        assert_eq!(5, entries.len());
+    }
+
+    #[test]
+    fn test_insert_position() {
+        let dir = TempDir::new("insert");
+        fs::create_dir(dir.join("lib")).unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        fs::write(dir.join("c.txt"), "").unwrap();
+
+        let siblings: Vec<_> = read_dir_entries(&dir, false)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.filename, e.file_type == FileType::Dir))
+            .collect();
+
+        fs::write(dir.join("b.txt"), "").unwrap();
+        fs::create_dir(dir.join("doc")).unwrap();
+        fs::write(dir.join(".b.swp"), "").unwrap();
+
+        let file = read_entry(&dir.join("b.txt"), false).unwrap();
+        assert_eq!(2, insert_position(&siblings, &file));
+        let new_dir = read_entry(&dir.join("doc"), false).unwrap();
+        assert_eq!(0, insert_position(&siblings, &new_dir));
+        assert!(read_entry(&dir.join(".b.swp"), false).is_none());
+        assert!(read_entry(&dir.join("missing"), false).is_none());
+    }
+
+    #[test]
+    fn test_renamed_path() {
+        let from = Path::new("/project/src");
+        let to = Path::new("/project/lib");
+        assert_eq!(Some(PathBuf::from("/project/lib")), renamed_path(from, from, to));
+        assert_eq!(
+            Some(PathBuf::from("/project/lib/main.rs")),
+            renamed_path(Path::new("/project/src/main.rs"), from, to)
+        );
+        assert_eq!(None, renamed_path(Path::new("/project/srcs/main.rs"), from, to));
+    }
+
+    #[test]
+    fn test_duplicate_and_copy() {
+        let dir = TempDir::new("duplicate");
+        let file = dir.join("main.rs");
+        fs::write(&file, "fn main() {}").unwrap();
+
+        let copy = duplicate_path(&file);
+        assert_eq!(dir.join("main copy.rs"), copy);
+        copy_recursive(&file, &copy).unwrap();
+        assert_eq!(dir.join("main copy 2.rs"), duplicate_path(&file));
+
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+        let copy = duplicate_path(&dir.join("src"));
+        assert_eq!(dir.join("src copy"), copy);
+        copy_recursive(&dir.join("src"), &copy).unwrap();
+        assert!(copy.join("lib.rs").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("copy-symlinks");
+        let src = dir.join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("lib.rs"), "").unwrap();
+        symlink("lib.rs", src.join("link.rs")).unwrap();
+        // a link to the parent would recurse forever if followed
+        symlink("..", src.join("parent")).unwrap();
+
+        let copy = dir.join("copy");
+        copy_recursive(&src, &copy).unwrap();
+        assert!(copy.join("lib.rs").is_file());
+        assert_eq!(PathBuf::from("lib.rs"), fs::read_link(copy.join("link.rs")).unwrap());
+        assert_eq!(PathBuf::from(".."), fs::read_link(copy.join("parent")).unwrap());
+    }
+
+    #[test]
+    fn test_open_mode() {
+        assert_eq!(
+            OpenMode::Edit,
+            OpenMode::from_modifiers(gdk::ModifierType::empty())
+        );
+        assert_eq!(
+            OpenMode::VSplit,
+            OpenMode::from_modifiers(gdk::ModifierType::CONTROL_MASK)
+        );
+        assert_eq!(
+            OpenMode::Tab,
+            OpenMode::from_modifiers(
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK
+            )
+        );
+        assert_eq!(
+            ":split src/a\\ b.rs",
+            OpenMode::Split.command("src/a b.rs")
+        );
+        assert_eq!(":pedit main.rs", OpenMode::Preview.command("main.rs"));
+    }
+}
@@ -0,0 +1,136 @@
+//! File lists shared by the file browser search, Quick Open and the command palette.
+
+use std::fs;
+use std::path::Path;
+
+use gtk;
+use gtk::prelude::*;
+
+use git;
+
+const MAX_FILES: usize = 100_000;
+
+/// Whether a file with the given name is shown.
+pub fn is_visible(filename: &str, show_hidden: bool) -> bool {
+    show_hidden || !(filename.starts_with(".") || filename.ends_with("~"))
+}
+
+/// Lists all files below `dir`, respecting `.gitignore` inside of git repositories.
+pub fn project_files(dir: &Path, show_hidden: bool) -> Vec<String> {
+    let mut files = git::ls_files(dir).unwrap_or_else(|err| {
+        debug!("Can't list files with git, reading directories: {}", err);
+        let mut files = Vec::new();
+        walk_files(dir, dir, show_hidden, &mut files);
+        files
+    });
+    if !show_hidden {
+        files.retain(|file| {
+            Path::new(file)
+                .iter()
+                .all(|name| name.to_str().map_or(false, |name| is_visible(name, false)))
+        });
+    }
+    files.truncate(MAX_FILES);
+    files
+}
+
+/// Recursively collects the visible files below `dir`, relative to `root`.
+///
+/// Directories come first, like in the file browser tree.
+fn walk_files(root: &Path, dir: &Path, show_hidden: bool, files: &mut Vec<String>) {
+    let mut entries: Vec<_> = match dir.read_dir() {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let visible = path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| is_visible(name, show_hidden));
+                // Symlinks might form cycles, so linked directories aren't followed.
+                let is_dir = entry.file_type().ok()?.is_dir();
+                if visible && (is_dir || path.is_file()) {
+                    Some((is_dir, path))
+                } else {
+                    None
+                }
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    entries.sort_by(|&(lhs_dir, ref lhs), &(rhs_dir, ref rhs)| {
+        rhs_dir.cmp(&lhs_dir).then_with(|| {
+            let lhs = lhs.file_name().map(|name| name.to_string_lossy().to_lowercase());
+            let rhs = rhs.file_name().map(|name| name.to_string_lossy().to_lowercase());
+            lhs.cmp(&rhs)
+        })
+    });
+
+    for (is_dir, path) in entries {
+        if files.len() >= MAX_FILES {
+            return;
+        }
+        if is_dir {
+            walk_files(root, &path, show_hidden, files);
+        } else if let Some(rel_path) = path.strip_prefix(root).ok().and_then(Path::to_str) {
+            files.push(rel_path.to_owned());
+        }
+    }
+}
+
+/// Moves the selection of a list by `offset` rows, selects the first row if nothing is selected.
+pub fn move_selection(view: &gtk::TreeView, store: &gtk::ListStore, offset: i32) {
+    let count = store.iter_n_children(None);
+    if count == 0 {
+        return;
+    }
+    let selection = view.get_selection();
+    let current = selection
+        .get_selected()
+        .and_then(|(_, iter)| store.get_path(&iter))
+        .map(|path| path.get_indices()[0]);
+    let idx = match current {
+        Some(idx) => (idx + offset).max(0).min(count - 1),
+        None => 0,
+    };
+    let path = gtk::TreePath::new_from_string(&format!("{}", idx));
+    selection.select_path(&path);
+    view.scroll_to_cell(Some(&path), None, false, 0.0, 0.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_util::TempDir;
+
+    #[test]
+    fn test_walk_files() {
+        let dir = TempDir::new("walk");
+        fs::create_dir_all(dir.join("src").join("ui")).unwrap();
+        fs::create_dir(dir.join(".git")).unwrap();
+        fs::write(dir.join("src").join("ui").join("main.rs"), "").unwrap();
+        fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+        fs::write(dir.join(".git").join("HEAD"), "").unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+
+        let mut files = Vec::new();
+        walk_files(&dir, &dir, false, &mut files);
+        let sep = ::std::path::MAIN_SEPARATOR;
+        assert_eq!(
+            vec![
+                format!("src{}ui{}main.rs", sep, sep),
+                format!("src{}lib.rs", sep),
+                "README.md".to_owned(),
+            ],
+            files
+        );
+    }
+
+    #[test]
+    fn test_is_visible() {
+        assert!(is_visible("main.rs", false));
+        assert!(!is_visible(".git", false));
+        assert!(!is_visible("main.rs~", false));
+        assert!(is_visible(".git", true));
+    }
+}
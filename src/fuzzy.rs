@@ -0,0 +1,320 @@
+use std::cmp::Ordering;
+
+use htmlescape::encode_minimal;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_SEGMENT_START: i64 = 24;
+const BONUS_WORD_START: i64 = 12;
+const BONUS_CONSECUTIVE: i64 = 16;
+const BONUS_FILENAME: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+
+/// Result of matching a pattern against a path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// Char indices of the matched characters.
+    pub positions: Vec<usize>,
+}
+
+/// Matches a pattern against many paths, reusing the buffers between paths.
+pub struct Matcher {
+    pattern: Vec<char>,
+    chars: Vec<char>,
+    lower: Vec<char>,
+    /// scores[i * n + j]: best score with pattern[i] matched at path[j], prev[i * n + j] the
+    /// position of pattern[i - 1] in that case.
+    scores: Vec<Option<i64>>,
+    prev: Vec<usize>,
+}
+
+impl Matcher {
+    pub fn new(pattern: &str) -> Self {
+        Matcher {
+            pattern: pattern
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect(),
+            chars: Vec::new(),
+            lower: Vec::new(),
+            scores: Vec::new(),
+            prev: Vec::new(),
+        }
+    }
+
+    /// Matches all characters of the pattern in order against `path`, ignoring case.
+    ///
+    /// Matches at the start of path segments and words, consecutive matches and matches in the
+    /// file name are ranked higher, gaps between matched characters lower.
+    pub fn match_path(&mut self, path: &str) -> Option<Match> {
+        if self.pattern.is_empty() {
+            return Some(Match {
+                score: 0,
+                positions: vec![],
+            });
+        }
+
+        self.chars.clear();
+        self.chars.extend(path.chars());
+        self.lower.clear();
+        self.lower
+            .extend(self.chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)));
+        if !self.is_subsequence() {
+            return None;
+        }
+
+        let pattern = &self.pattern;
+        let chars = &self.chars;
+        let lower = &self.lower;
+        let n = chars.len();
+        let filename_start = chars.iter().rposition(|&c| c == '/').map_or(0, |idx| idx + 1);
+
+        self.scores.clear();
+        self.scores.resize(pattern.len() * n, None);
+        self.prev.clear();
+        self.prev.resize(pattern.len() * n, 0);
+        let scores = &mut self.scores;
+        let prev = &mut self.prev;
+
+        for (i, &pc) in pattern.iter().enumerate() {
+            // Best `score + position` of the previous pattern char left of the current
+            // position, so the gap penalty can be applied in constant time.
+            let mut best_before: Option<(i64, usize)> = None;
+
+            for j in i..n {
+                if i > 0 && j >= 2 {
+                    if let Some(score) = scores[(i - 1) * n + j - 2] {
+                        let candidate = score + (j - 2) as i64 * PENALTY_GAP;
+                        if best_before.map_or(true, |(best, _)| candidate > best) {
+                            best_before = Some((candidate, j - 2));
+                        }
+                    }
+                }
+
+                if lower[j] != pc {
+                    continue;
+                }
+
+                let mut score = SCORE_MATCH + char_bonus(chars, j);
+                if j >= filename_start {
+                    score += BONUS_FILENAME;
+                }
+
+                if i == 0 {
+                    scores[j] = Some(score);
+                    continue;
+                }
+
+                let consecutive = scores[(i - 1) * n + j - 1].map(|s| s + BONUS_CONSECUTIVE);
+                let gap = best_before.map(|(best, _)| best - (j - 1) as i64 * PENALTY_GAP);
+                let (best, prev_idx) = match (consecutive, gap) {
+                    (Some(c), Some(g)) if g > c => (g, best_before.unwrap().1),
+                    (Some(c), _) => (c, j - 1),
+                    (None, Some(g)) => (g, best_before.unwrap().1),
+                    (None, None) => continue,
+                };
+                scores[i * n + j] = Some(best + score);
+                prev[i * n + j] = prev_idx;
+            }
+        }
+
+        let last = pattern.len() - 1;
+        let (end, score) = scores[last * n..]
+            .iter()
+            .enumerate()
+            .filter_map(|(j, score)| score.map(|score| (j, score)))
+            .max_by_key(|&(j, score)| (score, j))?;
+
+        let mut positions = vec![end; pattern.len()];
+        for i in (1..pattern.len()).rev() {
+            positions[i - 1] = prev[i * n + positions[i]];
+        }
+
+        // Prefer shorter paths among otherwise equal matches.
+        Some(Match {
+            score: score - n as i64 / 8,
+            positions,
+        })
+    }
+
+    /// Whether the pattern chars occur in order, a cheap check before scoring.
+    fn is_subsequence(&self) -> bool {
+        let mut lower = self.lower.iter();
+        self.pattern.iter().all(|pc| lower.any(|c| c == pc))
+    }
+}
+
+fn char_bonus(chars: &[char], idx: usize) -> i64 {
+    if idx == 0 {
+        return BONUS_SEGMENT_START;
+    }
+    let prev = chars[idx - 1];
+    let cur = chars[idx];
+    if prev == '/' || prev == '\\' {
+        BONUS_SEGMENT_START
+    } else if prev == '_' || prev == '-' || prev == '.' || prev == ' '
+        || (prev.is_lowercase() && cur.is_uppercase())
+    {
+        BONUS_WORD_START
+    } else {
+        0
+    }
+}
+
+/// Matches `pattern` against all `items` and returns the best `limit` matches, best first.
+pub fn rank<'a, S: AsRef<str>>(pattern: &str, items: &'a [S], limit: usize) -> Vec<(&'a str, Match)> {
+    rank_by(pattern, items, limit, |item| item.as_ref(), |_| 0)
+        .into_iter()
+        .map(|(idx, m)| (items[idx].as_ref(), m))
+        .collect()
+}
+
+/// Matches `pattern` against the `key` of all `items` and returns the indices of the best
+/// `limit` matches, best first.
+///
+/// `bonus` is added to the score of the item at an index, equal scores keep the order of `items`.
+pub fn rank_by<T, K, B>(
+    pattern: &str,
+    items: &[T],
+    limit: usize,
+    key: K,
+    bonus: B,
+) -> Vec<(usize, Match)>
+where
+    K: Fn(&T) -> &str,
+    B: Fn(usize) -> i64,
+{
+    let mut matcher = Matcher::new(pattern);
+    let mut matches: Vec<_> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| {
+            matcher.match_path(key(item)).map(|mut m| {
+                m.score += bonus(idx);
+                (idx, m)
+            })
+        })
+        .collect();
+    matches.sort_by(|&(lhs, ref lhs_match), &(rhs, ref rhs_match)| {
+        match rhs_match.score.cmp(&lhs_match.score) {
+            Ordering::Equal => lhs.cmp(&rhs),
+            ordering => ordering,
+        }
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// Pango markup of `text` with the characters at `positions` highlighted.
+pub fn highlight_markup(text: &str, positions: &[usize]) -> String {
+    let mut markup = String::new();
+    let mut highlighted = false;
+    for (idx, c) in text.chars().enumerate() {
+        let matched = positions.contains(&idx);
+        if matched != highlighted {
+            markup.push_str(if matched { "<b>" } else { "</b>" });
+            highlighted = matched;
+        }
+        markup.push_str(&encode_minimal(c.encode_utf8(&mut [0; 4])));
+    }
+    if highlighted {
+        markup.push_str("</b>");
+    }
+    markup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn match_path(pattern: &str, path: &str) -> Option<Match> {
+        Matcher::new(pattern).match_path(path)
+    }
+
+    #[test]
+    fn test_match_path() {
+        assert_eq!(None, match_path("xyz", "src/main.rs"));
+        assert_eq!(None, match_path("rsm", "src/main.rs"));
+        assert_eq!(
+            vec![4, 5, 6, 7],
+            match_path("main", "src/main.rs").unwrap().positions
+        );
+        assert_eq!(
+            vec![0, 4],
+            match_path("SM", "src/main.rs").unwrap().positions
+        );
+        assert_eq!(
+            Some(Match {
+                score: 0,
+                positions: vec![],
+            }),
+            match_path("", "src/main.rs")
+        );
+    }
+
+    #[test]
+    fn test_match_prefers_segments() {
+        // "fb" should pick the word starts of "file_browser", not the "f" of "diff".
+        let m = match_path("fb", "diff/file_browser.rs").unwrap();
+        assert_eq!(vec![5, 10], m.positions);
+    }
+
+    #[test]
+    fn test_matcher_reuse() {
+        let mut matcher = Matcher::new("mr");
+        let paths = ["src/main.rs", "README.md", "src/ui_model/model_rect.rs", "mr"];
+        for path in paths.iter() {
+            assert_eq!(match_path("mr", path), matcher.match_path(path));
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let files = vec![
+            "src/plug_manager/store.rs",
+            "src/shell.rs",
+            "resources/side-panel.ui",
+            "src/ui_model/model_rect.rs",
+        ];
+        let ranked: Vec<_> = rank("shell", &files, 10)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect();
+        assert_eq!(vec!["src/shell.rs"], ranked);
+
+        let ranked: Vec<_> = rank("sr", &files, 2)
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect();
+        assert_eq!(2, ranked.len());
+        assert_eq!("src/shell.rs", ranked[0]);
+    }
+
+    #[test]
+    fn test_rank_by() {
+        let items = vec![("b", "main.rs"), ("a", "main.rs"), ("c", "mod.rs")];
+        let indices = |ranked: Vec<(usize, Match)>| -> Vec<usize> {
+            ranked.into_iter().map(|(idx, _)| idx).collect()
+        };
+        // equal scores keep the order of the items
+        assert_eq!(
+            vec![0, 1],
+            indices(rank_by("main", &items, 10, |item| item.1, |_| 0))
+        );
+        assert_eq!(
+            vec![1, 0],
+            indices(rank_by("main", &items, 10, |item| item.1, |idx| idx as i64))
+        );
+        assert_eq!(
+            vec![2],
+            indices(rank_by("m", &items, 1, |item| item.1, |idx| if idx == 2 { 100 } else { 0 }))
+        );
+    }
+
+    #[test]
+    fn test_highlight_markup() {
+        assert_eq!("<b>a</b>b<b>c&amp;</b>", highlight_markup("abc&", &[0, 2, 3]));
+        assert_eq!("a&lt;b", highlight_markup("a<b", &[]));
+    }
+}
@@ -0,0 +1,43 @@
+/// Minimal horizontal velocity (pixels per second) for swipe to switch tabs
+const MIN_SWIPE_VELOCITY: f64 = 500.0;
+
+/// Font zoom steps (in points) for pinch gesture with given scale,
+/// `font_size` is font size in points at gesture begin
+pub fn pinch_zoom_steps(font_size: f64, scale: f64) -> i32 {
+    (font_size * scale - font_size).round() as i32
+}
+
+/// Tab page offset for swipe with given velocity.
+///
+/// Swipe to the left opens next tab, swipe to the right opens previous one.
+/// Mostly vertical or too slow swipes are ignored.
+pub fn swipe_tab_offset(velocity_x: f64, velocity_y: f64) -> Option<i32> {
+    if velocity_x.abs() < MIN_SWIPE_VELOCITY || velocity_x.abs() < velocity_y.abs() * 2.0 {
+        None
+    } else if velocity_x < 0.0 {
+        Some(1)
+    } else {
+        Some(-1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinch_zoom_steps() {
+        assert_eq!(0, pinch_zoom_steps(12.0, 1.0));
+        assert_eq!(6, pinch_zoom_steps(12.0, 1.5));
+        assert_eq!(-6, pinch_zoom_steps(12.0, 0.5));
+        assert_eq!(0, pinch_zoom_steps(12.0, 1.02));
+    }
+
+    #[test]
+    fn test_swipe_tab_offset() {
+        assert_eq!(Some(1), swipe_tab_offset(-1000.0, 0.0));
+        assert_eq!(Some(-1), swipe_tab_offset(1000.0, 100.0));
+        assert_eq!(None, swipe_tab_offset(1000.0, 800.0));
+        assert_eq!(None, swipe_tab_offset(100.0, 0.0));
+    }
+}
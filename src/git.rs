@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+use glib;
+
+/// Git status of a file, ordered by priority when propagated to parent directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FileStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified,
+    Conflicted,
+}
+
+impl FileStatus {
+    fn from_xy(xy: &str) -> FileStatus {
+        if xy.starts_with('A') {
+            FileStatus::Added
+        } else {
+            FileStatus::Modified
+        }
+    }
+
+    pub fn icon_name(&self) -> Option<&'static str> {
+        match *self {
+            FileStatus::Ignored => None,
+            FileStatus::Untracked => Some("list-add-symbolic"),
+            FileStatus::Added => Some("list-add-symbolic"),
+            FileStatus::Modified => Some("document-edit-symbolic"),
+            FileStatus::Conflicted => Some("dialog-warning-symbolic"),
+        }
+    }
+
+    pub fn color(&self) -> &'static str {
+        match *self {
+            FileStatus::Ignored => "#888a85",
+            FileStatus::Untracked => "#3465a4",
+            FileStatus::Added => "#4e9a06",
+            FileStatus::Modified => "#c4a000",
+            FileStatus::Conflicted => "#cc0000",
+        }
+    }
+}
+
+/// Status of all changed, untracked and ignored files of a repository.
+#[derive(Debug)]
+pub struct Status {
+    files: HashMap<PathBuf, FileStatus>,
+    dirs: HashMap<PathBuf, FileStatus>,
+}
+
+impl Status {
+    /// Parses output of `git status --porcelain=v2 -z --ignored`.
+    ///
+    /// Paths in the output are relative to the repository `root`.
+    pub fn parse(root: &Path, output: &[u8]) -> Result<Status, String> {
+        let output = String::from_utf8_lossy(output);
+        let mut files = HashMap::new();
+        let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+
+        while let Some(entry) = entries.next() {
+            let (status, path) = match entry.chars().next() {
+                Some('1') => {
+                    let fields: Vec<_> = entry.splitn(9, ' ').collect();
+                    (FileStatus::from_xy(fields[1]), fields.get(8).cloned())
+                }
+                Some('2') => {
+                    let fields: Vec<_> = entry.splitn(10, ' ').collect();
+                    // the original path of a rename follows as a separate entry
+                    entries.next();
+                    (FileStatus::from_xy(fields[1]), fields.get(9).cloned())
+                }
+                Some('u') => {
+                    let fields: Vec<_> = entry.splitn(11, ' ').collect();
+                    (FileStatus::Conflicted, fields.get(10).cloned())
+                }
+                Some('?') => (FileStatus::Untracked, entry.get(2..)),
+                Some('!') => (FileStatus::Ignored, entry.get(2..)),
+                Some('#') => continue,
+                _ => return Err(format!("Unknown git status entry: {}", entry)),
+            };
+
+            let path = path.ok_or_else(|| format!("Wrong git status entry: {}", entry))?;
+            files.insert(root.join(path.trim_right_matches('/')), status);
+        }
+
+        let mut dirs = HashMap::new();
+        for (path, status) in &files {
+            if *status == FileStatus::Ignored {
+                continue;
+            }
+
+            let mut parent = path.parent();
+            while let Some(dir) = parent {
+                if !dir.starts_with(root) {
+                    break;
+                }
+                let dir_status = dirs.entry(dir.to_path_buf()).or_insert(*status);
+                if *dir_status < *status {
+                    *dir_status = *status;
+                }
+                parent = dir.parent();
+            }
+        }
+
+        Ok(Status { files, dirs })
+    }
+
+    /// Status of a file or directory, `None` if it is unchanged.
+    pub fn get(&self, path: &Path) -> Option<FileStatus> {
+        if let Some(status) = self.files.get(path).or_else(|| self.dirs.get(path)) {
+            return Some(*status);
+        }
+
+        // whole untracked or ignored directories are reported as a single entry
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            match self.files.get(dir) {
+                Some(&FileStatus::Untracked) => return Some(FileStatus::Untracked),
+                Some(&FileStatus::Ignored) => return Some(FileStatus::Ignored),
+                _ => (),
+            }
+            parent = dir.parent();
+        }
+
+        None
+    }
+}
+
+/// Reads git status of the repository that contains `dir` in background thread.
+///
+/// `cb` is called on the main thread with `None` if `dir` is not inside of a git repository.
+pub fn status<F>(dir: String, cb: F)
+where
+    F: FnOnce(Option<Status>) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut result = Some(match read_status(Path::new(&dir)) {
+            Ok(status) => Some(status),
+            Err(err) => {
+                debug!("Can't read git status of {}: {}", dir, err);
+                None
+            }
+        });
+        let mut cb = Some(cb);
+
+        glib::idle_add(move || {
+            cb.take().unwrap()(result.take().unwrap());
+            glib::Continue(false)
+        })
+    });
+}
+
+fn read_status(dir: &Path) -> Result<Status, String> {
+    let prefix = git(dir, &["rev-parse", "--show-prefix"])?;
+    let root = repo_root(dir, String::from_utf8_lossy(&prefix).trim());
+    let output = git(&root, &["status", "--porcelain=v2", "-z", "--ignored"])?;
+    Status::parse(&root, &output)
+}
+
+/// Lists files below `dir` that are tracked or not ignored, relative to `dir`.
+pub fn ls_files(dir: &Path) -> Result<Vec<String>, String> {
+    let output = git(
+        dir,
+        &["ls-files", "-z", "--cached", "--others", "--exclude-standard"],
+    )?;
+    let mut files: Vec<String> = String::from_utf8_lossy(&output)
+        .split('\0')
+        // deleted files are still listed until the deletion is staged
+        .filter(|file| !file.is_empty() && dir.join(file).is_file())
+        .map(str::to_owned)
+        .collect();
+    // unmerged files are listed once per stage
+    files.dedup();
+    Ok(files)
+}
+
+/// Runs git in `dir` and returns its output.
+pub fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let out = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if out.status.success() {
+        Ok(out.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).into_owned())
+    }
+}
+
+/// Url of the `origin` remote in the contents of a git config file.
+pub fn origin_url(config: &str) -> Option<String> {
+    let mut in_origin = false;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_origin = line == "[remote \"origin\"]";
+        } else if in_origin {
+            let mut parts = line.splitn(2, '=');
+            if parts.next().map(str::trim) == Some("url") {
+                return parts.next().map(|url| url.trim().to_owned());
+            }
+        }
+    }
+    None
+}
+
+/// Repository root for directory `dir` located at `prefix` inside of the repository.
+///
+/// Unlike `git rev-parse --show-toplevel` this keeps symlinks in `dir`, so paths match the
+/// ones shown in the file browser.
+fn repo_root(dir: &Path, prefix: &str) -> PathBuf {
+    let mut root = dir.to_path_buf();
+    for _ in Path::new(prefix).components() {
+        root.pop();
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let root = Path::new("/repo");
+        let output = "# branch.oid 1234\0\
+            1 .M N... 100644 100644 100644 1234 1234 src/main.rs\0\
+            1 A. N... 000000 100644 100644 0000 1234 src/new file.rs\0\
+            2 R. N... 100644 100644 100644 1234 1234 R100 doc/b.md\0doc/a.md\0\
+            u UU N... 100644 100644 100644 100644 1 2 3 src/merge.rs\0\
+            ? notes/\0\
+            ! target/\0";
+
+        let status = Status::parse(root, output.as_bytes()).unwrap();
+        assert_eq!(
+            Some(FileStatus::Modified),
+            status.get(Path::new("/repo/src/main.rs"))
+        );
+        assert_eq!(
+            Some(FileStatus::Added),
+            status.get(Path::new("/repo/src/new file.rs"))
+        );
+        assert_eq!(
+            Some(FileStatus::Modified),
+            status.get(Path::new("/repo/doc/b.md"))
+        );
+        assert_eq!(None, status.get(Path::new("/repo/doc/a.md")));
+        assert_eq!(
+            Some(FileStatus::Conflicted),
+            status.get(Path::new("/repo/src"))
+        );
+        assert_eq!(
+            Some(FileStatus::Untracked),
+            status.get(Path::new("/repo/notes/todo.txt"))
+        );
+        assert_eq!(
+            Some(FileStatus::Ignored),
+            status.get(Path::new("/repo/target/debug"))
+        );
+        assert_eq!(None, status.get(Path::new("/repo/README.md")));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(Status::parse(Path::new("/repo"), b"x garbage\0").is_err());
+    }
+
+    #[test]
+    fn test_origin_url() {
+        let config = "[core]\n\
+                      \tbare = false\n\
+                      [remote \"upstream\"]\n\
+                      \turl = https://example.com/upstream.git\n\
+                      [remote \"origin\"]\n\
+                      \turl = https://github.com/tpope/vim-fugitive.git\n\
+                      \tfetch = +refs/heads/*:refs/remotes/origin/*\n";
+        assert_eq!(
+            Some("https://github.com/tpope/vim-fugitive.git".to_owned()),
+            origin_url(config)
+        );
+        assert_eq!(None, origin_url("[core]\n\tbare = false\n"));
+    }
+
+    #[test]
+    fn test_repo_root() {
+        assert_eq!(
+            PathBuf::from("/home/user/repo"),
+            repo_root(Path::new("/home/user/repo/src/ui"), "src/ui/")
+        );
+        assert_eq!(
+            PathBuf::from("/home/user/repo"),
+            repo_root(Path::new("/home/user/repo"), "")
+        );
+    }
+}
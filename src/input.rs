@@ -7,6 +7,8 @@ use neovim_lib::{Neovim, NeovimApi};
 
 use std::ascii::AsciiExt;
 
+use keymap::{KeyBinding, Keymap};
+
 include!(concat!(env!("OUT_DIR"), "/key_map_table.rs"));
 
 
@@ -52,6 +54,9 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
     if state.contains(gdk::ModifierType::MOD1_MASK) {
         input.push_str("A-");
     }
+    if state.contains(gdk::ModifierType::SUPER_MASK) {
+        input.push_str("D-");
+    }
 
     input.push_str(val);
 
@@ -62,9 +67,64 @@ pub fn keyval_to_input_string(in_str: &str, in_state: gdk::ModifierType) -> Stri
     }
 }
 
-pub fn convert_key(ev: &EventKey) -> Option<String> {
+/// Map a GDK button number to the nvim mouse button name
+pub fn mouse_button_name(button: u32) -> Option<&'static str> {
+    match button {
+        1 => Some("Left"),
+        2 => Some("Middle"),
+        3 => Some("Right"),
+        8 => Some("X1"),
+        9 => Some("X2"),
+        _ => None,
+    }
+}
+
+/// Counts repeated clicks of a button on the same cell.
+///
+/// GTK reports a double click as two presses followed by a `2BUTTON_PRESS`,
+/// so the count is tracked here from the plain presses only.
+#[derive(Default)]
+pub struct ClickCounter {
+    /// button, event time and cell of the last press
+    last: Option<(u32, u32, (u64, u64))>,
+    count: u32,
+}
+
+impl ClickCounter {
+    /// Register a press and return its click count
+    pub fn press(&mut self, button: u32, time: u32, cell: (u64, u64), double_click_time: u32) -> u32 {
+        self.count = match self.last {
+            Some((last_button, last_time, last_cell))
+                if last_button == button && last_cell == cell
+                    && time.wrapping_sub(last_time) <= double_click_time =>
+            {
+                self.count + 1
+            }
+            _ => 1,
+        };
+        self.last = Some((button, time, cell));
+        self.count
+    }
+}
+
+/// Build nvim mouse input, like `<C-2-LeftMouse>` or `<S-ScrollWheelUp>`,
+/// without the trailing position
+pub fn mouse_input_string(input: &str, click_count: u32, state: gdk::ModifierType) -> String {
+    if click_count > 1 {
+        keyval_to_input_string(&format!("{}-{}", click_count.min(4), input), state)
+    } else {
+        keyval_to_input_string(input, state)
+    }
+}
+
+pub fn convert_key(ev: &EventKey, super_as_cmd: bool) -> Option<String> {
     let keyval = ev.get_keyval();
-    let state = ev.get_state();
+    let mut state = ev.get_state();
+    if !super_as_cmd {
+        state.remove(gdk::ModifierType::SUPER_MASK);
+    } else if state.contains(gdk::ModifierType::MOD4_MASK) {
+        state.insert(gdk::ModifierType::SUPER_MASK);
+    }
     if let Some(ref keyval_name) = gdk::keyval_name(keyval) {
         if let Some(cnvt) = KEYVAL_MAP.get(keyval_name as &str).cloned() {
             return Some(keyval_to_input_string(cnvt, state));
@@ -90,8 +150,15 @@ pub fn im_input(nvim: &mut Neovim, input: &str) {
     nvim.input(&input).expect("Error run input command to nvim");
 }
 
-pub fn gtk_key_press(nvim: &mut Neovim, ev: &EventKey) -> Inhibit {
-    if let Some(input) = convert_key(ev) {
+pub fn gtk_key_press(nvim: &mut Neovim, ev: &EventKey, keymap: &Keymap) -> Inhibit {
+    let input = match keymap.lookup(ev) {
+        Some(&KeyBinding::Input(ref input)) => Some(input.clone()),
+        // actions are activated by the widget that owns them
+        Some(&KeyBinding::Action(_)) => return Inhibit(false),
+        None => convert_key(ev, keymap.super_as_cmd()),
+    };
+
+    if let Some(input) = input {
         debug!("nvim_input -> {}", input);
         nvim.input(&input).expect("Error run input command to nvim");
         Inhibit(true)
@@ -99,3 +166,92 @@ pub fn gtk_key_press(nvim: &mut Neovim, ev: &EventKey) -> Inhibit {
         Inhibit(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mouse_button_name() {
+        assert_eq!(Some("Left"), mouse_button_name(1));
+        assert_eq!(Some("Middle"), mouse_button_name(2));
+        assert_eq!(Some("Right"), mouse_button_name(3));
+        assert_eq!(Some("X1"), mouse_button_name(8));
+        assert_eq!(Some("X2"), mouse_button_name(9));
+        assert_eq!(None, mouse_button_name(4));
+    }
+
+    #[test]
+    fn test_click_counter() {
+        let mut counter = ClickCounter::default();
+        assert_eq!(1, counter.press(1, 1000, (2, 3), 400));
+        assert_eq!(2, counter.press(1, 1200, (2, 3), 400));
+        assert_eq!(3, counter.press(1, 1500, (2, 3), 400));
+        // too late
+        assert_eq!(1, counter.press(1, 2000, (2, 3), 400));
+        // other cell
+        assert_eq!(1, counter.press(1, 2100, (2, 4), 400));
+        // other button
+        assert_eq!(1, counter.press(3, 2200, (2, 4), 400));
+    }
+
+    #[test]
+    fn test_mouse_input_string() {
+        let empty = gdk::ModifierType::empty();
+        assert_eq!("<LeftMouse>", mouse_input_string("LeftMouse", 1, empty));
+        assert_eq!("<2-LeftMouse>", mouse_input_string("LeftMouse", 2, empty));
+        assert_eq!("<3-LeftMouse>", mouse_input_string("LeftMouse", 3, empty));
+        assert_eq!("<4-LeftMouse>", mouse_input_string("LeftMouse", 5, empty));
+        assert_eq!("<MiddleMouse>", mouse_input_string("MiddleMouse", 1, empty));
+        assert_eq!("<X1Mouse>", mouse_input_string("X1Mouse", 1, empty));
+        assert_eq!("<X2Drag>", mouse_input_string("X2Drag", 1, empty));
+        assert_eq!(
+            "<ScrollWheelLeft>",
+            mouse_input_string("ScrollWheelLeft", 1, empty)
+        );
+    }
+
+    #[test]
+    fn test_mouse_input_string_modifiers() {
+        assert_eq!(
+            "<C-LeftMouse>",
+            mouse_input_string("LeftMouse", 1, gdk::ModifierType::CONTROL_MASK)
+        );
+        assert_eq!(
+            "<S-ScrollWheelUp>",
+            mouse_input_string("ScrollWheelUp", 1, gdk::ModifierType::SHIFT_MASK)
+        );
+        assert_eq!(
+            "<C-2-LeftMouse>",
+            mouse_input_string("LeftMouse", 2, gdk::ModifierType::CONTROL_MASK)
+        );
+        assert_eq!(
+            "<S-C-A-RightMouse>",
+            mouse_input_string(
+                "RightMouse",
+                1,
+                gdk::ModifierType::SHIFT_MASK | gdk::ModifierType::CONTROL_MASK
+                    | gdk::ModifierType::MOD1_MASK
+            )
+        );
+        assert_eq!(
+            "<LeftDrag>",
+            mouse_input_string("LeftDrag", 1, gdk::ModifierType::BUTTON1_MASK)
+        );
+    }
+
+    #[test]
+    fn test_super_modifier() {
+        assert_eq!(
+            "<D-s>",
+            keyval_to_input_string("s", gdk::ModifierType::SUPER_MASK)
+        );
+        assert_eq!(
+            "<C-D-CR>",
+            keyval_to_input_string(
+                "CR",
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SUPER_MASK
+            )
+        );
+    }
+}
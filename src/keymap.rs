@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use gdk;
+use toml;
+
+use settings::SettingsLoader;
+
+/// Actions bound by default. Bindings in `keymap.toml` take precedence, binding a key
+/// to `""` in `[actions]` sends it to nvim again.
+const DEFAULT_ACTIONS: &[(&str, &str)] = &[
+    ("<C-p>", "win.quick-open"),
+    ("<C-S-p>", "win.command-palette"),
+];
+
+/// Modifiers that are taken into account when matching a key binding
+fn binding_modifiers(state: gdk::ModifierType) -> gdk::ModifierType {
+    let mut modifiers = state
+        & (gdk::ModifierType::SHIFT_MASK | gdk::ModifierType::CONTROL_MASK
+            | gdk::ModifierType::MOD1_MASK | gdk::ModifierType::SUPER_MASK);
+
+    // X11 reports Super as a real modifier only
+    if state.contains(gdk::ModifierType::MOD4_MASK) {
+        modifiers.insert(gdk::ModifierType::SUPER_MASK);
+    }
+
+    modifiers
+}
+
+/// GDK key combination in vim like notation: `<C-S-v>`, `<D-Return>`, `Caps_Lock`.
+///
+/// Key names are GDK keyval names, modifiers are `S` (shift), `C` (control),
+/// `A` or `M` (alt) and `D` (super).
+#[derive(Debug, PartialEq)]
+pub struct KeySpec {
+    modifiers: gdk::ModifierType,
+    key: String,
+}
+
+impl KeySpec {
+    pub fn parse(spec: &str) -> Option<KeySpec> {
+        if !(spec.len() > 2 && spec.starts_with('<') && spec.ends_with('>')) {
+            if spec.is_empty() {
+                return None;
+            }
+
+            return Some(KeySpec {
+                modifiers: gdk::ModifierType::empty(),
+                key: spec.to_owned(),
+            });
+        }
+
+        let mut parts: Vec<&str> = spec[1..spec.len() - 1].split('-').collect();
+        let key = parts.pop()?;
+        if key.is_empty() {
+            return None;
+        }
+
+        let mut modifiers = gdk::ModifierType::empty();
+        for part in parts {
+            match part {
+                "S" | "s" => modifiers.insert(gdk::ModifierType::SHIFT_MASK),
+                "C" | "c" => modifiers.insert(gdk::ModifierType::CONTROL_MASK),
+                "A" | "a" | "M" | "m" => modifiers.insert(gdk::ModifierType::MOD1_MASK),
+                "D" | "d" => modifiers.insert(gdk::ModifierType::SUPER_MASK),
+                _ => return None,
+            }
+        }
+
+        Some(KeySpec {
+            modifiers,
+            key: key.to_owned(),
+        })
+    }
+
+    /// Letters are compared case insensitive as shift is given explicitly
+    pub fn matches(&self, keyval_name: &str, state: gdk::ModifierType) -> bool {
+        self.modifiers == binding_modifiers(state) && self.key.eq_ignore_ascii_case(keyval_name)
+    }
+
+    fn is_same(&self, other: &KeySpec) -> bool {
+        self.modifiers == other.modifiers && self.key.eq_ignore_ascii_case(&other.key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyBinding {
+    /// Send given input to nvim instead of the key
+    Input(String),
+    /// Activate GTK action, like `app.new-window` or `win.paste`
+    Action(String),
+}
+
+pub struct Keymap {
+    super_as_cmd: bool,
+    ctrl_scroll_zoom: bool,
+    bindings: Vec<(KeySpec, KeyBinding)>,
+}
+
+impl Keymap {
+    pub fn load() -> Self {
+        Keymap::from_settings(Settings::load())
+    }
+
+    fn from_settings(settings: Settings) -> Self {
+        let mut bindings = Vec::new();
+        let mut unbound: Vec<KeySpec> = Vec::new();
+
+        let keys = settings
+            .keys
+            .into_iter()
+            .map(|(spec, input)| (spec, KeyBinding::Input(input)));
+        let actions = settings
+            .actions
+            .into_iter()
+            .map(|(spec, action)| (spec, KeyBinding::Action(action)));
+
+        for (spec, binding) in keys.chain(actions) {
+            match KeySpec::parse(&spec) {
+                Some(key_spec) => {
+                    if binding == KeyBinding::Action(String::new()) {
+                        unbound.push(key_spec);
+                    } else {
+                        bindings.push((key_spec, binding));
+                    }
+                }
+                None => error!("Can't parse key binding {}", spec),
+            }
+        }
+
+        // defaults come last, so bindings of keymap.toml are found first
+        let defaults = DEFAULT_ACTIONS
+            .iter()
+            .filter_map(|&(spec, action)| {
+                KeySpec::parse(spec).map(|spec| (spec, KeyBinding::Action(action.to_owned())))
+            })
+            .filter(|&(ref spec, _)| !unbound.iter().any(|key_spec| key_spec.is_same(spec)));
+        bindings.extend(defaults);
+
+        Keymap {
+            super_as_cmd: settings.super_as_cmd,
+            ctrl_scroll_zoom: settings.ctrl_scroll_zoom,
+            bindings,
+        }
+    }
+
+    /// Forward Super modifier to nvim as `<D-…>`
+    pub fn super_as_cmd(&self) -> bool {
+        self.super_as_cmd
+    }
+
+    /// Zoom the font with Ctrl+scroll instead of sending `<C-ScrollWheelUp>` to nvim
+    pub fn ctrl_scroll_zoom(&self) -> bool {
+        self.ctrl_scroll_zoom
+    }
+
+    pub fn lookup(&self, ev: &gdk::EventKey) -> Option<&KeyBinding> {
+        let keyval_name = gdk::keyval_name(ev.get_keyval())?;
+        self.find(&keyval_name, ev.get_state())
+    }
+
+    fn find(&self, keyval_name: &str, state: gdk::ModifierType) -> Option<&KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|&&(ref spec, _)| spec.matches(keyval_name, state))
+            .map(|&(_, ref binding)| binding)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    super_as_cmd: bool,
+    ctrl_scroll_zoom: bool,
+    keys: HashMap<String, String>,
+    actions: HashMap<String, String>,
+}
+
+impl Default for Settings {
+    /// Super is sent to nvim unless disabled, so `<D-…>` mappings work out of the box.
+    fn default() -> Self {
+        Settings {
+            super_as_cmd: true,
+            ctrl_scroll_zoom: true,
+            keys: HashMap::new(),
+            actions: HashMap::new(),
+        }
+    }
+}
+
+impl SettingsLoader for Settings {
+    const SETTINGS_FILE: &'static str = "keymap.toml";
+
+    fn empty() -> Self {
+        Settings::default()
+    }
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        toml::from_str(&s).map_err(|e| format!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_spec() {
+        assert_eq!(
+            Some(KeySpec {
+                modifiers: gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                key: "v".to_owned(),
+            }),
+            KeySpec::parse("<C-S-v>")
+        );
+        assert_eq!(
+            Some(KeySpec {
+                modifiers: gdk::ModifierType::empty(),
+                key: "Caps_Lock".to_owned(),
+            }),
+            KeySpec::parse("Caps_Lock")
+        );
+        assert_eq!(
+            Some(KeySpec {
+                modifiers: gdk::ModifierType::SUPER_MASK,
+                key: "Return".to_owned(),
+            }),
+            KeySpec::parse("<D-Return>")
+        );
+        assert_eq!(None, KeySpec::parse("<C->"));
+        assert_eq!(None, KeySpec::parse("<X-a>"));
+        assert_eq!(None, KeySpec::parse(""));
+    }
+
+    #[test]
+    fn test_key_spec_matches() {
+        let spec = KeySpec::parse("<C-S-v>").unwrap();
+        assert!(spec.matches(
+            "V",
+            gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK
+                | gdk::ModifierType::LOCK_MASK
+        ));
+        assert!(!spec.matches("V", gdk::ModifierType::CONTROL_MASK));
+        assert!(!spec.matches(
+            "c",
+            gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK
+        ));
+
+        let spec = KeySpec::parse("<D-n>").unwrap();
+        assert!(spec.matches("n", gdk::ModifierType::MOD4_MASK));
+        assert!(spec.matches("n", gdk::ModifierType::SUPER_MASK));
+    }
+
+    #[test]
+    fn test_keymap_from_settings() {
+        let settings = Settings::from_str(
+            r#"
+            [keys]
+            Caps_Lock = "<Esc>"
+
+            [actions]
+            "<C-S-n>" = "app.new-window"
+            "<X-n>" = "app.new-window"
+            "#,
+        ).unwrap();
+        let keymap = Keymap::from_settings(settings);
+
+        assert!(keymap.super_as_cmd());
+        assert!(keymap.ctrl_scroll_zoom());
+        assert_eq!(
+            Some(&KeyBinding::Input("<Esc>".to_owned())),
+            keymap.find("Caps_Lock", gdk::ModifierType::empty())
+        );
+        assert_eq!(
+            Some(&KeyBinding::Action("app.new-window".to_owned())),
+            keymap.find(
+                "N",
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK
+            )
+        );
+        assert_eq!(None, keymap.find("n", gdk::ModifierType::CONTROL_MASK));
+        assert_eq!(
+            Some(&KeyBinding::Action("win.quick-open".to_owned())),
+            keymap.find("p", gdk::ModifierType::CONTROL_MASK)
+        );
+        assert_eq!(
+            Some(&KeyBinding::Action("win.command-palette".to_owned())),
+            keymap.find(
+                "P",
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK
+            )
+        );
+    }
+
+    #[test]
+    fn test_default_actions() {
+        let settings = Settings::from_str(
+            r#"
+            ctrl_scroll_zoom = false
+
+            [actions]
+            "<C-P>" = ""
+            "#,
+        ).unwrap();
+        let keymap = Keymap::from_settings(settings);
+        assert_eq!(None, keymap.find("p", gdk::ModifierType::CONTROL_MASK));
+        assert!(!keymap.ctrl_scroll_zoom());
+
+        let settings = Settings::from_str(
+            r#"
+            [keys]
+            "<C-p>" = "<Up>"
+            "#,
+        ).unwrap();
+        let keymap = Keymap::from_settings(settings);
+        assert_eq!(
+            Some(&KeyBinding::Input("<Up>".to_owned())),
+            keymap.find("p", gdk::ModifierType::CONTROL_MASK)
+        );
+    }
+}
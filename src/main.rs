@@ -22,6 +22,7 @@ extern crate pangocairo;
 extern crate percent_encoding;
 extern crate phf;
 extern crate regex;
+extern crate reqwest;
 
 extern crate serde;
 #[macro_use]
@@ -45,16 +46,26 @@ mod nvim;
 mod render;
 mod shell;
 mod input;
+mod gesture;
+mod keymap;
 mod settings;
 mod cursor;
 mod shell_dlg;
 mod popup_menu;
 mod project;
+mod project_config;
+mod command_palette;
 mod tabline;
 mod error;
 mod file_browser;
+mod file_list;
+mod git;
+mod fuzzy;
+mod quick_open;
 mod subscriptions;
 mod misc;
+#[cfg(test)]
+mod test_util;
 
 use std::env;
 use std::time::Duration;
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::hash::Hasher;
 
 use regex::Regex;
 use percent_encoding::percent_decode;
@@ -17,6 +18,28 @@ pub fn escape_filename<'t>(filename: &'t str) -> Cow<'t, str> {
     SPECIAL_CHARS.replace_all(&*filename, r"\$0")
 }
 
+/// FNV-1a hash, unlike `DefaultHasher` it doesn't change between Rust releases.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 /// Decode a file URI.
 ///
 ///   - On UNIX: `file:///path/to/a%20file.ext` -> `/path/to/a file.ext`
@@ -4,6 +4,7 @@ use nvim;
 pub enum NvimMode {
     Normal,
     Insert,
+    Terminal,
     Other,
 }
 
@@ -11,6 +12,7 @@ pub struct Mode {
     mode: NvimMode,
     idx: usize,
     info: Option<Vec<nvim::ModeInfo>>,
+    im_active: bool,
 }
 
 impl Mode {
@@ -19,6 +21,7 @@ impl Mode {
             mode: NvimMode::Normal,
             idx: 0,
             info: None,
+            im_active: false,
         }
     }
 
@@ -30,10 +33,20 @@ impl Mode {
         self.info.as_ref().and_then(|i| i.get(self.idx))
     }
 
+    /// Whether an input method composes text, the cursor uses `CursorIM` then, like in Vim
+    pub fn im_active(&self) -> bool {
+        self.im_active
+    }
+
+    pub fn set_im_active(&mut self, im_active: bool) {
+        self.im_active = im_active;
+    }
+
     pub fn update(&mut self, mode: &str, idx: usize) {
         match mode {
             "normal" => self.mode = NvimMode::Normal,
             "insert" => self.mode = NvimMode::Insert,
+            "terminal" => self.mode = NvimMode::Terminal,
             _ => self.mode = NvimMode::Other,
         }
 
@@ -54,6 +54,7 @@ impl NvimHandler {
                     }
 
                     ui.on_redraw(&repaint_mode);
+                    ui.animate_cursor_move();
                     Ok(())
                 });
             }
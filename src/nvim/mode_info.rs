@@ -32,6 +32,14 @@ impl CursorShape {
 pub struct ModeInfo {
     cursor_shape: Option<CursorShape>,
     cell_percentage: Option<u64>,
+    blinkwait: Option<u64>,
+    blinkon: Option<u64>,
+    blinkoff: Option<u64>,
+    hl_id: Option<u64>,
+    /// `lCursor` highlight group, used when language mappings are active
+    id_lm: Option<u64>,
+    attr_id: Option<u64>,
+    attr_id_lm: Option<u64>,
 }
 
 impl ModeInfo {
@@ -50,9 +58,18 @@ impl ModeInfo {
             None
         };
 
+        let get_u64 = |name: &str| mode_info_map.get(name).and_then(|v| v.as_u64());
+
         Ok(ModeInfo {
             cursor_shape,
             cell_percentage,
+            blinkwait: get_u64("blinkwait"),
+            blinkon: get_u64("blinkon"),
+            blinkoff: get_u64("blinkoff"),
+            hl_id: get_u64("hl_id"),
+            id_lm: get_u64("id_lm"),
+            attr_id: get_u64("attr_id"),
+            attr_id_lm: get_u64("attr_id_lm"),
         })
     }
 
@@ -63,4 +80,59 @@ impl ModeInfo {
     pub fn cell_percentage(&self) -> u64 {
         self.cell_percentage.unwrap_or(0)
     }
+
+    /// Blink timings in milliseconds as (blinkwait, blinkon, blinkoff)
+    pub fn blink(&self) -> Option<(u64, u64, u64)> {
+        match (self.blinkwait, self.blinkon, self.blinkoff) {
+            (Some(wait), Some(on), Some(off)) => Some((wait, on, off)),
+            _ => None,
+        }
+    }
+
+    /// Highlight group of the cursor, `lCursor` one when `lang_mapping` is active.
+    ///
+    /// Zero if not set or the group has no attributes, so the default cursor is used.
+    pub fn hl_id(&self, lang_mapping: bool) -> u64 {
+        let (hl_id, attr_id) = if lang_mapping && self.id_lm.unwrap_or(0) > 0 {
+            (self.id_lm, self.attr_id_lm)
+        } else {
+            (self.hl_id, self.attr_id)
+        };
+
+        if attr_id == Some(0) {
+            0
+        } else {
+            hl_id.unwrap_or(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode_info(attrs: &[(&str, u64)]) -> ModeInfo {
+        let attrs = attrs
+            .iter()
+            .map(|&(name, value)| (Value::from(name), Value::from(value)))
+            .collect();
+        ModeInfo::new(&attrs).unwrap()
+    }
+
+    #[test]
+    fn test_hl_id() {
+        let info = mode_info(&[("hl_id", 10), ("attr_id", 3), ("id_lm", 11), ("attr_id_lm", 4)]);
+        assert_eq!(10, info.hl_id(false));
+        assert_eq!(11, info.hl_id(true));
+
+        // no lCursor in guicursor
+        let info = mode_info(&[("hl_id", 10), ("attr_id", 3), ("id_lm", 0)]);
+        assert_eq!(10, info.hl_id(true));
+
+        // cleared highlight group
+        let info = mode_info(&[("hl_id", 10), ("attr_id", 0)]);
+        assert_eq!(0, info.hl_id(false));
+
+        assert_eq!(0, mode_info(&[]).hl_id(false));
+    }
 }
@@ -6,6 +6,8 @@ use neovim_lib::neovim_api::Tabpage;
 
 use ui::UiMutex;
 use shell;
+use cursor::Animation;
+use tabline::TablineMode;
 use gtk::ClipboardExt;
 
 use value::ValueMapExt;
@@ -71,6 +73,10 @@ pub trait RedrawEvents {
 
 pub trait GuiApi {
     fn set_font(&mut self, font_desc: &str);
+
+    fn font_zoom(&mut self, step: i64);
+
+    fn font_zoom_reset(&mut self);
 }
 
 macro_rules! try_str {
@@ -114,6 +120,8 @@ pub fn call_gui_event(
 ) -> result::Result<(), String> {
     match method {
         "Font" => ui.set_font(try_str!(args[0])),
+        "FontZoom" => ui.font_zoom(try_int!(args[0])),
+        "FontReset" => ui.font_zoom_reset(),
         "Clipboard" => {
             match try_str!(args[0]) {
                 "Set" => {
@@ -143,6 +151,14 @@ pub fn call_gui_event(
                                 .map_err(|e| e.to_string())
                         })?
                 }
+                "TablineMode" => {
+                    ui.change_tabline_mode(TablineMode::from_name(try_str!(args[1]))?)
+                }
+                "CursorAnimation" => {
+                    let easing = args.get(2).and_then(|easing| easing.as_str());
+                    let trail = args.get(3).and_then(|trail| trail.as_u64()) == Some(1);
+                    ui.set_cursor_animation(Animation::new(try_uint!(args[1]), easing, trail)?);
+                }
                 opt => error!("Unknown option {}", opt),
             }
         }
@@ -0,0 +1,327 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use neovim_lib::{Neovim, NeovimApi, Value};
+
+use dirs;
+use misc::escape_filename;
+use nvim::ErrorReport;
+use value::ValueMapExt;
+
+use super::store::PlugInfo;
+use super::{dein, minpac, packages, vim_plug};
+
+/// Plugin manager that loads the plugins configured in the Plugins dialog.
+pub trait Backend {
+    /// Name shown in the Plugins dialog.
+    fn name(&self) -> &'static str;
+
+    /// Whether the running nvim uses this plugin manager.
+    fn is_loaded(&self, nvim: &mut Neovim) -> bool;
+
+    /// Plugins configured in the running nvim.
+    fn get_plugs(&self, nvim: &mut Neovim) -> Result<Vec<PlugInfo>, String>;
+
+    /// Directory the plugins are installed to, if the plugin manager needs one.
+    fn base_path(&self, _nvim: &mut Neovim) -> Option<String> {
+        None
+    }
+
+    /// Vim script that loads `plugs` at startup.
+    fn config_source(&self, plugs: &[PlugInfo], base_path: Option<&str>) -> String;
+
+    /// Directory `plug` is installed to.
+    fn plug_dir(
+        &self,
+        plug: &PlugInfo,
+        base_path: Option<&str>,
+        dirs: &NvimDirs,
+    ) -> Result<PathBuf, String>;
+
+    /// Directories of all plugins installed by this plugin manager.
+    fn installed_dirs(&self, base_path: Option<&str>, dirs: &NvimDirs) -> Vec<PathBuf>;
+}
+
+/// Config and data directories of nvim, plugin managers install below them.
+pub struct NvimDirs {
+    /// `stdpath('config')`
+    pub config: PathBuf,
+    /// `stdpath('data')`
+    pub data: PathBuf,
+}
+
+impl NvimDirs {
+    /// Directories of the attached nvim, the XDG defaults if nvim isn't attached.
+    ///
+    /// `NVIM_APPNAME` or Windows change them, so they are only guessed without nvim.
+    pub fn new(nvim: Option<&mut Neovim>) -> Result<Self, String> {
+        let (config, data) = match nvim {
+            Some(nvim) => (
+                eval_string(nvim, "exists('*stdpath') ? stdpath('config') : ''"),
+                eval_string(nvim, "exists('*stdpath') ? stdpath('data') : ''"),
+            ),
+            None => (None, None),
+        };
+
+        Ok(NvimDirs {
+            config: match config {
+                Some(config) => PathBuf::from(config),
+                None => dirs::get_nvim_config_dir()?,
+            },
+            data: match data {
+                Some(data) => PathBuf::from(data),
+                None => dirs::get_nvim_data_dir()?,
+            },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BackendKind {
+    #[serde(rename = "vim-plug")]
+    VimPlug,
+    #[serde(rename = "dein")]
+    Dein,
+    #[serde(rename = "minpac")]
+    Minpac,
+    #[serde(rename = "packages")]
+    Packages,
+}
+
+impl BackendKind {
+    /// All backends in the order they are detected.
+    ///
+    /// Native packages come last, as the other plugin managers install to packages too.
+    pub const ALL: [BackendKind; 4] = [
+        BackendKind::VimPlug,
+        BackendKind::Dein,
+        BackendKind::Minpac,
+        BackendKind::Packages,
+    ];
+
+    pub fn backend(&self) -> &'static Backend {
+        match *self {
+            BackendKind::VimPlug => &vim_plug::VimPlug,
+            BackendKind::Dein => &dein::Dein,
+            BackendKind::Minpac => &minpac::Minpac,
+            BackendKind::Packages => &packages::Packages,
+        }
+    }
+
+    /// Finds the plugin manager used by the running nvim.
+    pub fn detect(nvim: &mut Neovim) -> Option<BackendKind> {
+        BackendKind::ALL
+            .iter()
+            .find(|kind| kind.backend().is_loaded(nvim))
+            .cloned()
+    }
+}
+
+impl Default for BackendKind {
+    fn default() -> Self {
+        BackendKind::VimPlug
+    }
+}
+
+/// Evaluates a vim expression that returns a boolean.
+pub fn eval_bool(nvim: &mut Neovim, expr: &str) -> bool {
+    nvim.eval(expr)
+        .ok_and_report()
+        .and_then(|value| value.as_i64())
+        .map_or(false, |value| value > 0)
+}
+
+/// Evaluates a vim expression that returns a string, `None` if it is empty.
+pub fn eval_string(nvim: &mut Neovim, expr: &str) -> Option<String> {
+    nvim.eval(expr)
+        .ok_and_report()
+        .and_then(|value| value.as_str().map(str::to_owned))
+        .and_then(|value| if value.is_empty() { None } else { Some(value) })
+}
+
+/// Parses a dictionary of plugin names to attribute dictionaries, as returned by `dein#get()`
+/// and `minpac#getpluglist()`, into plugins sorted by name.
+///
+/// `url_attr` is the attribute that contains the url or the github `user/repo`.
+pub fn parse_plug_dict(plugs: &Value, url_attr: &str) -> Result<Vec<PlugInfo>, String> {
+    let plugs_map = plugs
+        .as_map()
+        .ok_or_else(|| "Plugins are not a dictionary".to_owned())?
+        .to_attrs_map()?;
+
+    let mut plugs: Vec<_> = plugs_map
+        .iter()
+        .filter_map(|(name, desc)| {
+            let desc = desc.as_map()?.to_attrs_map().ok()?;
+            let url = desc.get(url_attr).and_then(|url| url.as_str())?;
+            Some(PlugInfo::new((*name).to_owned(), plug_url(url)))
+        })
+        .collect();
+    plugs.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    Ok(plugs)
+}
+
+/// Url of a plugin given as url, local path or as github `user/repo`.
+pub fn plug_url(repo: &str) -> String {
+    if repo.contains("://") || repo.starts_with("git@") || Path::new(repo).is_absolute() {
+        repo.to_owned()
+    } else {
+        format!("https://github.com/{}", repo)
+    }
+}
+
+/// Directories `depth` levels below `root`.
+pub fn child_dirs(root: &Path, depth: usize) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for _ in 0..depth {
+        dirs = dirs.iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+    }
+    dirs.sort();
+    dirs
+}
+
+/// Expands `~` at the start of `path`, like vim does for paths in settings.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.starts_with("~/"), env::home_dir()) {
+        (true, Some(home)) => home.join(&path[2..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Vim script string literal.
+pub fn vim_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Vim script list of strings.
+pub fn vim_list(items: &[String]) -> String {
+    let items: Vec<_> = items.iter().map(|item| vim_string(item)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Vim script dictionary of already rendered values.
+pub fn vim_dict(entries: &[(&str, String)]) -> String {
+    let entries: Vec<_> = entries
+        .iter()
+        .map(|&(key, ref value)| format!("{}: {}", vim_string(key), value))
+        .collect();
+    format!("{{ {} }}", entries.join(", "))
+}
+
+/// Loads the optional package of `plug` on first use of its commands or filetypes.
+pub fn packadd_triggers(plug: &PlugInfo) -> String {
+    let packadd = format!("silent! packadd {}", escape_filename(&plug.name));
+    let mut builder = String::new();
+
+    for cmd in &plug.options.on {
+        builder += &format!(
+            "command! -nargs=* -bang {cmd} delcommand {cmd} | {packadd} | {cmd}<bang> <args>\n",
+            cmd = cmd,
+            packadd = packadd
+        );
+    }
+
+    if !plug.options.filetypes.is_empty() {
+        builder += &format!(
+            "autocmd FileType {} ++once {}\n",
+            plug.options.filetypes.join(","),
+            packadd
+        );
+    }
+
+    builder
+}
+
+/// Config snippets of the enabled plugins, sourced after the plugin manager configuration.
+pub fn config_snippets(plugs: &[PlugInfo]) -> String {
+    plugs
+        .iter()
+        .filter(|plug| plug.options.enabled)
+        .filter_map(|plug| {
+            plug.options
+                .config
+                .as_ref()
+                .map(|config| format!("\" {}\n{}\n", plug.name, config.trim_right()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plug_dict() {
+        let desc = |attr: &str, url: &str| {
+            Value::Map(vec![(Value::from(attr), Value::from(url))])
+        };
+        let plugs = Value::Map(vec![
+            (Value::from("vim-fugitive"), desc("repo", "tpope/vim-fugitive")),
+            (
+                Value::from("denite.nvim"),
+                desc("repo", "https://github.com/Shougo/denite.nvim"),
+            ),
+        ]);
+
+        let plugs = parse_plug_dict(&plugs, "repo").unwrap();
+        assert_eq!(2, plugs.len());
+        assert_eq!("denite.nvim", plugs[0].name);
+        assert_eq!("https://github.com/Shougo/denite.nvim", plugs[0].url);
+        assert_eq!("https://github.com/tpope/vim-fugitive", plugs[1].url);
+
+        assert!(parse_plug_dict(&Value::from(0), "repo").is_err());
+    }
+
+    #[test]
+    fn test_plug_url() {
+        assert_eq!("https://github.com/tpope/vim-fugitive", plug_url("tpope/vim-fugitive"));
+        assert_eq!("/srv/git/plugin.git", plug_url("/srv/git/plugin.git"));
+        assert_eq!("git@github.com:user/repo.git", plug_url("git@github.com:user/repo.git"));
+    }
+
+    #[test]
+    fn test_vim_string() {
+        assert_eq!("'it''s'", vim_string("it's"));
+    }
+
+    #[test]
+    fn test_vim_dict() {
+        assert_eq!(
+            "{ 'as': 'a', 'on': ['A', 'B'] }",
+            vim_dict(&[
+                ("as", vim_string("a")),
+                ("on", vim_list(&["A".to_owned(), "B".to_owned()])),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_packadd_triggers() {
+        let mut plug = PlugInfo::new(
+            "vim-fugitive".to_owned(),
+            "https://github.com/tpope/vim-fugitive".to_owned(),
+        );
+        plug.options.on = vec!["Git".to_owned()];
+        plug.options.filetypes = vec!["git".to_owned(), "gitcommit".to_owned()];
+        plug.options.config = Some("nmap <leader>g :Git<CR>\n".to_owned());
+
+        assert_eq!(
+            "command! -nargs=* -bang Git delcommand Git | silent! packadd vim-fugitive | Git<bang> <args>\n\
+             autocmd FileType git,gitcommit ++once silent! packadd vim-fugitive\n",
+            packadd_triggers(&plug)
+        );
+        assert_eq!(
+            "\" vim-fugitive\nnmap <leader>g :Git<CR>\n",
+            config_snippets(&[plug.clone()])
+        );
+
+        plug.options.enabled = false;
+        assert_eq!("", config_snippets(&[plug]));
+    }
+}
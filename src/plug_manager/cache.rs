@@ -0,0 +1,155 @@
+//! Disk cache of downloaded plugin information, so it's available offline.
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use dirs;
+use misc::FnvHasher;
+
+/// Entries that weren't updated for this long are removed, as every query has its own entry.
+const KEEP_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    /// Cache in the NeovimGtk cache directory.
+    pub fn open() -> Result<Self, String> {
+        Ok(Cache::new(dirs::get_app_cache_dir()?.join("plugins")))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut hasher = FnvHasher::default();
+        hasher.write(key.as_bytes());
+        self.dir.join(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Cached data of `key`, if it is younger than `max_age`.
+    pub fn get(&self, key: &str, max_age: Duration) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age < max_age {
+            fs::read(path).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Cached data of `key` regardless of its age.
+    pub fn get_stale(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(key)).ok()
+    }
+
+    pub fn put(&self, key: &str, data: &[u8]) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("{}", e))?;
+        fs::write(self.path(key), data).map_err(|e| format!("{}", e))?;
+        self.prune(KEEP_AGE);
+        Ok(())
+    }
+
+    /// Removes the entries that are at least `max_age` old.
+    fn prune(&self, max_age: Duration) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let now = SystemTime::now();
+        for entry in entries.filter_map(Result::ok) {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map_or(false, |modified| {
+                    now.duration_since(modified).unwrap_or_default() >= max_age
+                });
+            if expired {
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    error!("Can't remove cache entry {}: {}", entry.path().display(), e);
+                }
+            }
+        }
+    }
+
+    /// Returns fresh cached data or downloads it with `download`,
+    /// expired data is used when the download fails.
+    pub fn fetch<F>(&self, key: &str, max_age: Duration, download: F) -> Result<Vec<u8>, String>
+    where
+        F: FnOnce() -> Result<Vec<u8>, String>,
+    {
+        if let Some(data) = self.get(key, max_age) {
+            return Ok(data);
+        }
+
+        match download() {
+            Ok(data) => {
+                if let Err(e) = self.put(key, &data) {
+                    error!("Can't cache {}: {}", key, e);
+                }
+                Ok(data)
+            }
+            Err(e) => self.get_stale(key).ok_or(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_util::TempDir;
+
+    #[test]
+    fn test_fetch() {
+        let dir = TempDir::new("cache");
+        let cache = Cache::new(dir.join("cache"));
+        let day = Duration::from_secs(60 * 60 * 24);
+
+        assert_eq!(
+            Err("offline".to_owned()),
+            cache.fetch("key", day, || Err("offline".to_owned()))
+        );
+        assert_eq!(
+            Ok(b"data".to_vec()),
+            cache.fetch("key", day, || Ok(b"data".to_vec()))
+        );
+        // fresh data is not downloaded again
+        assert_eq!(
+            Ok(b"data".to_vec()),
+            cache.fetch("key", day, || panic!("Unexpected download"))
+        );
+        // expired data is used when offline
+        assert_eq!(None, cache.get("key", Duration::from_secs(0)));
+        assert_eq!(
+            Ok(b"data".to_vec()),
+            cache.fetch("key", Duration::from_secs(0), || Err("offline".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = TempDir::new("cache-prune");
+        let cache = Cache::new(dir.join("cache"));
+        cache.put("key", b"data").unwrap();
+
+        cache.prune(Duration::from_secs(60));
+        assert_eq!(Some(b"data".to_vec()), cache.get_stale("key"));
+        cache.prune(Duration::from_secs(0));
+        assert_eq!(None, cache.get_stale("key"));
+    }
+
+    #[test]
+    fn test_path_is_stable() {
+        let cache = Cache::new(PathBuf::from("cache"));
+        assert_eq!(PathBuf::from("cache").join("af63dc4c8601ec8c"), cache.path("a"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use neovim_lib::{Neovim, NeovimApi};
+
+use super::backend::{child_dirs, config_snippets, eval_bool, eval_string, expand_home,
+                     parse_plug_dict, vim_dict, vim_list, vim_string, Backend, NvimDirs};
+use super::store::PlugInfo;
+
+/// Base path suggested by the dein.vim installer.
+const DEFAULT_BASE_PATH: &str = "~/.cache/dein";
+
+pub struct Dein;
+
+impl Backend for Dein {
+    fn name(&self) -> &'static str {
+        "dein.vim"
+    }
+
+    fn is_loaded(&self, nvim: &mut Neovim) -> bool {
+        eval_bool(nvim, "exists('*dein#get')")
+    }
+
+    fn get_plugs(&self, nvim: &mut Neovim) -> Result<Vec<PlugInfo>, String> {
+        let plugs = nvim.eval("dein#get()")
+            .map_err(|e| format!("Can't retrive dein plugins: {}", e))?;
+        parse_plug_dict(&plugs, "repo")
+    }
+
+    fn base_path(&self, nvim: &mut Neovim) -> Option<String> {
+        eval_string(nvim, "get(g:, 'dein#_base_path', '')")
+    }
+
+    fn config_source(&self, plugs: &[PlugInfo], base_path: Option<&str>) -> String {
+        let base_path = base_path.unwrap_or(DEFAULT_BASE_PATH);
+        let mut builder = format!(
+            "let s:dein_dir = expand({})\n\
+             if &runtimepath !~# '/dein.vim'\n  \
+             execute 'set runtimepath+=' . fnameescape(s:dein_dir . '/repos/github.com/Shougo/dein.vim')\n\
+             endif\n\
+             call dein#begin(s:dein_dir)\n",
+            vim_string(base_path)
+        );
+
+        for plug in plugs {
+            let options = &plug.options;
+            let mut dict = vec![("name", vim_string(&plug.name))];
+            if let Some(rev) = options.rev() {
+                dict.push(("rev", vim_string(rev)));
+            }
+            if !options.on.is_empty() {
+                dict.push(("on_cmd", vim_list(&options.on)));
+            }
+            if !options.filetypes.is_empty() {
+                dict.push(("on_ft", vim_list(&options.filetypes)));
+            }
+            if !options.enabled {
+                dict.push(("if", "0".to_owned()));
+            }
+            if let Some(cmd) = options.post_install_shell_cmd() {
+                dict.push(("build", vim_string(cmd)));
+            }
+            if let Some(cmd) = options.post_install_vim_cmd() {
+                dict.push(("hook_post_update", vim_string(cmd)));
+            }
+
+            builder += &format!(
+                "call dein#add({}, {})\n",
+                vim_string(&plug.url),
+                vim_dict(&dict)
+            );
+        }
+
+        builder += "call dein#end()\n";
+        builder += &config_snippets(plugs);
+        builder
+    }
+
+    fn plug_dir(
+        &self,
+        plug: &PlugInfo,
+        base_path: Option<&str>,
+        _dirs: &NvimDirs,
+    ) -> Result<PathBuf, String> {
+        Ok(repos_dir(base_path).join(repo_dir(&plug.url)))
+    }
+
+    fn installed_dirs(&self, base_path: Option<&str>, _dirs: &NvimDirs) -> Vec<PathBuf> {
+        let repos_dir = repos_dir(base_path);
+        let dein_dir = repos_dir.join(repo_dir("https://github.com/Shougo/dein.vim"));
+        let mut dirs = child_dirs(&repos_dir, 3);
+        dirs.retain(|dir| *dir != dein_dir);
+        dirs
+    }
+}
+
+fn repos_dir(base_path: Option<&str>) -> PathBuf {
+    expand_home(base_path.unwrap_or(DEFAULT_BASE_PATH)).join("repos")
+}
+
+/// Directory of a repository below `repos`, like `github.com/Shougo/dein.vim`.
+fn repo_dir(url: &str) -> PathBuf {
+    let path = match url.find("://") {
+        Some(idx) => &url[idx + 3..],
+        None => url.trim_left_matches("git@"),
+    };
+    // drop user info like in `https://git::@github.com/…`
+    let path = match (path.find('@'), path.find('/')) {
+        (Some(at), Some(slash)) if at < slash => &path[at + 1..],
+        _ => path,
+    };
+    let path = path.trim_right_matches(".git").replacen(':', "/", 1);
+    Path::new(path.trim_left_matches('/')).to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_source() {
+        let plugs = vec![
+            PlugInfo::new(
+                "denite.nvim".to_owned(),
+                "https://github.com/Shougo/denite.nvim".to_owned(),
+            ),
+        ];
+        let source = Dein.config_source(&plugs, Some("/home/user/.dein"));
+        assert!(source.starts_with("let s:dein_dir = expand('/home/user/.dein')\n"));
+        assert!(source.contains(
+            "call dein#begin(s:dein_dir)\n\
+             call dein#add('https://github.com/Shougo/denite.nvim', { 'name': 'denite.nvim' })\n\
+             call dein#end()\n"
+        ));
+    }
+
+    #[test]
+    fn test_config_source_options() {
+        let mut plug = PlugInfo::new(
+            "denite.nvim".to_owned(),
+            "https://github.com/Shougo/denite.nvim".to_owned(),
+        );
+        plug.options.branch = Some("dev".to_owned());
+        plug.options.on = vec!["Denite".to_owned()];
+        plug.options.post_install = Some(":UpdateRemotePlugins".to_owned());
+        assert!(Dein.config_source(&[plug], None).contains(
+            "call dein#add('https://github.com/Shougo/denite.nvim', { 'name': 'denite.nvim', \
+             'rev': 'dev', 'on_cmd': ['Denite'], 'hook_post_update': 'UpdateRemotePlugins' })\n"
+        ));
+    }
+
+    #[test]
+    fn test_repo_dir() {
+        assert_eq!(
+            PathBuf::from("github.com/Shougo/denite.nvim"),
+            repo_dir("https://github.com/Shougo/denite.nvim.git")
+        );
+        assert_eq!(
+            PathBuf::from("github.com/rust-lang/rust.vim"),
+            repo_dir("https://git::@github.com/rust-lang/rust.vim.git")
+        );
+        assert_eq!(
+            PathBuf::from("github.com/user/repo"),
+            repo_dir("git@github.com:user/repo.git")
+        );
+    }
+}
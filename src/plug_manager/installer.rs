@@ -0,0 +1,417 @@
+//! Installs, updates and cleans plugins with git, independent of the plugin manager.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::thread;
+
+use glib;
+
+use git;
+
+use super::backend::plug_url;
+use super::store::PlugOptions;
+
+/// Plugin to install or update.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub name: String,
+    pub url: String,
+    pub dir: PathBuf,
+    /// Commit to check out on restore.
+    pub commit: Option<String>,
+    pub options: PlugOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Install,
+    Update,
+    /// Checks out the commits of a lockfile.
+    Restore,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Plugin was cloned at the given commit.
+    Installed(String),
+    AlreadyInstalled,
+    Updated(Update),
+    UpToDate,
+    /// Plugin was checked out at the given commit.
+    Restored(String),
+}
+
+/// Commits pulled by an update.
+#[derive(Debug, PartialEq)]
+pub struct Update {
+    /// `hash subject` of each commit, newest first.
+    pub log: Vec<String>,
+    /// `git diff --stat` between the old and the new commit.
+    pub diff: String,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Started(String),
+    Finished(String, Result<Outcome, String>),
+    Done,
+}
+
+/// Runs `action` for all `jobs` in background, `cb` is called on the main thread for each step.
+pub fn run<F>(action: Action, jobs: Vec<Job>, cb: F)
+where
+    F: Fn(Event) + Send + Sync + 'static,
+{
+    let cb = Arc::new(cb);
+    thread::spawn(move || {
+        for job in jobs {
+            notify(cb.clone(), Event::Started(job.name.clone()));
+            let result = match action {
+                Action::Install => install(&job),
+                Action::Update => update(&job),
+                Action::Restore => restore(&job),
+            };
+            notify(cb.clone(), Event::Finished(job.name, result));
+        }
+        // The last reference is moved, so `cb` is dropped on the main thread.
+        notify(cb, Event::Done);
+    });
+}
+
+/// Removes `dirs` in background and calls `cb` on the main thread with the errors.
+pub fn run_clean<F>(dirs: Vec<PathBuf>, cb: F)
+where
+    F: FnOnce(Vec<String>) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut errors = Some(clean(&dirs));
+        let mut cb = Some(cb);
+
+        glib::idle_add(move || {
+            cb.take().unwrap()(errors.take().unwrap());
+            glib::Continue(false)
+        })
+    });
+}
+
+fn notify<F>(cb: Arc<F>, event: Event)
+where
+    F: Fn(Event) + Send + Sync + 'static,
+{
+    let mut event = Some(event);
+    glib::idle_add(move || {
+        cb(event.take().unwrap());
+        glib::Continue(false)
+    });
+}
+
+fn is_installed(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// Clones the plugin, if it isn't installed yet.
+pub fn install(job: &Job) -> Result<Outcome, String> {
+    if is_installed(&job.dir) {
+        return Ok(Outcome::AlreadyInstalled);
+    }
+
+    let parent = job.dir
+        .parent()
+        .ok_or_else(|| format!("Wrong plugin directory {}", job.dir.display()))?;
+    fs::create_dir_all(parent).map_err(|e| format!("{}", e))?;
+    let dir = job.dir.to_string_lossy();
+    let url = plug_url(&job.url);
+    let mut args = vec!["clone", "--quiet"];
+    // `--branch` accepts tags too
+    if let Some(branch) = job.options.tag.as_ref().or_else(|| job.options.branch.as_ref()) {
+        args.extend(&["--branch", branch.as_str()]);
+    }
+    args.extend(&[url.as_str(), &dir]);
+    git::git(parent, &args)?;
+
+    if let Some(ref commit) = job.options.commit {
+        git::git(&job.dir, &["checkout", "--quiet", commit])?;
+    }
+    run_post_install(job)?;
+
+    Ok(Outcome::Installed(head(&job.dir)?))
+}
+
+/// Pulls new commits of the plugin, installs it if it isn't installed yet.
+pub fn update(job: &Job) -> Result<Outcome, String> {
+    if !is_installed(&job.dir) {
+        return install(job);
+    }
+
+    let old_head = head(&job.dir)?;
+    let pinned = job.options
+        .commit
+        .as_ref()
+        .or_else(|| job.options.tag.as_ref());
+    match pinned {
+        Some(rev) => {
+            git::git(&job.dir, &["fetch", "--quiet", "--tags", "origin"])?;
+            git::git(&job.dir, &["checkout", "--quiet", rev])?;
+        }
+        None => {
+            checkout_branch(&job.dir, job.options.branch.as_ref().map(String::as_str))?;
+            git::git(&job.dir, &["pull", "--quiet", "--ff-only"])?;
+        }
+    }
+    let new_head = head(&job.dir)?;
+    if old_head == new_head {
+        return Ok(Outcome::UpToDate);
+    }
+    run_post_install(job)?;
+
+    let range = format!("{}..{}", old_head, new_head);
+    let log = git::git(&job.dir, &["log", "--format=%h %s", &range])?;
+    let diff = git::git(&job.dir, &["diff", "--stat", &range])?;
+    Ok(Outcome::Updated(Update {
+        log: String::from_utf8_lossy(&log)
+            .lines()
+            .map(str::to_owned)
+            .collect(),
+        diff: String::from_utf8_lossy(&diff).into_owned(),
+    }))
+}
+
+/// Checks out the commit of the job, installs the plugin if it isn't installed yet.
+pub fn restore(job: &Job) -> Result<Outcome, String> {
+    let commit = job.commit
+        .as_ref()
+        .ok_or_else(|| format!("No locked commit for {}", job.name))?;
+    if !is_installed(&job.dir) {
+        install(job)?;
+    }
+
+    let object = format!("{}^{{commit}}", commit);
+    if git::git(&job.dir, &["cat-file", "-e", &object]).is_err() {
+        git::git(&job.dir, &["fetch", "--quiet", "origin"])?;
+    }
+    git::git(&job.dir, &["checkout", "--quiet", commit])?;
+
+    Ok(Outcome::Restored(head(&job.dir)?))
+}
+
+/// Checks out the pinned branch, or the remote default branch when restore or
+/// a removed pin left a detached HEAD.
+fn checkout_branch(dir: &Path, branch: Option<&str>) -> Result<(), String> {
+    if let Some(branch) = branch {
+        git::git(dir, &["fetch", "--quiet", "origin"])?;
+        git::git(dir, &["checkout", "--quiet", branch])?;
+        return Ok(());
+    }
+    if git::git(dir, &["symbolic-ref", "--quiet", "HEAD"]).is_ok() {
+        return Ok(());
+    }
+
+    let out = git::git(dir, &["rev-parse", "--abbrev-ref", "origin/HEAD"])?;
+    let remote_branch = String::from_utf8_lossy(&out).trim().to_owned();
+    let branch = remote_branch.trim_left_matches("origin/");
+    git::git(dir, &["checkout", "--quiet", branch])?;
+    Ok(())
+}
+
+/// Runs the shell command hook, vim command hooks are run by nvim.
+fn run_post_install(job: &Job) -> Result<(), String> {
+    let cmd = match job.options.post_install_shell_cmd() {
+        Some(cmd) => cmd,
+        None => return Ok(()),
+    };
+
+    let out = shell_command(cmd)
+        .current_dir(&job.dir)
+        .output()
+        .map_err(|e| format!("Can't run post-install hook `{}`: {}", cmd, e))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Post-install hook `{}` failed: {}",
+            cmd,
+            String::from_utf8_lossy(&out.stderr)
+        ))
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(cmd: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+#[cfg(windows)]
+fn shell_command(cmd: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(cmd);
+    command
+}
+
+fn head(dir: &Path) -> Result<String, String> {
+    let out = git::git(dir, &["rev-parse", "--short", "HEAD"])?;
+    Ok(String::from_utf8_lossy(&out).trim().to_owned())
+}
+
+/// Installed plugin directories that don't belong to any of `jobs`.
+///
+/// Backends list installed directories to a fixed depth, so parents and children
+/// of a plugin directory are kept too.
+pub fn unused_dirs(installed: Vec<PathBuf>, jobs: &[Job]) -> Vec<PathBuf> {
+    installed
+        .into_iter()
+        .filter(|dir| {
+            !jobs.iter()
+                .any(|job| job.dir.starts_with(dir) || dir.starts_with(&job.dir))
+        })
+        .collect()
+}
+
+/// Removes plugin directories, returns the errors.
+pub fn clean(dirs: &[PathBuf]) -> Vec<String> {
+    dirs.iter()
+        .filter_map(|dir| {
+            fs::remove_dir_all(dir)
+                .err()
+                .map(|e| format!("Can't remove {}: {}", dir.display(), e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_util::TempDir;
+
+    fn commit(work_dir: &Path, file: &str, message: &str) {
+        fs::write(work_dir.join(file), message).unwrap();
+        git::git(work_dir, &["add", file]).unwrap();
+        git::git(
+            work_dir,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                message,
+            ],
+        ).unwrap();
+        git::git(work_dir, &["push", "--quiet", "origin", "HEAD"]).unwrap();
+    }
+
+    #[test]
+    fn test_install_update_clean() {
+        let dir = TempDir::new("installer");
+        let remote = dir.join("plugin.git");
+        let work_dir = dir.join("work");
+        git::git(&dir, &["init", "--quiet", "--bare", "plugin.git"]).unwrap();
+        git::git(&dir, &["clone", "--quiet", "plugin.git", "work"]).unwrap();
+        commit(&work_dir, "plugin.vim", "Add plugin");
+
+        let job = Job {
+            name: "plugin".to_owned(),
+            url: remote.to_string_lossy().into_owned(),
+            dir: dir.join("plugged").join("plugin"),
+            commit: None,
+            options: PlugOptions::default(),
+        };
+        match install(&job) {
+            Ok(Outcome::Installed(_)) => (),
+            res => panic!("Unexpected install result {:?}", res),
+        }
+        assert!(job.dir.join("plugin.vim").is_file());
+        assert_eq!(Ok(Outcome::AlreadyInstalled), install(&job));
+        assert_eq!(Ok(Outcome::UpToDate), update(&job));
+
+        commit(&work_dir, "README.md", "Add readme");
+        match update(&job) {
+            Ok(Outcome::Updated(update)) => {
+                assert_eq!(1, update.log.len());
+                assert!(update.log[0].ends_with(" Add readme"));
+                assert!(update.diff.contains("README.md"));
+            }
+            res => panic!("Unexpected update result {:?}", res),
+        }
+
+        let previous = git::git(&job.dir, &["rev-parse", "HEAD~1"]).unwrap();
+        let restore_job = Job {
+            commit: Some(String::from_utf8_lossy(&previous).trim().to_owned()),
+            ..job.clone()
+        };
+        match restore(&restore_job) {
+            Ok(Outcome::Restored(_)) => (),
+            res => panic!("Unexpected restore result {:?}", res),
+        }
+        assert!(!job.dir.join("README.md").exists());
+        match update(&job) {
+            Ok(Outcome::Updated(_)) => (),
+            res => panic!("Unexpected update after restore result {:?}", res),
+        }
+        assert!(job.dir.join("README.md").is_file());
+
+        let mut pinned = Job {
+            name: "pinned".to_owned(),
+            dir: dir.join("plugged").join("pinned"),
+            ..job.clone()
+        };
+        pinned.options.commit = restore_job.commit.clone();
+        pinned.options.post_install = Some("touch built".to_owned());
+        match install(&pinned) {
+            Ok(Outcome::Installed(_)) => (),
+            res => panic!("Unexpected pinned install result {:?}", res),
+        }
+        assert!(!pinned.dir.join("README.md").exists());
+        assert!(pinned.dir.join("built").is_file());
+        assert_eq!(Ok(Outcome::UpToDate), update(&pinned));
+
+        let unused = dir.join("plugged").join("removed");
+        fs::create_dir_all(&unused).unwrap();
+        let installed = vec![job.dir.clone(), pinned.dir.clone(), unused.clone()];
+        let unused_dirs = unused_dirs(installed, &[job.clone(), pinned]);
+        assert_eq!(vec![unused.clone()], unused_dirs);
+        assert!(clean(&unused_dirs).is_empty());
+        assert!(!unused.exists());
+    }
+
+    #[test]
+    fn test_unused_dirs() {
+        let repos = PathBuf::from("/repos");
+        let job = Job {
+            name: "plugin".to_owned(),
+            url: "https://example.com/user/plugin".to_owned(),
+            dir: repos.join("example.com").join("user").join("plugin"),
+            commit: None,
+            options: PlugOptions::default(),
+        };
+        let installed = vec![
+            repos.join("example.com").join("user"),
+            job.dir.clone(),
+            job.dir.join("autoload"),
+            repos.join("example.com").join("other"),
+        ];
+        assert_eq!(
+            vec![repos.join("example.com").join("other")],
+            unused_dirs(installed, &[job])
+        );
+    }
+
+    #[test]
+    fn test_install_error() {
+        let dir = TempDir::new("installer-error");
+        let job = Job {
+            name: "missing".to_owned(),
+            url: dir.join("missing.git").to_string_lossy().into_owned(),
+            dir: dir.join("plugged").join("missing"),
+            commit: None,
+            options: PlugOptions::default(),
+        };
+        assert!(install(&job).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Lockfile with the exact commit of each installed plugin.
+
+use std::fs;
+use std::path::Path;
+
+use toml;
+
+use git;
+
+use super::backend::plug_url;
+use super::installer::Job;
+use super::store::PlugInfo;
+
+/// Suggested file name for exported lockfiles.
+pub const LOCKFILE_NAME: &str = "plugs.lock";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub plugs: Vec<LockedPlug>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LockedPlug {
+    pub name: String,
+    pub url: String,
+    pub commit: String,
+}
+
+impl Lockfile {
+    /// Records the checked out commit of each plugin, all of them must be installed.
+    pub fn record(jobs: &[Job]) -> Result<Self, String> {
+        let plugs = jobs.iter()
+            .map(|job| {
+                if !job.dir.join(".git").exists() {
+                    return Err(format!(
+                        "Plugin {} is not installed, install it before exporting a lockfile",
+                        job.name
+                    ));
+                }
+                let out = git::git(&job.dir, &["rev-parse", "HEAD"])?;
+                Ok(LockedPlug {
+                    name: job.name.clone(),
+                    url: job.url.clone(),
+                    commit: String::from_utf8_lossy(&out).trim().to_owned(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Lockfile { plugs })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Can't read lockfile {}: {}", path.display(), e))?;
+        Lockfile::from_str(&contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_string()?)
+            .map_err(|e| format!("Can't write lockfile {}: {}", path.display(), e))
+    }
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|e| format!("Wrong lockfile: {}", e))
+    }
+
+    fn to_string(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| format!("{}", e))
+    }
+
+    /// Commit of the plugin `name` cloned from `url`.
+    ///
+    /// A plugin locked with another url is an error, as its commit is from another repository.
+    pub fn commit(&self, name: &str, url: &str) -> Result<Option<&str>, String> {
+        match self.plugs.iter().find(|plug| plug.name == name) {
+            Some(plug) if plug_url(&plug.url) == plug_url(url) => Ok(Some(&plug.commit)),
+            Some(plug) => Err(format!(
+                "Plugin {} is locked from {}, but the plugin list has {}",
+                name, plug.url, url
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn plugs_info(&self) -> Vec<PlugInfo> {
+        self.plugs
+            .iter()
+            .map(|plug| PlugInfo::new(plug.name.clone(), plug.url.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::store::PlugOptions;
+    use test_util::TempDir;
+
+    #[test]
+    fn test_to_from_str() {
+        let lockfile = Lockfile {
+            plugs: vec![
+                LockedPlug {
+                    name: "rust.vim".to_owned(),
+                    url: "https://github.com/rust-lang/rust.vim".to_owned(),
+                    commit: "e52f3a8c3ac1fe4a3bae6c4cb9e2dd9dbae3e4c8".to_owned(),
+                },
+            ],
+        };
+        let s = lockfile.to_string().unwrap();
+        assert_eq!(lockfile, Lockfile::from_str(&s).unwrap());
+        assert_eq!(
+            Ok(Some("e52f3a8c3ac1fe4a3bae6c4cb9e2dd9dbae3e4c8")),
+            lockfile.commit("rust.vim", "rust-lang/rust.vim")
+        );
+        assert!(lockfile.commit("rust.vim", "https://github.com/fork/rust.vim").is_err());
+        assert_eq!(Ok(None), lockfile.commit("vim-fugitive", "tpope/vim-fugitive"));
+        assert_eq!("rust.vim", lockfile.plugs_info()[0].name);
+    }
+
+    #[test]
+    fn test_record_not_installed() {
+        let dir = TempDir::new("lockfile");
+        let job = Job {
+            name: "missing".to_owned(),
+            url: "https://github.com/user/missing".to_owned(),
+            dir: dir.join("missing"),
+            commit: None,
+            options: PlugOptions::default(),
+        };
+        assert!(Lockfile::record(&[job]).is_err());
+    }
+}
@@ -1,12 +1,17 @@
+use std::path::PathBuf;
 use std::rc::Rc;
 
-use super::vim_plug;
+use neovim_lib::NeovimApiAsync;
+
+use super::backend::{BackendKind, NvimDirs};
+use super::installer;
+use super::lockfile::Lockfile;
 use super::store::{Store, PlugInfo};
 
-use nvim::NeovimClient;
+use nvim::{NeovimClient, ErrorReport, NeovimRef};
 
 pub struct Manager {
-    pub vim_plug: vim_plug::Manager,
+    nvim: Option<Rc<NeovimClient>>,
     pub store: Store,
     pub plug_manage_state: PlugManageState,
 }
@@ -20,7 +25,7 @@ impl Manager {
         };
 
         Manager {
-            vim_plug: vim_plug::Manager::new(),
+            nvim: None,
             plug_manage_state,
             store,
         }
@@ -35,43 +40,121 @@ impl Manager {
     }
 
     pub fn init_nvim_client(&mut self, nvim: Rc<NeovimClient>) {
-        self.vim_plug.initialize(nvim);
+        self.nvim = Some(nvim);
+    }
+
+    fn nvim(&self) -> Option<NeovimRef> {
+        self.nvim.as_ref().and_then(|nvim| nvim.nvim())
     }
 
+    /// Loads the NeovimGtk settings, or the plugins of the plugin manager nvim uses.
     pub fn reload_store(&mut self) {
-        match self.plug_manage_state {
-            PlugManageState::Unknown => {
-                if self.vim_plug.is_loaded() {
-                    self.store = Store::load_from_plug(&self.vim_plug);
-                    self.plug_manage_state = PlugManageState::VimPlug;
-                } else {
-                    self.store = Store::empty();
-                }
-            }
-            PlugManageState::NvimGtk => {
-                if Store::is_config_exists() {
-                    self.store = Store::load();
-                } else {
-                    self.store = Store::empty();
-                }
+        if Store::is_config_exists() {
+            self.store = Store::load();
+            self.plug_manage_state = PlugManageState::NvimGtk;
+            return;
+        }
+
+        let detected = self.nvim().and_then(|mut nvim| {
+            BackendKind::detect(&mut nvim).map(|backend_kind| {
+                (backend_kind, Store::load_from_backend(backend_kind, &mut nvim))
+            })
+        });
+        match detected {
+            Some((backend_kind, store)) => {
+                self.store = store;
+                self.plug_manage_state = PlugManageState::Detected(backend_kind);
             }
-            PlugManageState::VimPlug => {
-                if Store::is_config_exists() {
-                    self.store = Store::load();
-                    self.plug_manage_state = PlugManageState::NvimGtk;
-                } else {
-                    self.store = Store::empty();
-                }
+            None => {
+                self.store = Store::empty();
+                self.plug_manage_state = PlugManageState::Unknown;
             }
         }
-        if let PlugManageState::Unknown = self.plug_manage_state {
-            if self.vim_plug.is_loaded() {
-                self.store = Store::load_from_plug(&self.vim_plug);
-                self.plug_manage_state = PlugManageState::VimPlug;
-            }
+    }
+
+    pub fn reload(&self, path: &str) {
+        self.command(&format!("source {}", path));
+    }
+
+    /// Runs an Ex command in nvim, like vim command post-install hooks.
+    pub fn command(&self, cmd: &str) {
+        if let Some(mut nvim) = self.nvim() {
+            nvim.command_async(cmd).cb(|r| r.report_err()).call()
+        }
+    }
+
+    /// Config and data directories of the attached nvim.
+    fn nvim_dirs(&self) -> Result<NvimDirs, String> {
+        match self.nvim() {
+            Some(mut nvim) => NvimDirs::new(Some(&mut *nvim)),
+            None => NvimDirs::new(None),
         }
     }
 
+    /// Install or update jobs of the plugins that are not removed.
+    pub fn jobs(&self) -> Result<Vec<installer::Job>, String> {
+        let backend = self.store.backend_kind().backend();
+        let dirs = self.nvim_dirs()?;
+        self.store
+            .get_plugs()
+            .iter()
+            .filter(|plug| !plug.removed)
+            .map(|plug| {
+                Ok(installer::Job {
+                    name: plug.name.clone(),
+                    url: plug.url.clone(),
+                    dir: backend.plug_dir(plug, self.store.base_path(), &dirs)?,
+                    commit: None,
+                    options: plug.options.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Restore jobs of the plugins in the list that have a commit in `lockfile`.
+    ///
+    /// Fails if a plugin is locked with another url than the one in the list.
+    pub fn restore_jobs(&self, lockfile: &Lockfile) -> Result<Vec<installer::Job>, String> {
+        let mut errors = Vec::new();
+        let jobs = self.jobs()?
+            .into_iter()
+            .filter_map(|mut job| match lockfile.commit(&job.name, &job.url) {
+                Ok(commit) => {
+                    job.commit = commit.map(str::to_owned);
+                    if job.commit.is_some() {
+                        Some(job)
+                    } else {
+                        None
+                    }
+                }
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(jobs)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Lockfile with the commits of the installed plugins in the list.
+    pub fn lockfile(&self) -> Result<Lockfile, String> {
+        Lockfile::record(&self.jobs()?)
+    }
+
+    /// Installed plugins that are not in the plugin list.
+    pub fn unused_dirs(&self) -> Result<Vec<PathBuf>, String> {
+        let installed = self.store
+            .backend_kind()
+            .backend()
+            .installed_dirs(self.store.base_path(), &self.nvim_dirs()?);
+        Ok(installer::unused_dirs(installed, &self.jobs()?))
+    }
+
     pub fn save(&self) {
         self.store.save();
     }
@@ -91,7 +174,8 @@ impl Manager {
 
 pub enum PlugManageState {
     NvimGtk,
-    VimPlug,
+    /// Plugins are loaded from the plugin manager nvim was started with.
+    Detected(BackendKind),
     Unknown,
 }
 
@@ -102,20 +186,6 @@ pub struct PlugManagerConfigSource {
 
 impl PlugManagerConfigSource {
     pub fn new(store: &Store) -> Self {
-        let mut builder = "call plug#begin()\n".to_owned();
-
-        for plug in store.get_plugs() {
-            if !plug.removed {
-                builder += &format!(
-                    "Plug '{}', {{ 'as': '{}' }}\n",
-                    plug.get_plug_path(),
-                    plug.name
-                );
-            }
-        }
-
-        builder += "call plug#end()\n";
-
-        PlugManagerConfigSource { source: builder }
+        PlugManagerConfigSource { source: store.config_source() }
     }
 }
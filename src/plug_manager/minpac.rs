@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use neovim_lib::{Neovim, NeovimApi};
+
+use super::backend::{child_dirs, config_snippets, eval_bool, packadd_triggers, parse_plug_dict,
+                     vim_dict, vim_string, Backend, NvimDirs};
+use super::store::PlugInfo;
+
+pub struct Minpac;
+
+impl Backend for Minpac {
+    fn name(&self) -> &'static str {
+        "minpac"
+    }
+
+    fn is_loaded(&self, nvim: &mut Neovim) -> bool {
+        eval_bool(nvim, "exists('*minpac#getpluglist')")
+    }
+
+    fn get_plugs(&self, nvim: &mut Neovim) -> Result<Vec<PlugInfo>, String> {
+        let plugs = nvim.eval("minpac#getpluglist()")
+            .map_err(|e| format!("Can't retrive minpac plugins: {}", e))?;
+        let mut plugs = parse_plug_dict(&plugs, "url")?;
+        // minpac manages itself as an optional package
+        plugs.retain(|plug| plug.name != "minpac");
+        Ok(plugs)
+    }
+
+    fn config_source(&self, plugs: &[PlugInfo], _base_path: Option<&str>) -> String {
+        let mut builder = "packadd minpac\n\
+                           call minpac#init()\n\
+                           call minpac#add('k-takata/minpac', { 'type': 'opt' })\n"
+            .to_owned();
+
+        for plug in plugs {
+            let options = &plug.options;
+            let mut dict = vec![("name", vim_string(&plug.name))];
+            if is_opt(plug) {
+                dict.push(("type", vim_string("opt")));
+            }
+            if let Some(ref branch) = options.branch {
+                dict.push(("branch", vim_string(branch)));
+            }
+            if let Some(rev) = options.commit.as_ref().or_else(|| options.tag.as_ref()) {
+                dict.push(("rev", vim_string(rev)));
+            }
+            // minpac runs `do` as an Ex command
+            if let Some(cmd) = options.post_install_vim_cmd() {
+                dict.push(("do", vim_string(cmd)));
+            } else if let Some(cmd) = options.post_install_shell_cmd() {
+                dict.push(("do", vim_string(&format!("silent !{}", cmd))));
+            }
+
+            builder += &format!(
+                "call minpac#add({}, {})\n",
+                vim_string(&plug.url),
+                vim_dict(&dict)
+            );
+        }
+
+        for plug in plugs.iter().filter(|plug| plug.options.enabled) {
+            builder += &packadd_triggers(plug);
+        }
+
+        builder += &config_snippets(plugs);
+        builder
+    }
+
+    fn plug_dir(
+        &self,
+        plug: &PlugInfo,
+        _base_path: Option<&str>,
+        dirs: &NvimDirs,
+    ) -> Result<PathBuf, String> {
+        let kind = if is_opt(plug) { "opt" } else { "start" };
+        Ok(pack_dir(dirs).join(kind).join(&plug.name))
+    }
+
+    fn installed_dirs(&self, _base_path: Option<&str>, dirs: &NvimDirs) -> Vec<PathBuf> {
+        let pack_dir = pack_dir(dirs);
+        let minpac_dir = pack_dir.join("opt").join("minpac");
+        let mut dirs = child_dirs(&pack_dir, 2);
+        dirs.retain(|dir| *dir != minpac_dir);
+        dirs
+    }
+}
+
+/// Lazy and disabled plugins are optional packages, so nvim doesn't load them at startup.
+fn is_opt(plug: &PlugInfo) -> bool {
+    !plug.options.enabled || plug.options.is_lazy()
+}
+
+/// minpac installs to the first directory of `packpath`, which is `stdpath('config')`.
+fn pack_dir(dirs: &NvimDirs) -> PathBuf {
+    dirs.config.join("pack").join("minpac")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_source() {
+        let plugs = vec![
+            PlugInfo::new(
+                "vim-fugitive".to_owned(),
+                "https://github.com/tpope/vim-fugitive".to_owned(),
+            ),
+        ];
+        assert_eq!(
+            "packadd minpac\n\
+             call minpac#init()\n\
+             call minpac#add('k-takata/minpac', { 'type': 'opt' })\n\
+             call minpac#add('https://github.com/tpope/vim-fugitive', { 'name': 'vim-fugitive' })\n",
+            Minpac.config_source(&plugs, None)
+        );
+    }
+
+    #[test]
+    fn test_config_source_options() {
+        let mut plug = PlugInfo::new(
+            "vim-fugitive".to_owned(),
+            "https://github.com/tpope/vim-fugitive".to_owned(),
+        );
+        plug.options.on = vec!["Git".to_owned()];
+        plug.options.commit = Some("a1b2c3d".to_owned());
+        plug.options.post_install = Some("make".to_owned());
+        let source = Minpac.config_source(&[plug], None);
+        assert!(source.contains(
+            "call minpac#add('https://github.com/tpope/vim-fugitive', { 'name': 'vim-fugitive', \
+             'type': 'opt', 'rev': 'a1b2c3d', 'do': 'silent !make' })\n\
+             command! -nargs=* -bang Git "
+        ));
+    }
+}
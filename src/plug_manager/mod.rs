@@ -1,8 +1,16 @@
 mod ui;
+mod backend;
 mod vim_plug;
+mod dein;
+mod minpac;
+mod packages;
+mod installer;
+mod lockfile;
+mod progress;
 mod store;
 mod manager;
 mod plugin_settings_dlg;
+mod cache;
 mod vimawesome;
 
 pub use self::ui::Ui;
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use neovim_lib::{Neovim, NeovimApi};
+
+use git;
+use misc::escape_filename;
+
+use super::backend::{child_dirs, config_snippets, eval_bool, packadd_triggers, Backend, NvimDirs};
+use super::store::PlugInfo;
+
+/// Package directories of the user, packages shipped with nvim are skipped.
+const PACKAGES_EXPR: &str = "filter(globpath(&packpath, 'pack/*/start/*', 0, 1) \
+                             + globpath(&packpath, 'pack/*/opt/*', 0, 1), \
+                             'isdirectory(v:val) && stridx(v:val, $VIMRUNTIME) != 0')";
+
+/// Native packages in `pack/*/start` and `pack/*/opt` directories of `packpath`.
+pub struct Packages;
+
+impl Backend for Packages {
+    fn name(&self) -> &'static str {
+        "native packages"
+    }
+
+    fn is_loaded(&self, nvim: &mut Neovim) -> bool {
+        eval_bool(nvim, &format!("!empty({})", PACKAGES_EXPR))
+    }
+
+    fn get_plugs(&self, nvim: &mut Neovim) -> Result<Vec<PlugInfo>, String> {
+        let dirs = nvim.eval(PACKAGES_EXPR)
+            .map_err(|e| format!("Can't retrive packages: {}", e))?;
+        let dirs = dirs.as_array()
+            .ok_or_else(|| "Can't retrive packages".to_owned())?;
+
+        let mut plugs: Vec<PlugInfo> = Vec::new();
+        for dir in dirs.iter().filter_map(|dir| dir.as_str()) {
+            let dir = Path::new(dir);
+            let name = match dir.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            // the same package can be found in several directories of packpath
+            if plugs.iter().any(|plug| plug.name == name) {
+                continue;
+            }
+
+            let url = fs::read_to_string(dir.join(".git").join("config"))
+                .ok()
+                .and_then(|config| git::origin_url(&config))
+                .unwrap_or_else(|| dir.to_string_lossy().into_owned());
+            plugs.push(PlugInfo::new(name.to_owned(), url));
+        }
+        Ok(plugs)
+    }
+
+    /// Packages in `start` are always loaded by nvim, even disabled ones,
+    /// `packadd` loads the ones in `opt`.
+    fn config_source(&self, plugs: &[PlugInfo], _base_path: Option<&str>) -> String {
+        let mut builder: String = plugs
+            .iter()
+            .filter(|plug| plug.options.enabled)
+            .map(|plug| if plug.options.is_lazy() {
+                packadd_triggers(plug)
+            } else {
+                format!("silent! packadd {}\n", escape_filename(&plug.name))
+            })
+            .collect();
+        builder += &config_snippets(plugs);
+        builder
+    }
+
+    /// Existing packages are updated in place, new ones are installed as optional packages
+    /// of NeovimGtk, so they are loaded by the generated configuration only.
+    fn plug_dir(
+        &self,
+        plug: &PlugInfo,
+        _base_path: Option<&str>,
+        dirs: &NvimDirs,
+    ) -> Result<PathBuf, String> {
+        let site_dir = dirs.data.join("site");
+        let roots = [dirs.config.clone(), site_dir.clone()];
+        let existing = roots
+            .iter()
+            .flat_map(|root| child_dirs(&root.join("pack"), 2))
+            .map(|dir| dir.join(&plug.name))
+            .find(|dir| dir.is_dir());
+
+        Ok(existing.unwrap_or_else(|| nvim_gtk_pack_dir(&site_dir).join("opt").join(&plug.name)))
+    }
+
+    /// Only packages installed by NeovimGtk are cleaned.
+    fn installed_dirs(&self, _base_path: Option<&str>, dirs: &NvimDirs) -> Vec<PathBuf> {
+        child_dirs(&nvim_gtk_pack_dir(&dirs.data.join("site")), 2)
+    }
+}
+
+fn nvim_gtk_pack_dir(site_dir: &Path) -> PathBuf {
+    site_dir.join("pack").join("nvim-gtk")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_source() {
+        let plugs = vec![
+            PlugInfo::new(
+                "vim-fugitive".to_owned(),
+                "https://github.com/tpope/vim-fugitive".to_owned(),
+            ),
+        ];
+        assert_eq!(
+            "silent! packadd vim-fugitive\n",
+            Packages.config_source(&plugs, None)
+        );
+    }
+}
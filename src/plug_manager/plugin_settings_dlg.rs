@@ -1,15 +1,25 @@
 use gtk;
 use gtk::prelude::*;
 
-use super::store;
+use super::store::{self, PlugOptions};
+
+/// Labels of the revision pin combo, in the order of `get_pin` indexes.
+const PIN_LABELS: [&str; 4] = ["Latest", "Branch", "Tag", "Commit"];
 
 pub struct Builder<'a> {
     title: &'a str,
+    plug: Option<&'a store::PlugInfo>,
 }
 
 impl<'a> Builder<'a> {
     pub fn new(title: &'a str) -> Self {
-        Builder { title }
+        Builder { title, plug: None }
+    }
+
+    /// Fills the dialog with the settings of an existing plugin.
+    pub fn plug(mut self, plug: &'a store::PlugInfo) -> Self {
+        self.plug = Some(plug);
+        self
     }
 
     pub fn show<F: IsA<gtk::Window>>(&self, parent: &F) -> Option<store::PlugInfo> {
@@ -30,27 +40,63 @@ impl<'a> Builder<'a> {
         let list = gtk::ListBox::new();
         list.set_selection_mode(gtk::SelectionMode::None);
 
-        let path = gtk::Box::new(gtk::Orientation::Horizontal, 5);
-        path.set_border_width(5);
-        let path_lbl = gtk::Label::new("Repo");
-        let path_e = gtk::Entry::new();
-        path_e.set_placeholder_text("user_name/repo_name");
+        let path_e = add_entry_row(&list, "Repo", "user_name/repo_name");
+        let name_e = add_entry_row(&list, "Name", "");
+
+        let pin = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        pin.set_border_width(5);
+        let pin_lbl = gtk::Label::new("Revision");
+        let pin_combo = gtk::ComboBoxText::new();
+        for label in PIN_LABELS.iter() {
+            pin_combo.append_text(label);
+        }
+        let pin_e = gtk::Entry::new();
+        pin_e.set_placeholder_text("Branch, tag or commit");
+
+        pin.pack_start(&pin_lbl, true, true, 0);
+        pin.pack_end(&pin_e, false, true, 0);
+        pin.pack_end(&pin_combo, false, true, 0);
 
-        path.pack_start(&path_lbl, true, true, 0);
-        path.pack_end(&path_e, false, true, 0);
+        list.add(&pin);
 
-        list.add(&path);
+        let on_e = add_entry_row(&list, "Load on commands", "Command names");
+        let for_e = add_entry_row(&list, "Load for filetypes", "rust, toml");
+        let do_e = add_entry_row(&list, "Post-install hook", "make or :UpdateRemotePlugins");
 
+        let config = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        config.set_border_width(5);
+        let config_lbl = gtk::Label::new("Config, sourced after the plugin is loaded");
+        config_lbl.set_halign(gtk::Align::Start);
+        let config_scroll = gtk::ScrolledWindow::new(None, None);
+        config_scroll.set_size_request(-1, 100);
+        config_scroll.set_shadow_type(gtk::ShadowType::In);
+        let config_view = gtk::TextView::new();
+        config_view.set_monospace(true);
+        config_scroll.add(&config_view);
 
-        let name = gtk::Box::new(gtk::Orientation::Horizontal, 5);
-        name.set_border_width(5);
-        let name_lbl = gtk::Label::new("Name");
-        let name_e = gtk::Entry::new();
+        config.pack_start(&config_lbl, false, true, 0);
+        config.pack_start(&config_scroll, true, true, 0);
 
-        name.pack_start(&name_lbl, true, true, 0);
-        name.pack_end(&name_e, false, true, 0);
+        list.add(&config);
 
-        list.add(&name);
+        if let Some(plug) = self.plug {
+            path_e.set_text(&plug.url);
+            name_e.set_text(&plug.name);
+
+            let options = &plug.options;
+            let (pin_idx, pin_value) = get_pin(options);
+            pin_combo.set_active(pin_idx as i32);
+            pin_e.set_text(pin_value);
+            on_e.set_text(&options.on.join(", "));
+            for_e.set_text(&options.filetypes.join(", "));
+            do_e.set_text(options.post_install.as_ref().map_or("", String::as_str));
+            if let Some(ref config) = options.config {
+                config_view.get_buffer().map(|buffer| buffer.set_text(config));
+            }
+        } else {
+            pin_combo.set_active(0);
+        }
+        pin_e.set_sensitive(pin_combo.get_active() > 0);
 
         border.pack_start(&list, true, true, 0);
         content.add(&border);
@@ -62,6 +108,10 @@ impl<'a> Builder<'a> {
             }
         }));
 
+        pin_combo.connect_changed(clone!(pin_e => move |combo| {
+            pin_e.set_sensitive(combo.get_active() > 0);
+        }));
+
         let ok: i32 = gtk::ResponseType::Ok.into();
         let res = if dlg.run() == ok {
             path_e.get_text().map(|path| {
@@ -75,7 +125,32 @@ impl<'a> Builder<'a> {
                     .or_else(|| extract_name(&path))
                     .unwrap_or_else(|| path.clone());
 
-                store::PlugInfo::new(name.to_owned(), path.to_owned())
+                let mut plug = match self.plug {
+                    Some(plug) => store::PlugInfo {
+                        name: name.to_owned(),
+                        url: path.to_owned(),
+                        ..plug.clone()
+                    },
+                    None => store::PlugInfo::new(name.to_owned(), path.to_owned()),
+                };
+
+                let options = &mut plug.options;
+                set_pin(
+                    options,
+                    pin_combo.get_active().max(0) as usize,
+                    &pin_e.get_text().unwrap_or_default(),
+                );
+                options.on = split_list(&on_e.get_text().unwrap_or_default());
+                options.filetypes = split_list(&for_e.get_text().unwrap_or_default());
+                options.post_install = non_empty(&do_e.get_text().unwrap_or_default());
+                options.config = config_view
+                    .get_buffer()
+                    .and_then(|buffer| {
+                        buffer.get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false)
+                    })
+                    .and_then(|config| non_empty(&config));
+
+                plug
             })
         } else {
             None
@@ -87,6 +162,60 @@ impl<'a> Builder<'a> {
     }
 }
 
+fn add_entry_row(list: &gtk::ListBox, label: &str, placeholder: &str) -> gtk::Entry {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_border_width(5);
+    let lbl = gtk::Label::new(label);
+    let entry = gtk::Entry::new();
+    if !placeholder.is_empty() {
+        entry.set_placeholder_text(placeholder);
+    }
+
+    row.pack_start(&lbl, true, true, 0);
+    row.pack_end(&entry, false, true, 0);
+
+    list.add(&row);
+
+    entry
+}
+
+/// Index in `PIN_LABELS` and value of the pinned revision.
+fn get_pin(options: &PlugOptions) -> (usize, &str) {
+    if let Some(ref commit) = options.commit {
+        (3, commit)
+    } else if let Some(ref tag) = options.tag {
+        (2, tag)
+    } else if let Some(ref branch) = options.branch {
+        (1, branch)
+    } else {
+        (0, "")
+    }
+}
+
+fn set_pin(options: &mut PlugOptions, idx: usize, value: &str) {
+    let value = non_empty(value);
+    options.branch = if idx == 1 { value.clone() } else { None };
+    options.tag = if idx == 2 { value.clone() } else { None };
+    options.commit = if idx == 3 { value } else { None };
+}
+
+/// Items separated by commas or whitespace.
+fn split_list(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
+
 fn extract_name(path: &str) -> Option<String> {
     if let Some(idx) = path.rfind(|c| c == '/' || c == '\\') {
         if idx < path.len() - 1 {
@@ -111,4 +240,25 @@ mod tests {
             extract_name("http://github.com/somebody/plugin_name.git")
         );
     }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(
+            vec!["rust".to_owned(), "toml".to_owned(), "c".to_owned()],
+            split_list(" rust, toml c,")
+        );
+        assert!(split_list("").is_empty());
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut options = PlugOptions::default();
+        set_pin(&mut options, 2, " v1.0 ");
+        assert_eq!(Some("v1.0".to_owned()), options.tag);
+        assert_eq!((2, "v1.0"), get_pin(&options));
+
+        set_pin(&mut options, 0, "v1.0");
+        assert_eq!((0, ""), get_pin(&options));
+        assert_eq!(PlugOptions::default(), options);
+    }
 }
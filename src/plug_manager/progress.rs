@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use gtk;
+use gtk::prelude::*;
+use htmlescape::encode_minimal;
+
+use super::installer::{Action, Event, Job, Outcome};
+
+const COLOR_ERROR: &str = "#cc0000";
+
+struct ProgressRow {
+    spinner: gtk::Spinner,
+    status: gtk::Label,
+    details: gtk::Label,
+    expander: gtk::Expander,
+}
+
+/// Shows progress and results of installing, updating and cleaning plugins.
+pub struct ProgressPanel {
+    scroll: gtk::ScrolledWindow,
+    list: gtk::ListBox,
+    action: Option<Action>,
+    rows: HashMap<String, ProgressRow>,
+}
+
+impl ProgressPanel {
+    pub fn new() -> Self {
+        let scroll = gtk::ScrolledWindow::new(None, None);
+        scroll.get_style_context().map(|c| c.add_class("view"));
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+        scroll.add(&list);
+
+        ProgressPanel {
+            scroll,
+            list,
+            action: None,
+            rows: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for child in self.list.get_children() {
+            self.list.remove(&child);
+        }
+        self.rows.clear();
+    }
+
+    /// Shows a row for each job, waiting to be started.
+    pub fn start(&mut self, action: Action, jobs: &[Job]) {
+        self.clear();
+        self.action = Some(action);
+
+        for job in jobs {
+            let row = gtk::ListBoxRow::new();
+            let row_container = gtk::Box::new(gtk::Orientation::Vertical, 5);
+            row_container.set_border_width(5);
+            let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+
+            let spinner = gtk::Spinner::new();
+            let name_lbl = gtk::Label::new(None);
+            name_lbl.set_markup(&format!("<b>{}</b>", encode_minimal(&job.name)));
+            let status = gtk::Label::new("Waiting");
+            status.set_halign(gtk::Align::End);
+
+            let expander = gtk::Expander::new("Details");
+            let details = gtk::Label::new(None);
+            details.set_halign(gtk::Align::Start);
+            details.set_selectable(true);
+            details.set_line_wrap(true);
+            expander.add(&details);
+
+            hbox.pack_start(&spinner, false, true, 0);
+            hbox.pack_start(&name_lbl, false, true, 0);
+            hbox.pack_end(&status, false, true, 0);
+            row_container.pack_start(&hbox, true, true, 0);
+            row_container.pack_start(&expander, true, true, 0);
+            row.add(&row_container);
+            row.show_all();
+            expander.hide();
+
+            self.list.add(&row);
+            self.rows.insert(
+                job.name.clone(),
+                ProgressRow {
+                    spinner,
+                    status,
+                    details,
+                    expander,
+                },
+            );
+        }
+    }
+
+    pub fn handle(&self, event: Event) {
+        match event {
+            Event::Started(name) => if let Some(row) = self.rows.get(&name) {
+                row.spinner.start();
+                row.status.set_text(match self.action {
+                    Some(Action::Update) => "Updating…",
+                    Some(Action::Restore) => "Checking out…",
+                    _ => "Installing…",
+                });
+            },
+            Event::Finished(name, result) => if let Some(row) = self.rows.get(&name) {
+                row.spinner.stop();
+                match result {
+                    Ok(outcome) => {
+                        let (status, details) = describe_outcome(&outcome);
+                        row.status.set_text(&status);
+                        if let Some(details) = details {
+                            row.details.set_markup(&details);
+                            row.expander.show();
+                        }
+                    }
+                    Err(err) => {
+                        row.status.set_markup(&format!(
+                            "<span foreground=\"{}\">Failed</span>",
+                            COLOR_ERROR
+                        ));
+                        row.details.set_text(&err);
+                        row.expander.show();
+                        row.expander.set_expanded(true);
+                    }
+                }
+            },
+            Event::Done => (),
+        }
+    }
+
+    /// Shows the result of removing unused plugins.
+    pub fn show_cleaned(&mut self, removed: usize, errors: &[String]) {
+        self.clear();
+        self.action = None;
+
+        let mut messages = vec![format!("Removed {} unused plugins", removed)];
+        messages.extend(errors.iter().map(|err| {
+            format!("<span foreground=\"{}\">{}</span>", COLOR_ERROR, encode_minimal(err))
+        }));
+        for message in messages {
+            self.show_message(&message);
+        }
+    }
+
+    pub fn show_error(&mut self, err: &str) {
+        self.clear();
+        self.action = None;
+        self.show_message(&format!(
+            "<span foreground=\"{}\">{}</span>",
+            COLOR_ERROR,
+            encode_minimal(err)
+        ));
+    }
+
+    pub fn show_message(&self, markup: &str) {
+        let label = gtk::Label::new(None);
+        label.set_markup(markup);
+        label.set_halign(gtk::Align::Start);
+        label.set_margin_start(5);
+        label.set_margin_top(5);
+        label.set_margin_bottom(5);
+        label.show();
+        self.list.add(&label);
+    }
+}
+
+/// Status and optional markup with commits and changed files.
+fn describe_outcome(outcome: &Outcome) -> (String, Option<String>) {
+    match *outcome {
+        Outcome::Installed(ref commit) => (format!("Installed at {}", commit), None),
+        Outcome::AlreadyInstalled => ("Already installed".to_owned(), None),
+        Outcome::UpToDate => ("Up to date".to_owned(), None),
+        Outcome::Restored(ref commit) => (format!("Checked out {}", commit), None),
+        Outcome::Updated(ref update) => {
+            let status = if update.log.len() == 1 {
+                "Updated, 1 new commit".to_owned()
+            } else {
+                format!("Updated, {} new commits", update.log.len())
+            };
+            let details = format!(
+                "{}\n\n<tt>{}</tt>",
+                encode_minimal(&update.log.join("\n")),
+                encode_minimal(update.diff.trim_right())
+            );
+            (status, Some(details))
+        }
+    }
+}
+
+impl Deref for ProgressPanel {
+    type Target = gtk::ScrolledWindow;
+
+    fn deref(&self) -> &gtk::ScrolledWindow {
+        &self.scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::installer::Update;
+
+    #[test]
+    fn test_describe_outcome() {
+        assert_eq!(
+            ("Up to date".to_owned(), None),
+            describe_outcome(&Outcome::UpToDate)
+        );
+
+        let (status, details) = describe_outcome(&Outcome::Updated(Update {
+            log: vec!["1234567 Fix <C-w> mapping".to_owned()],
+            diff: " plugin/a.vim | 2 +-\n".to_owned(),
+        }));
+        assert_eq!("Updated, 1 new commit", status);
+        assert_eq!(
+            Some("1234567 Fix &lt;C-w&gt; mapping\n\n<tt> plugin/a.vim | 2 +-</tt>".to_owned()),
+            details
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use toml;
 
+use neovim_lib::Neovim;
+
 use settings::SettingsLoader;
-use super::vim_plug;
+use super::backend::BackendKind;
 
 pub struct Store {
     settings: Settings,
@@ -24,24 +26,56 @@ impl Store {
         Store { settings: Settings::empty() }
     }
 
-    pub fn load_from_plug(vim_plug: &vim_plug::Manager) -> Self {
-        let settings = match vim_plug.get_plugs() {
+    pub fn load_from_backend(backend_kind: BackendKind, nvim: &mut Neovim) -> Self {
+        let backend = backend_kind.backend();
+        let mut settings = match backend.get_plugs(nvim) {
             Err(msg) => {
                 error!("{}", msg);
                 Settings::empty()
             }
-            Ok(plugs) => {
-                let plugs = plugs
-                    .iter()
-                    .map(|vpi| PlugInfo::new(vpi.name.to_owned(), vpi.uri.to_owned()))
-                    .collect();
-                Settings::new(plugs)
-            }
+            Ok(plugs) => Settings::new(plugs),
         };
+        settings.backend = backend_kind;
+        settings.base_path = backend.base_path(nvim);
 
         Store { settings }
     }
 
+    pub fn backend_kind(&self) -> BackendKind {
+        self.settings.backend
+    }
+
+    pub fn base_path(&self) -> Option<&str> {
+        self.settings.base_path.as_ref().map(String::as_str)
+    }
+
+    pub fn set_backend_kind(&mut self, backend_kind: BackendKind) {
+        self.settings.backend = backend_kind;
+    }
+
+    /// Url or JSON file of the plugin index, vimawesome.com if not set.
+    pub fn index(&self) -> Option<&str> {
+        self.settings.index.as_ref().map(String::as_str)
+    }
+
+    pub fn set_index(&mut self, index: Option<String>) {
+        self.settings.index = index;
+    }
+
+    /// Vim script that loads the plugins that are not removed.
+    pub fn config_source(&self) -> String {
+        let plugs: Vec<_> = self.settings
+            .plugs
+            .iter()
+            .filter(|plug| !plug.removed)
+            .cloned()
+            .collect();
+        self.settings
+            .backend
+            .backend()
+            .config_source(&plugs, self.base_path())
+    }
+
     pub fn get_plugs(&self) -> &[PlugInfo] {
         &self.settings.plugs
     }
@@ -66,6 +100,23 @@ impl Store {
         self.settings.plugs[idx].removed = false;
     }
 
+    pub fn set_plug_enabled(&mut self, idx: usize, enabled: bool) {
+        self.settings.plugs[idx].options.enabled = enabled;
+    }
+
+    /// Replaces the plugin, unless another plugin has the same name or path.
+    pub fn update_plug(&mut self, idx: usize, plug: PlugInfo) -> bool {
+        let path = plug.get_plug_path();
+        if self.settings.plugs.iter().enumerate().any(|(i, p)| {
+            i != idx && (p.get_plug_path() == path || p.name == plug.name)
+        })
+        {
+            return false;
+        }
+        self.settings.plugs[idx] = plug;
+        true
+    }
+
     pub fn add_plug(&mut self, plug: PlugInfo) -> bool {
         let path = plug.get_plug_path();
         if self.settings.plugs.iter().any(|p| {
@@ -91,9 +142,24 @@ impl Store {
     }
 }
 
+/// Version of the settings file, increased on incompatible schema changes.
+const SETTINGS_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize)]
 struct Settings {
     enabled: bool,
+    /// Settings without a version were written before plugin options were added.
+    #[serde(default)]
+    version: u32,
+    /// Plugin manager the configuration is generated for, vim-plug for older settings.
+    #[serde(default)]
+    backend: BackendKind,
+    /// Install directory of the plugin manager, if it needs one.
+    #[serde(default)]
+    base_path: Option<String>,
+    /// Url of a vimawesome compatible API or path of a JSON file with plugins.
+    #[serde(default)]
+    index: Option<String>,
     plugs: Vec<PlugInfo>,
 }
 
@@ -101,9 +167,20 @@ impl Settings {
     fn new(plugs: Vec<PlugInfo>) -> Self {
         Settings {
             plugs,
-            enabled: false,
+            ..Settings::empty()
         }
     }
+
+    fn migrate(mut self) -> Self {
+        if self.version < 1 {
+            // Plugins get default options, which keep them enabled and unpinned.
+            // Removed plugins could be saved by older versions, they are dropped now.
+            self.plugs.retain(|plug| !plug.removed);
+        }
+
+        self.version = SETTINGS_VERSION;
+        self
+    }
 }
 
 impl SettingsLoader for Settings {
@@ -112,20 +189,28 @@ impl SettingsLoader for Settings {
     fn empty() -> Self {
         Settings {
             plugs: vec![],
+            backend: BackendKind::default(),
+            base_path: None,
+            index: None,
             enabled: false,
+            version: SETTINGS_VERSION,
         }
     }
 
     fn from_str(s: &str) -> Result<Self, String> {
-        toml::from_str(&s).map_err(|e| format!("{}", e))
+        toml::from_str::<Settings>(&s)
+            .map(Settings::migrate)
+            .map_err(|e| format!("{}", e))
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PlugInfo {
     pub name: String,
     pub url: String,
     pub removed: bool,
+    #[serde(default)]
+    pub options: PlugOptions,
 }
 
 impl PlugInfo {
@@ -134,6 +219,7 @@ impl PlugInfo {
             name,
             url,
             removed: false,
+            options: PlugOptions::default(),
         }
     }
 
@@ -152,6 +238,73 @@ impl PlugInfo {
     }
 }
 
+/// Options of a plugin, named after the vim-plug ones.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlugOptions {
+    /// Disabled plugins stay installed, but are not loaded.
+    pub enabled: bool,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub commit: Option<String>,
+    /// Commands that load the plugin on first use.
+    pub on: Vec<String>,
+    /// Filetypes that load the plugin.
+    #[serde(rename = "for")]
+    pub filetypes: Vec<String>,
+    /// Shell command, or vim command starting with `:`, run after install and update.
+    #[serde(rename = "do")]
+    pub post_install: Option<String>,
+    /// Vim script sourced after the plugins are loaded.
+    pub config: Option<String>,
+}
+
+impl PlugOptions {
+    pub fn is_lazy(&self) -> bool {
+        !self.on.is_empty() || !self.filetypes.is_empty()
+    }
+
+    /// Pinned commit, tag or branch, in this order.
+    pub fn rev(&self) -> Option<&str> {
+        self.commit
+            .as_ref()
+            .or_else(|| self.tag.as_ref())
+            .or_else(|| self.branch.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Vim command of the post install hook.
+    pub fn post_install_vim_cmd(&self) -> Option<&str> {
+        self.post_install
+            .as_ref()
+            .filter(|cmd| cmd.starts_with(':'))
+            .map(|cmd| &cmd[1..])
+    }
+
+    /// Shell command of the post install hook.
+    pub fn post_install_shell_cmd(&self) -> Option<&str> {
+        self.post_install
+            .as_ref()
+            .filter(|cmd| !cmd.starts_with(':'))
+            .map(String::as_str)
+    }
+}
+
+impl Default for PlugOptions {
+    fn default() -> Self {
+        PlugOptions {
+            enabled: true,
+            branch: None,
+            tag: None,
+            commit: None,
+            on: vec![],
+            filetypes: vec![],
+            post_install: None,
+            config: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,4 +317,48 @@ mod tests {
         );
         assert_eq!("rust-lang/rust.vim".to_owned(), plug.get_plug_path());
     }
+
+    #[test]
+    fn test_settings_without_backend() {
+        let settings = Settings::from_str(
+            "enabled = true\n\
+             [[plugs]]\n\
+             name = \"rust.vim\"\n\
+             url = \"https://github.com/rust-lang/rust.vim\"\n\
+             removed = false\n",
+        ).unwrap();
+        assert_eq!(BackendKind::VimPlug, settings.backend);
+        assert_eq!(None, settings.base_path);
+        assert_eq!(1, settings.plugs.len());
+        assert_eq!(SETTINGS_VERSION, settings.version);
+        assert_eq!(PlugOptions::default(), settings.plugs[0].options);
+    }
+
+    #[test]
+    fn test_plug_options() {
+        let settings = Settings::from_str(
+            "enabled = true\n\
+             version = 1\n\
+             [[plugs]]\n\
+             name = \"rust.vim\"\n\
+             url = \"https://github.com/rust-lang/rust.vim\"\n\
+             removed = false\n\
+             [plugs.options]\n\
+             enabled = false\n\
+             branch = \"master\"\n\
+             for = [\"rust\"]\n\
+             do = \":UpdateRemotePlugins\"\n",
+        ).unwrap();
+        let options = &settings.plugs[0].options;
+        assert!(!options.enabled);
+        assert!(options.is_lazy());
+        assert_eq!(Some("master"), options.rev());
+        assert_eq!(vec!["rust".to_owned()], options.filetypes);
+        assert_eq!(Some("UpdateRemotePlugins"), options.post_install_vim_cmd());
+        assert_eq!(None, options.post_install_shell_cmd());
+
+        let saved = toml::to_string(&settings).unwrap();
+        let reloaded = Settings::from_str(&saved).unwrap();
+        assert_eq!(settings.plugs[0].options, reloaded.plugs[0].options);
+    }
 }
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ops::Deref;
+use std::path::PathBuf;
 
 use ui::UiMutex;
 
@@ -9,7 +11,11 @@ use gtk;
 use gtk::prelude::*;
 use gtk_sys;
 
+use super::backend::BackendKind;
+use super::installer::{self, Action};
+use super::lockfile::{self, Lockfile};
 use super::manager;
+use super::progress::ProgressPanel;
 use super::store::{Store, PlugInfo};
 use super::plugin_settings_dlg;
 use super::vimawesome;
@@ -55,6 +61,9 @@ impl<'a> Ui<'a> {
 
         header_bar.pack_end(&enable_swc);
 
+        let backend_combo = create_backend_combo(&self.manager);
+        header_bar.pack_start(&backend_combo);
+
         header_bar.set_title("Plug");
         header_bar.set_show_close_button(true);
         header_bar.show();
@@ -80,20 +89,47 @@ impl<'a> Ui<'a> {
         let plugins_lbl = gtk::Label::new("Plugins");
         pages.add_page(&plugins_lbl, &plugins, "plugins");
 
+        let progress = Arc::new(UiMutex::new(ProgressPanel::new()));
+        let progress_lbl = gtk::Label::new("Progress");
+        let progress_row = pages.add_page(&progress_lbl, &**progress.borrow(), "progress");
+        plugins.pack_start(
+            &create_action_btns(
+                self.manager,
+                &plugs_panel,
+                &progress,
+                &pages.categories,
+                &progress_row,
+            ),
+            false,
+            true,
+            0,
+        );
+
         add_help_tab(
             &pages,
             &format!(
-                "NeovimGtk plugin manager is a GUI for vim-plug.\n\
-            It can load plugins from vim-plug configuration if vim-plug sarted and NeovimGtk manager settings is empty.\n\
-            When enabled it generate and load vim-plug as simple vim file at startup before init.vim is processed.\n\
-            So <b>after</b> enabling this manager <b>you must disable vim-plug</b> configuration in init.vim.\n\
-            This manager currently only manage vim-plug configuration and do not any actions on plugin management.\n\
-            So you must call all vim-plug (PlugInstall, PlugUpdate, PlugClean) commands manually.\n\
+                "NeovimGtk plugin manager is a GUI for vim-plug, dein.vim, minpac and native packages.\n\
+            It can load plugins from the configuration of the plugin manager nvim was started with,\n\
+            if NeovimGtk manager settings is empty.\n\
+            When enabled it generate and load configuration of the plugin manager selected in the header bar\n\
+            as simple vim file at startup before init.vim is processed.\n\
+            So <b>after</b> enabling this manager <b>you must disable the plugin manager</b> configuration in init.vim.\n\
+            Plugins are searched on vimawesome.com, or in the index set on the Get Plugins page,\n\
+            which can be the url of a vimawesome compatible API or a local JSON file in the same format.\n\
+            Search results and READMEs are cached, so they are available offline.\n\
+            Each plugin can be disabled without removing it, its settings pin a branch, tag or commit,\n\
+            load it lazily on commands or filetypes, run a post-install hook and source a config snippet.\n\
+            Install, Update and Clean buttons below the plugin list clone, pull and remove plugins with git\n\
+            to the directories the selected plugin manager uses, progress is shown on the Progress page.\n\
+            Export a lockfile to record the commit of each installed plugin, import a lockfile to add its plugins\n\
+            to the list and restore it to check out exactly the same commits on another machine.\n\
             Current configuration source is <b>{}</b>",
                 match self.manager.borrow().plug_manage_state {
-                    manager::PlugManageState::NvimGtk => "NeovimGtk config file",
-                    manager::PlugManageState::VimPlug => "loaded from vim-plug",
-                    manager::PlugManageState::Unknown => "Unknown",
+                    manager::PlugManageState::NvimGtk => "NeovimGtk config file".to_owned(),
+                    manager::PlugManageState::Detected(backend_kind) => {
+                        format!("loaded from {}", backend_kind.backend().name())
+                    }
+                    manager::PlugManageState::Unknown => "Unknown".to_owned(),
                 }
             ),
         );
@@ -122,7 +158,7 @@ impl<'a> Ui<'a> {
                 NvimConfig::new(manager.generate_config()).generate_config()
             {
                 if let Some(path) = config_path.to_str() {
-                    manager.vim_plug.reload(path);
+                    manager.reload(path);
                 }
             }
         }
@@ -155,6 +191,32 @@ impl<'a> Ui<'a> {
     }
 }
 
+fn create_backend_combo(manager: &Arc<UiMutex<manager::Manager>>) -> gtk::ComboBoxText {
+    let combo = gtk::ComboBoxText::new();
+    combo.set_valign(gtk::Align::Center);
+    combo.set_tooltip_text("Plugin manager to generate configuration for");
+
+    let current = manager.borrow().store.backend_kind();
+    for (idx, backend_kind) in BackendKind::ALL.iter().enumerate() {
+        combo.append_text(backend_kind.backend().name());
+        if *backend_kind == current {
+            combo.set_active(idx as i32);
+        }
+    }
+
+    combo.connect_changed(clone!(manager => move |combo| {
+        let idx = combo.get_active();
+        if idx >= 0 {
+            if let Some(backend_kind) = BackendKind::ALL.get(idx as usize) {
+                manager.borrow_mut().store.set_backend_kind(*backend_kind);
+            }
+        }
+    }));
+    combo.show();
+
+    combo
+}
+
 fn create_up_down_btns(
     plugs_panel: &gtk::ListBox,
     manager: &Arc<UiMutex<manager::Manager>>,
@@ -200,15 +262,245 @@ fn create_up_down_btns(
     buttons_panel
 }
 
+fn create_action_btns(
+    manager: &Arc<UiMutex<manager::Manager>>,
+    plugs_panel: &gtk::ListBox,
+    progress: &Arc<UiMutex<ProgressPanel>>,
+    categories: &gtk::ListBox,
+    progress_row: &gtk::ListBoxRow,
+) -> gtk::Box {
+    let buttons_panel = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let install_btn = gtk::Button::new_with_label("Install");
+    install_btn.set_tooltip_text("Clone plugins that are not installed yet");
+    let update_btn = gtk::Button::new_with_label("Update");
+    update_btn.set_tooltip_text("Pull new commits of all plugins");
+    let clean_btn = gtk::Button::new_with_label("Clean");
+    clean_btn.set_tooltip_text("Remove installed plugins that are not in the list");
+
+    let export_btn = gtk::Button::new_with_label("Export Lock..");
+    export_btn.set_tooltip_text("Save the commit of each installed plugin to a lockfile");
+    let import_btn = gtk::Button::new_with_label("Import Lock..");
+    import_btn.set_tooltip_text("Add the plugins of a lockfile to the list");
+    let restore_btn = gtk::Button::new_with_label("Restore..");
+    restore_btn.set_tooltip_text("Check out the commits of a lockfile");
+
+    for &(btn, action) in &[(&install_btn, Action::Install), (&update_btn, Action::Update)] {
+        btn.connect_clicked(
+            clone!(manager, progress, categories, progress_row, buttons_panel => move |_| {
+                categories.select_row(&progress_row);
+                let jobs = manager.borrow().jobs();
+                run_action(action, jobs, &manager, &progress, &buttons_panel);
+            }),
+        );
+    }
+
+    restore_btn.connect_clicked(
+        clone!(manager, progress, categories, progress_row, buttons_panel => move |btn| {
+            let path = match choose_lockfile(btn, gtk::FileChooserAction::Open) {
+                Some(path) => path,
+                None => return,
+            };
+            categories.select_row(&progress_row);
+            let jobs = Lockfile::load(&path)
+                .and_then(|lockfile| manager.borrow().restore_jobs(&lockfile));
+            run_action(Action::Restore, jobs, &manager, &progress, &buttons_panel);
+        }),
+    );
+
+    export_btn.connect_clicked(clone!(manager => move |btn| {
+        let lockfile = match manager.borrow().lockfile() {
+            Ok(lockfile) => lockfile,
+            Err(e) => {
+                show_error_dlg(&e);
+                return;
+            }
+        };
+        if let Some(path) = choose_lockfile(btn, gtk::FileChooserAction::Save) {
+            if let Err(e) = lockfile.save(&path) {
+                show_error_dlg(&e);
+            }
+        }
+    }));
+
+    import_btn.connect_clicked(clone!(manager, plugs_panel => move |btn| {
+        if let Some(path) = choose_lockfile(btn, gtk::FileChooserAction::Open) {
+            match Lockfile::load(&path) {
+                Ok(lockfile) => for plug in lockfile.plugs_info() {
+                    add_plugin_row(&manager, &plugs_panel, plug);
+                },
+                Err(e) => show_error_dlg(&e),
+            }
+        }
+    }));
+
+    clean_btn.connect_clicked(
+        clone!(manager, progress, categories, progress_row, buttons_panel => move |_| {
+            categories.select_row(&progress_row);
+            run_clean(&manager, &progress, &buttons_panel);
+        }),
+    );
+
+    buttons_panel.pack_start(&install_btn, false, true, 0);
+    buttons_panel.pack_start(&update_btn, false, true, 0);
+    buttons_panel.pack_start(&clean_btn, false, true, 0);
+    buttons_panel.pack_start(
+        &gtk::Separator::new(gtk::Orientation::Vertical),
+        false,
+        true,
+        0,
+    );
+    buttons_panel.pack_start(&export_btn, false, true, 0);
+    buttons_panel.pack_start(&import_btn, false, true, 0);
+    buttons_panel.pack_start(&restore_btn, false, true, 0);
+    buttons_panel.set_halign(gtk::Align::End);
+
+    buttons_panel
+}
+
+fn choose_lockfile(btn: &gtk::Button, action: gtk::FileChooserAction) -> Option<PathBuf> {
+    let window = btn.get_toplevel()
+        .and_then(|w| w.downcast::<gtk::Window>().ok());
+    let (title, accept) = match action {
+        gtk::FileChooserAction::Save => ("Export Lockfile", "_Save"),
+        _ => ("Open Lockfile", "_Open"),
+    };
+    let dlg = gtk::FileChooserDialog::new(Some(title), window.as_ref(), action);
+
+    const ACCEPT_ID: i32 = 0;
+    const CANCEL_ID: i32 = 1;
+
+    dlg.add_buttons(&[(accept, ACCEPT_ID), ("_Cancel", CANCEL_ID)]);
+    if action == gtk::FileChooserAction::Save {
+        dlg.set_current_name(lockfile::LOCKFILE_NAME);
+        dlg.set_do_overwrite_confirmation(true);
+    }
+
+    let path = if dlg.run() == ACCEPT_ID {
+        dlg.get_filename()
+    } else {
+        None
+    };
+    dlg.destroy();
+    path
+}
+
+fn show_error_dlg(msg: &str) {
+    let dlg = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Ok,
+        msg,
+    );
+    dlg.run();
+    dlg.destroy();
+}
+
+fn run_action(
+    action: Action,
+    jobs: Result<Vec<installer::Job>, String>,
+    manager: &Arc<UiMutex<manager::Manager>>,
+    progress: &Arc<UiMutex<ProgressPanel>>,
+    buttons_panel: &gtk::Box,
+) {
+    let jobs = match jobs {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            progress.borrow_mut().show_error(&e);
+            error!("{}", e);
+            return;
+        }
+    };
+
+    progress.borrow_mut().start(action, &jobs);
+    buttons_panel.set_sensitive(false);
+
+    // the installer runs shell hooks, vim command hooks are run by nvim
+    let vim_hooks: HashMap<String, String> = jobs.iter()
+        .filter_map(|job| {
+            job.options
+                .post_install_vim_cmd()
+                .map(|cmd| (job.name.clone(), cmd.to_owned()))
+        })
+        .collect();
+
+    let buttons_panel = UiMutex::new(buttons_panel.clone());
+    let manager = manager.clone();
+    let progress = progress.clone();
+    installer::run(action, jobs, move |event| {
+        match event {
+            installer::Event::Done => buttons_panel.borrow().set_sensitive(true),
+            installer::Event::Finished(ref name, Ok(installer::Outcome::Installed(_))) |
+            installer::Event::Finished(ref name, Ok(installer::Outcome::Updated(_))) => {
+                if let Some(cmd) = vim_hooks.get(name) {
+                    manager.borrow().command(cmd);
+                }
+            }
+            _ => (),
+        }
+        progress.borrow().handle(event);
+    });
+}
+
+fn run_clean(
+    manager: &Arc<UiMutex<manager::Manager>>,
+    progress: &Arc<UiMutex<ProgressPanel>>,
+    buttons_panel: &gtk::Box,
+) {
+    let dirs = match manager.borrow().unused_dirs() {
+        Ok(dirs) => dirs,
+        Err(e) => {
+            progress.borrow_mut().show_error(&e);
+            error!("{}", e);
+            return;
+        }
+    };
+
+    if dirs.is_empty() {
+        let mut progress = progress.borrow_mut();
+        progress.clear();
+        progress.show_message("No unused plugins");
+        return;
+    }
+
+    let dir_list: Vec<String> = dirs.iter().map(|dir| format!("{}", dir.display())).collect();
+    let dlg = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::empty(),
+        gtk::MessageType::Question,
+        gtk::ButtonsType::OkCancel,
+        &format!("Remove unused plugins?\n\n{}", dir_list.join("\n")),
+    );
+    let ok: i32 = gtk::ResponseType::Ok.into();
+    let confirmed = dlg.run() == ok;
+    dlg.destroy();
+    if !confirmed {
+        return;
+    }
+
+    let count = dirs.len();
+    buttons_panel.set_sensitive(false);
+
+    let buttons_panel = UiMutex::new(buttons_panel.clone());
+    let progress = progress.clone();
+    installer::run_clean(dirs, move |errors| {
+        buttons_panel.borrow().set_sensitive(true);
+        progress
+            .borrow_mut()
+            .show_cleaned(count - errors.len(), &errors);
+    });
+}
+
 fn populate_get_plugins(
     query: Option<String>,
     get_plugins: &gtk::Box,
     manager: Arc<UiMutex<manager::Manager>>,
     plugs_panel: gtk::ListBox,
 ) {
+    let source = vimawesome::IndexSource::new(manager.borrow().store.index());
     let plugs_panel = UiMutex::new(plugs_panel);
     let get_plugins = UiMutex::new(get_plugins.clone());
-    vimawesome::call(query, move |res| {
+    vimawesome::call(source, query, move |res| {
         let panel = get_plugins.borrow();
         for child in panel.get_children() {
             panel.remove(&child);
@@ -245,6 +537,19 @@ fn create_plug_row(
 
     let exists_button_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
 
+    let enabled_swc = gtk::Switch::new();
+    enabled_swc.set_valign(gtk::Align::Center);
+    enabled_swc.set_state(plug_info.options.enabled);
+    enabled_swc.set_tooltip_text("Load the plugin");
+    exists_button_box.pack_start(&enabled_swc, false, true, 0);
+
+    let settings_btn = gtk::Button::new_from_icon_name(
+        "emblem-system-symbolic",
+        gtk_sys::GTK_ICON_SIZE_BUTTON as i32,
+    );
+    settings_btn.set_tooltip_text("Plugin settings");
+    exists_button_box.pack_start(&settings_btn, false, true, 0);
+
     let remove_btn = gtk::Button::new_with_label("Remove");
     exists_button_box.pack_start(&remove_btn, false, true, 0);
 
@@ -279,9 +584,49 @@ fn create_plug_row(
                 }),
     );
 
+    // rows can be moved, so the index is taken from the row
+    enabled_swc.connect_state_set(clone!(manager, row => move |_, state| {
+        let idx = row.get_index();
+        if idx >= 0 {
+            manager.borrow_mut().store.set_plug_enabled(idx as usize, state);
+        }
+        Inhibit(false)
+    }));
+
+    settings_btn.connect_clicked(clone!(manager, row, label_box => move |btn| {
+        let idx = row.get_index();
+        if idx < 0 {
+            return;
+        }
+        let window = btn.get_toplevel()
+            .and_then(|w| w.downcast::<gtk::Window>().ok());
+        if let Some(window) = window {
+            show_plug_settings_dlg(&window, &manager, idx as usize, &label_box);
+        }
+    }));
+
     row
 }
 
+fn show_plug_settings_dlg(
+    parent: &gtk::Window,
+    manager: &Arc<UiMutex<manager::Manager>>,
+    idx: usize,
+    label_box: &gtk::Box,
+) {
+    let plug = manager.borrow().store.get_plugs()[idx].clone();
+    if let Some(plug) = plugin_settings_dlg::Builder::new("Plugin settings")
+        .plug(&plug)
+        .show(parent)
+    {
+        if manager.borrow_mut().store.update_plug(idx, plug.clone()) {
+            fill_plug_label(label_box, &plug);
+        } else {
+            show_error_dlg("Plugin with this name or path already exists");
+        }
+    }
+}
+
 fn show_add_plug_dlg<F: IsA<gtk::Window>>(
     parent: &F,
     manager: &Arc<UiMutex<manager::Manager>>,
@@ -296,6 +641,20 @@ fn add_plugin(
     manager: &Arc<UiMutex<manager::Manager>>,
     plugs_panel: &gtk::ListBox,
     new_plugin: PlugInfo,
+) -> bool {
+    if add_plugin_row(manager, plugs_panel, new_plugin) {
+        true
+    } else {
+        show_error_dlg("Plugin with this name or path already exists");
+        false
+    }
+}
+
+/// Adds the plugin to the list, unless a plugin with the same name or path exists.
+fn add_plugin_row(
+    manager: &Arc<UiMutex<manager::Manager>>,
+    plugs_panel: &gtk::ListBox,
+    new_plugin: PlugInfo,
 ) -> bool {
     let row = create_plug_row(manager.borrow().store.plugs_count(), &new_plugin, manager);
 
@@ -304,32 +663,66 @@ fn add_plugin(
         plugs_panel.add(&row);
         true
     } else {
-        let dlg = gtk::MessageDialog::new(
-            None::<&gtk::Window>,
-            gtk::DialogFlags::empty(),
-            gtk::MessageType::Error,
-            gtk::ButtonsType::Ok,
-            "Plugin with this name or path already exists",
-        );
-        dlg.run();
-        dlg.destroy();
         false
     }
 }
 
 fn create_plug_label(plug_info: &PlugInfo) -> gtk::Box {
     let label_box = gtk::Box::new(gtk::Orientation::Vertical, 5);
+    fill_plug_label(&label_box, plug_info);
+    label_box
+}
+
+fn fill_plug_label(label_box: &gtk::Box, plug_info: &PlugInfo) {
+    for child in label_box.get_children() {
+        label_box.remove(&child);
+    }
 
     let name_lbl = gtk::Label::new(None);
     name_lbl.set_markup(&format!("<b>{}</b>", plug_info.name));
     name_lbl.set_halign(gtk::Align::Start);
-    let url_lbl = gtk::Label::new(Some(plug_info.get_plug_path().as_str()));
+    let summary = options_summary(plug_info);
+    let url_lbl = if summary.is_empty() {
+        gtk::Label::new(Some(plug_info.get_plug_path().as_str()))
+    } else {
+        gtk::Label::new(Some(
+            format!("{} ({})", plug_info.get_plug_path(), summary).as_str(),
+        ))
+    };
     url_lbl.set_halign(gtk::Align::Start);
 
 
     label_box.pack_start(&name_lbl, true, true, 0);
     label_box.pack_start(&url_lbl, true, true, 0);
-    label_box
+    label_box.show_all();
+}
+
+/// Short description of the options, like `tag v1.0, for rust`.
+fn options_summary(plug_info: &PlugInfo) -> String {
+    let options = &plug_info.options;
+    let mut parts = vec![];
+    for &(name, value) in &[
+        ("branch", &options.branch),
+        ("tag", &options.tag),
+        ("commit", &options.commit),
+    ] {
+        if let Some(ref value) = *value {
+            parts.push(format!("{} {}", name, value));
+        }
+    }
+    if !options.on.is_empty() {
+        parts.push(format!("on {}", options.on.join(" ")));
+    }
+    if !options.filetypes.is_empty() {
+        parts.push(format!("for {}", options.filetypes.join(" ")));
+    }
+    if options.post_install.is_some() {
+        parts.push("post-install hook".to_owned());
+    }
+    if options.config.is_some() {
+        parts.push("config".to_owned());
+    }
+    parts.join(", ")
 }
 
 fn add_vimawesome_tab(
@@ -344,12 +737,25 @@ fn add_vimawesome_tab(
 
     let list_panel = gtk::Box::new(gtk::Orientation::Vertical, 0);
     let link_button = gtk::Label::new(None);
-    link_button.set_markup(
-        "Plugins are taken from: <a href=\"https://vimawesome.com\">https://vimawesome.com</a>",
-    );
+    let source = vimawesome::IndexSource::new(manager.borrow().store.index());
+    link_button.set_markup(&source.markup());
     let search_entry = gtk::SearchEntry::new();
 
+    let index = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let index_lbl = gtk::Label::new("Index");
+    let index_e = gtk::Entry::new();
+    index_e.set_placeholder_text(vimawesome::DEFAULT_INDEX);
+    index_e.set_tooltip_text(
+        "Url of a vimawesome compatible API or path of a JSON file with plugins",
+    );
+    if let Some(index_value) = manager.borrow().store.index() {
+        index_e.set_text(index_value);
+    }
+    index.pack_start(&index_lbl, false, true, 0);
+    index.pack_start(&index_e, true, true, 0);
+
     get_plugins.pack_start(&link_button, false, true, 10);
+    get_plugins.pack_start(&index, false, true, 5);
     get_plugins.pack_start(&search_entry, false, true, 5);
     get_plugins.pack_start(&list_panel, true, true, 0);
     list_panel.pack_start(&spinner, true, true, 0);
@@ -363,6 +769,25 @@ fn add_vimawesome_tab(
         populate_get_plugins(se.get_text(), &list_panel, manager.clone(), plugs_panel.clone());
     }));
 
+    index_e.connect_activate(
+        clone!(list_panel, manager, plugs_panel, link_button, search_entry => move |e| {
+            let index = e.get_text().filter(|index| !index.trim().is_empty());
+            manager.borrow_mut().store.set_index(index);
+            let source = vimawesome::IndexSource::new(manager.borrow().store.index());
+            link_button.set_markup(&source.markup());
+            let spinner = gtk::Spinner::new();
+            list_panel.pack_start(&spinner, false, true, 5);
+            spinner.show();
+            spinner.start();
+            populate_get_plugins(
+                search_entry.get_text(),
+                &list_panel,
+                manager.clone(),
+                plugs_panel.clone(),
+            );
+        }),
+    );
+
     gtk::idle_add(clone!(manager, plugs_panel => move || {
         populate_get_plugins(None, &list_panel, manager.clone(), plugs_panel.clone());
         Continue(false)
@@ -424,7 +849,7 @@ impl SettingsPages {
         label: &gtk::Label,
         widget: &W,
         name: &'static str,
-    ) {
+    ) -> gtk::ListBoxRow {
         let row = gtk::ListBoxRow::new();
 
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
@@ -434,7 +859,9 @@ impl SettingsPages {
 
         self.categories.add(&row);
         self.stack.add_named(widget, name);
-        self.rows.borrow_mut().push((row, name));
+        self.rows.borrow_mut().push((row.clone(), name));
+
+        row
     }
 }
 
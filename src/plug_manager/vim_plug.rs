@@ -1,104 +1,249 @@
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
-use neovim_lib::{NeovimApi, NeovimApiAsync};
+use neovim_lib::{Neovim, NeovimApi, Value};
 
-use nvim::{NeovimClient, ErrorReport, NeovimRef};
 use value::ValueMapExt;
 
-pub struct Manager {
-    nvim: Option<Rc<NeovimClient>>,
-}
+use super::backend::{child_dirs, config_snippets, eval_bool, eval_string, expand_home, vim_dict,
+                     vim_list, vim_string, Backend, NvimDirs};
+use super::store::{PlugInfo, PlugOptions};
+
+pub struct VimPlug;
 
-impl Manager {
-    pub fn new() -> Self {
-        Manager { nvim: None }
+impl Backend for VimPlug {
+    fn name(&self) -> &'static str {
+        "vim-plug"
     }
 
-    pub fn initialize(&mut self, nvim: Rc<NeovimClient>) {
-        self.nvim = Some(nvim);
+    fn is_loaded(&self, nvim: &mut Neovim) -> bool {
+        eval_bool(nvim, "exists('g:loaded_plug')")
     }
 
-    fn nvim(&self) -> Option<NeovimRef> {
-        self.nvim.as_ref().unwrap().nvim()
+    fn get_plugs(&self, nvim: &mut Neovim) -> Result<Vec<PlugInfo>, String> {
+        let g_plugs = nvim.eval("g:plugs").map_err(|e| {
+            format!("Can't retrive g:plugs map: {}", e)
+        })?;
+
+        let plugs_map = g_plugs
+            .as_map()
+            .ok_or("Can't retrive g:plugs map".to_owned())?
+            .to_attrs_map()?;
+
+        let g_plugs_order = nvim.eval("g:plugs_order").map_err(|e| format!("{}", e))?;
+
+        let order_arr = g_plugs_order.as_array().ok_or(
+            "Can't find g:plugs_order array"
+                .to_owned(),
+        )?;
+
+        let plugs_info: Vec<PlugInfo> = order_arr
+            .iter()
+            .map(|n| n.as_str())
+            .filter_map(|name| if let Some(name) = name {
+                plugs_map
+                    .get(name)
+                    .and_then(|desc| desc.as_map())
+                    .and_then(|desc| desc.to_attrs_map().ok())
+                    .and_then(|desc| {
+                        let uri = desc.get("uri").and_then(|uri| uri.as_str());
+                        if let Some(uri) = uri {
+                            let mut plug = PlugInfo::new(name.to_owned(), uri.to_owned());
+                            plug.options = parse_options(&desc);
+                            Some(plug)
+                        } else {
+                            None
+                        }
+                    })
+            } else {
+                None
+            })
+            .collect();
+        Ok(plugs_info)
     }
 
-    pub fn get_plugs(&self) -> Result<Box<[VimPlugInfo]>, String> {
-        if let Some(mut nvim) = self.nvim() {
-            let g_plugs = nvim.eval("g:plugs").map_err(|e| {
-                format!("Can't retrive g:plugs map: {}", e)
-            })?;
-
-            let plugs_map = g_plugs
-                .as_map()
-                .ok_or("Can't retrive g:plugs map".to_owned())?
-                .to_attrs_map()?;
-
-            let g_plugs_order = nvim.eval("g:plugs_order").map_err(|e| format!("{}", e))?;
-
-            let order_arr = g_plugs_order.as_array().ok_or(
-                "Can't find g:plugs_order array"
-                    .to_owned(),
-            )?;
-
-            let plugs_info: Vec<VimPlugInfo> = order_arr
-                .iter()
-                .map(|n| n.as_str())
-                .filter_map(|name| if let Some(name) = name {
-                    plugs_map
-                        .get(name)
-                        .and_then(|desc| desc.as_map())
-                        .and_then(|desc| desc.to_attrs_map().ok())
-                        .and_then(|desc| {
-                            let uri = desc.get("uri").and_then(|uri| uri.as_str());
-                            if let Some(uri) = uri {
-                                Some(VimPlugInfo::new(name.to_owned(), uri.to_owned()))
-                            } else {
-                                None
-                            }
-                        })
-                } else {
-                    None
-                })
-                .collect();
-            Ok(plugs_info.into_boxed_slice())
-        } else {
-            Err("Nvim not initialized".to_owned())
-        }
+    /// Plugin directory given to `plug#begin()`.
+    fn base_path(&self, nvim: &mut Neovim) -> Option<String> {
+        eval_string(nvim, "get(g:, 'plug_home', '')")
     }
 
-    pub fn is_loaded(&self) -> bool {
-        if let Some(mut nvim) = self.nvim() {
-            let loaded_plug = nvim.eval("exists('g:loaded_plug')");
-            loaded_plug
-                .ok_and_report()
-                .and_then(|loaded_plug| loaded_plug.as_i64())
-                .map_or(false, |loaded_plug| if loaded_plug > 0 {
-                    true
-                } else {
-                    false
-                })
-        } else {
-            false
+    fn config_source(&self, plugs: &[PlugInfo], base_path: Option<&str>) -> String {
+        let mut builder = match base_path {
+            Some(base_path) => format!("call plug#begin({})\n", vim_string(base_path)),
+            None => "call plug#begin()\n".to_owned(),
+        };
+
+        for plug in plugs {
+            let options = &plug.options;
+            let mut dict = vec![("as", vim_string(&plug.name))];
+            for &(key, value) in &[
+                ("branch", &options.branch),
+                ("tag", &options.tag),
+                ("commit", &options.commit),
+                ("do", &options.post_install),
+            ] {
+                if let Some(ref value) = *value {
+                    dict.push((key, vim_string(value)));
+                }
+            }
+            // plugins with an empty `on` list are registered, but never loaded
+            if !options.enabled {
+                dict.push(("on", "[]".to_owned()));
+            } else {
+                if !options.on.is_empty() {
+                    dict.push(("on", vim_list(&options.on)));
+                }
+                if !options.filetypes.is_empty() {
+                    dict.push(("for", vim_list(&options.filetypes)));
+                }
+            }
+
+            builder += &format!(
+                "Plug {}, {}\n",
+                vim_string(&plug.get_plug_path()),
+                vim_dict(&dict)
+            );
         }
+
+        builder += "call plug#end()\n";
+        builder += &config_snippets(plugs);
+        builder
     }
 
-    pub fn reload(&self, path: &str) {
-        if let Some(mut nvim) = self.nvim() {
-            nvim.command_async(&format!("source {}", path))
-                .cb(|r| r.report_err())
-                .call()
-        }
+    fn plug_dir(
+        &self,
+        plug: &PlugInfo,
+        base_path: Option<&str>,
+        dirs: &NvimDirs,
+    ) -> Result<PathBuf, String> {
+        Ok(plugged_dir(base_path, dirs).join(&plug.name))
+    }
+
+    fn installed_dirs(&self, base_path: Option<&str>, dirs: &NvimDirs) -> Vec<PathBuf> {
+        child_dirs(&plugged_dir(base_path, dirs), 1)
     }
 }
 
-#[derive(Debug)]
-pub struct VimPlugInfo {
-    pub name: String,
-    pub uri: String,
+/// Options of a `g:plugs` entry, `on` and `for` can be a string or a list.
+fn parse_options(desc: &HashMap<&str, &Value>) -> PlugOptions {
+    let string = |key: &str| {
+        desc.get(key)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+    let list = |key: &str| match desc.get(key) {
+        Some(&&Value::Array(ref items)) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(str::to_owned)
+            .collect(),
+        Some(value) => value
+            .as_str()
+            .map(|value| vec![value.to_owned()])
+            .unwrap_or_default(),
+        None => vec![],
+    };
+
+    PlugOptions {
+        branch: string("branch"),
+        tag: string("tag"),
+        commit: string("commit"),
+        on: list("on"),
+        filetypes: list("for"),
+        post_install: string("do"),
+        ..PlugOptions::default()
+    }
+}
+
+/// `plug#begin()` installs to `stdpath('data') . '/plugged'` by default.
+fn plugged_dir(base_path: Option<&str>, dirs: &NvimDirs) -> PathBuf {
+    match base_path {
+        Some(base_path) => expand_home(base_path),
+        None => dirs.data.join("plugged"),
+    }
 }
 
-impl VimPlugInfo {
-    pub fn new(name: String, uri: String) -> Self {
-        VimPlugInfo { name, uri }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_source() {
+        let plugs = vec![
+            PlugInfo::new(
+                "rust.vim".to_owned(),
+                "https://github.com/rust-lang/rust.vim".to_owned(),
+            ),
+        ];
+        assert_eq!(
+            "call plug#begin()\n\
+             Plug 'rust-lang/rust.vim', { 'as': 'rust.vim' }\n\
+             call plug#end()\n",
+            VimPlug.config_source(&plugs, None)
+        );
+        assert!(
+            VimPlug
+                .config_source(&plugs, Some("~/.vim/plugged"))
+                .starts_with("call plug#begin('~/.vim/plugged')\n")
+        );
+    }
+
+    #[test]
+    fn test_plugged_dir() {
+        let dirs = NvimDirs {
+            config: PathBuf::from("/config/nvim"),
+            data: PathBuf::from("/data/nvim"),
+        };
+        assert_eq!(PathBuf::from("/data/nvim/plugged"), plugged_dir(None, &dirs));
+        assert_eq!(PathBuf::from("/vim/plugged"), plugged_dir(Some("/vim/plugged"), &dirs));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let branch = Value::from("");
+        let tag = Value::from("v1.0");
+        let on = Value::from("RustFmt");
+        let filetypes = Value::Array(vec![Value::from("rust"), Value::from("toml")]);
+        let mut desc = HashMap::new();
+        desc.insert("branch", &branch);
+        desc.insert("tag", &tag);
+        desc.insert("on", &on);
+        desc.insert("for", &filetypes);
+
+        let options = parse_options(&desc);
+        assert_eq!(None, options.branch);
+        assert_eq!(Some("v1.0".to_owned()), options.tag);
+        assert_eq!(vec!["RustFmt".to_owned()], options.on);
+        assert_eq!(vec!["rust".to_owned(), "toml".to_owned()], options.filetypes);
+        assert!(options.enabled);
+    }
+
+    #[test]
+    fn test_config_source_options() {
+        let mut plug = PlugInfo::new(
+            "rust.vim".to_owned(),
+            "https://github.com/rust-lang/rust.vim".to_owned(),
+        );
+        plug.options.tag = Some("v1.0".to_owned());
+        plug.options.filetypes = vec!["rust".to_owned()];
+        plug.options.post_install = Some(":UpdateRemotePlugins".to_owned());
+        plug.options.config = Some("let g:rustfmt_autosave = 1".to_owned());
+        assert_eq!(
+            "call plug#begin()\n\
+             Plug 'rust-lang/rust.vim', { 'as': 'rust.vim', 'tag': 'v1.0', \
+             'do': ':UpdateRemotePlugins', 'for': ['rust'] }\n\
+             call plug#end()\n\
+             \" rust.vim\n\
+             let g:rustfmt_autosave = 1\n",
+            VimPlug.config_source(&[plug.clone()], None)
+        );
+
+        plug.options.enabled = false;
+        assert!(
+            VimPlug
+                .config_source(&[plug], None)
+                .contains("'do': ':UpdateRemotePlugins', 'on': [] }\n")
+        );
     }
 }
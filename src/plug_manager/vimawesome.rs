@@ -1,22 +1,133 @@
-use std::io;
+use std::cell::RefCell;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
 use std::thread;
 use std::rc::Rc;
-use std::process::{Command, Stdio};
+use std::time::Duration;
 
+use reqwest;
 use serde_json;
 
 use gtk;
 use gtk::prelude::*;
 use glib;
+use htmlescape::encode_minimal;
 
+use ui::UiMutex;
+
+use super::backend::expand_home;
+use super::cache::Cache;
 use super::store::PlugInfo;
 
-pub fn call<F>(query: Option<String>, cb: F)
+/// Search API of vimawesome.com, used when no index is configured.
+pub const DEFAULT_INDEX: &str = "https://vimawesome.com/api/plugins";
+
+const SEARCH_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+const README_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+const SNIPPET_MAX_LEN: usize = 600;
+
+/// Where plugins are searched: the vimawesome API, a compatible server,
+/// or a local JSON file in the same format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexSource {
+    Url(String),
+    File(PathBuf),
+}
+
+impl IndexSource {
+    pub fn new(index: Option<&str>) -> Self {
+        match index.map(str::trim).filter(|index| !index.is_empty()) {
+            None => IndexSource::Url(DEFAULT_INDEX.to_owned()),
+            Some(index) if index.starts_with("http://") || index.starts_with("https://") => {
+                IndexSource::Url(index.to_owned())
+            }
+            Some(index) => IndexSource::File(expand_home(index.trim_left_matches("file://"))),
+        }
+    }
+
+    pub fn markup(&self) -> String {
+        match *self {
+            IndexSource::Url(ref url) if url == DEFAULT_INDEX => {
+                "Plugins are taken from: <a href=\"https://vimawesome.com\">https://vimawesome.com</a>"
+                    .to_owned()
+            }
+            IndexSource::Url(ref url) => {
+                let url = encode_minimal(url);
+                format!("Plugins are taken from: <a href=\"{}\">{}</a>", url, url)
+            }
+            IndexSource::File(ref path) => format!(
+                "Plugins are taken from: {}",
+                encode_minimal(&path.to_string_lossy())
+            ),
+        }
+    }
+
+    fn search(
+        &self,
+        query: Option<&str>,
+        cache: Option<&Cache>,
+    ) -> Result<DescriptionList, String> {
+        match *self {
+            IndexSource::Url(ref url) => {
+                let url = format!("{}?query={}&page=1", url, encode_query(query.unwrap_or("")));
+                let body = match cache {
+                    Some(cache) => cache.fetch(&url, SEARCH_MAX_AGE, || http_get(&url))?,
+                    None => http_get(&url)?,
+                };
+                DescriptionList::from_slice(&body)
+            }
+            IndexSource::File(ref path) => {
+                let body = fs::read(path)
+                    .map_err(|e| format!("Can't read plugin index {}: {}", path.display(), e))?;
+                Ok(DescriptionList::from_slice(&body)?.filter(query))
+            }
+        }
+    }
+}
+
+pub fn call<F>(source: IndexSource, query: Option<String>, cb: F)
 where
-    F: FnOnce(io::Result<DescriptionList>) + Send + 'static,
+    F: FnOnce(Result<DescriptionList, String>) + Send + 'static,
+{
+    spawn(
+        move || {
+            let cache = Cache::open().map_err(|e| error!("{}", e)).ok();
+            source.search(query.as_ref().map(|s| s.as_ref()), cache.as_ref())
+        },
+        cb,
+    );
+}
+
+/// Loads the start of the README of a GitHub plugin.
+pub fn call_readme<F>(github_url: String, cb: F)
+where
+    F: FnOnce(Result<String, String>) + Send + 'static,
+{
+    spawn(
+        move || {
+            let url = raw_readme_url(&github_url)
+                .ok_or_else(|| "README is available for GitHub plugins only".to_owned())?;
+            let body = match Cache::open() {
+                Ok(cache) => cache.fetch(&url, README_MAX_AGE, || http_get(&url))?,
+                Err(_) => http_get(&url)?,
+            };
+            Ok(extract_snippet(&String::from_utf8_lossy(&body)))
+        },
+        cb,
+    );
+}
+
+/// Runs `work` in background and passes its result to `cb` on the main thread.
+fn spawn<T, W, F>(work: W, cb: F)
+where
+    T: Send + 'static,
+    W: FnOnce() -> T + Send + 'static,
+    F: FnOnce(T) + Send + 'static,
 {
     thread::spawn(move || {
-        let mut result = Some(request(query.as_ref().map(|s| s.as_ref())));
+        let mut result = Some(work());
         let mut cb = Some(cb);
 
         glib::idle_add(move || {
@@ -26,40 +137,74 @@ where
     });
 }
 
-fn request(query: Option<&str>) -> io::Result<DescriptionList> {
-    let child = Command::new("curl")
-        .arg("-s")
-        .arg(format!(
-            "https://vimawesome.com/api/plugins?query={}&page=1",
-            query.unwrap_or("")
-        ))
-        .stdout(Stdio::piped())
-        .spawn()?;
+fn http_get(url: &str) -> Result<Vec<u8>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .map_err(|e| format!("{}", e))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .map_err(|e| format!("Can't load {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Can't load {}: {}", url, response.status()));
+    }
 
-    let out = child.wait_with_output()?;
+    let mut body = Vec::new();
+    response
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Can't load {}: {}", url, e))?;
+    Ok(body)
+}
 
-    if out.status.success() {
-        if out.stdout.is_empty() {
-            Ok(DescriptionList::empty())
-        } else {
-            let description_list: DescriptionList =
-                serde_json::from_slice(&out.stdout).map_err(|e| {
-                    io::Error::new(io::ErrorKind::Other, e)
-                })?;
-            Ok(description_list)
+/// Percent encodes a query parameter value.
+fn encode_query(query: &str) -> String {
+    query
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn raw_readme_url(github_url: &str) -> Option<String> {
+    let path = github_url
+        .trim_right_matches('/')
+        .trim_right_matches(".git")
+        .splitn(2, "github.com/")
+        .nth(1)?;
+    Some(format!(
+        "https://raw.githubusercontent.com/{}/HEAD/README.md",
+        path
+    ))
+}
+
+/// First paragraphs of a markdown README, without headings, badges and images.
+fn extract_snippet(readme: &str) -> String {
+    let mut snippet = String::new();
+    for paragraph in readme.split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() || paragraph.starts_with('#') || paragraph.starts_with("[![")
+            || paragraph.starts_with("![") || paragraph.starts_with('<')
+            || paragraph.starts_with("===") || paragraph.starts_with("---")
+        {
+            continue;
+        }
+
+        if !snippet.is_empty() {
+            snippet += "\n\n";
+        }
+        snippet += paragraph;
+        if snippet.chars().count() >= SNIPPET_MAX_LEN {
+            let mut cut: String = snippet.chars().take(SNIPPET_MAX_LEN).collect();
+            cut += "…";
+            return cut;
         }
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "curl exit with error:\n{}",
-                match out.status.code() {
-                    Some(code) => format!("Exited with status code: {}", code),
-                    None => "Process terminated by signal".to_owned(),
-                }
-            ),
-        ))
     }
+    snippet
 }
 
 pub fn build_result_panel<F: Fn(PlugInfo) + 'static>(
@@ -91,6 +236,7 @@ fn create_plug_row<F: Fn(PlugInfo) + 'static>(
     row_container.set_border_width(5);
     let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 5);
     let label_box = create_plug_label(plug);
+    let readme = create_readme_expander(plug);
 
 
     let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
@@ -102,6 +248,9 @@ fn create_plug_row<F: Fn(PlugInfo) + 'static>(
     row_container.pack_start(&hbox, true, true, 0);
     hbox.pack_start(&label_box, true, true, 0);
     hbox.pack_start(&button_box, false, true, 0);
+    if let Some(ref readme) = readme {
+        row_container.pack_start(readme, false, true, 0);
+    }
 
     row.add(&row_container);
 
@@ -120,27 +269,66 @@ fn create_plug_row<F: Fn(PlugInfo) + 'static>(
 fn create_plug_label(plug: &Description) -> gtk::Box {
     let label_box = gtk::Box::new(gtk::Orientation::Vertical, 5);
 
+    // the index can come from any server or file, so its strings are escaped
     let name_lbl = gtk::Label::new(None);
     name_lbl.set_markup(&format!(
         "<b>{}</b> by {}",
-        plug.name,
-        plug.author.as_ref().map(|s| s.as_ref()).unwrap_or(
+        encode_minimal(&plug.name),
+        encode_minimal(plug.author.as_ref().map(|s| s.as_ref()).unwrap_or(
             "unknown",
-        )
+        ))
     ));
     name_lbl.set_halign(gtk::Align::Start);
     let url_lbl = gtk::Label::new(None);
     if let Some(url) = plug.github_url.as_ref() {
+        let url = encode_minimal(url);
         url_lbl.set_markup(&format!("<a href=\"{}\">{}</a>", url, url));
     }
     url_lbl.set_halign(gtk::Align::Start);
 
 
     label_box.pack_start(&name_lbl, true, true, 0);
+    if let Some(ref short_desc) = plug.short_desc {
+        let desc_lbl = gtk::Label::new(Some(short_desc.as_str()));
+        desc_lbl.set_halign(gtk::Align::Start);
+        desc_lbl.set_line_wrap(true);
+        label_box.pack_start(&desc_lbl, true, true, 0);
+    }
     label_box.pack_start(&url_lbl, true, true, 0);
     label_box
 }
 
+/// README snippet, loaded when the expander is opened the first time.
+fn create_readme_expander(plug: &Description) -> Option<gtk::Expander> {
+    let github_url = plug.github_url.clone()?;
+    let expander = gtk::Expander::new("README");
+    let readme_lbl = gtk::Label::new(None);
+    readme_lbl.set_halign(gtk::Align::Start);
+    readme_lbl.set_line_wrap(true);
+    readme_lbl.set_selectable(true);
+    expander.add(&readme_lbl);
+
+    let github_url = RefCell::new(Some(github_url));
+    expander.connect_property_expanded_notify(move |expander| {
+        if !expander.get_expanded() {
+            return;
+        }
+        if let Some(github_url) = github_url.borrow_mut().take() {
+            readme_lbl.set_text("Loading…");
+            let readme_lbl = UiMutex::new(readme_lbl.clone());
+            call_readme(github_url, move |res| {
+                let readme_lbl = readme_lbl.borrow();
+                match res {
+                    Ok(snippet) => readme_lbl.set_text(&snippet),
+                    Err(e) => readme_lbl.set_text(&e),
+                }
+            });
+        }
+    });
+
+    Some(expander)
+}
+
 #[derive(Deserialize, Debug)]
 pub struct DescriptionList {
     pub plugins: Box<[Description]>,
@@ -150,6 +338,34 @@ impl DescriptionList {
     fn empty() -> DescriptionList {
         DescriptionList { plugins: Box::new([]) }
     }
+
+    fn from_slice(body: &[u8]) -> Result<DescriptionList, String> {
+        if body.is_empty() {
+            Ok(DescriptionList::empty())
+        } else {
+            serde_json::from_slice(body).map_err(|e| format!("Wrong plugin index: {}", e))
+        }
+    }
+
+    /// Plugins that contain all words of `query`, most starred first.
+    fn filter(self, query: Option<&str>) -> DescriptionList {
+        let words: Vec<String> = query
+            .unwrap_or("")
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        let mut plugins: Vec<Description> = self.plugins
+            .into_vec()
+            .into_iter()
+            .filter(|plug| {
+                let text = plug.search_text();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .collect();
+        plugins.sort_by(|lhs, rhs| rhs.github_stars.cmp(&lhs.github_stars));
+
+        DescriptionList { plugins: plugins.into_boxed_slice() }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -158,4 +374,139 @@ pub struct Description {
     pub github_url: Option<String>,
     pub author: Option<String>,
     pub github_stars: Option<i64>,
+    pub short_desc: Option<String>,
+}
+
+impl Description {
+    fn search_text(&self) -> String {
+        let fields = [
+            Some(&self.name),
+            self.author.as_ref(),
+            self.short_desc.as_ref(),
+        ];
+        let fields: Vec<&str> = fields.iter().filter_map(|f| f.map(String::as_str)).collect();
+        fields.join(" ").to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use test_util::TempDir;
+
+    const INDEX: &str = r#"{"plugins": [
+        {"name": "vim-fugitive", "github_url": "https://github.com/tpope/vim-fugitive",
+         "author": "Tim Pope", "github_stars": 100, "short_desc": "A Git wrapper"},
+        {"name": "vim-rhubarb", "github_url": "https://github.com/tpope/vim-rhubarb",
+         "author": "Tim Pope", "github_stars": 10, "short_desc": "GitHub extension for fugitive"},
+        {"name": "rust.vim", "github_url": "https://github.com/rust-lang/rust.vim",
+         "author": "rust-lang", "github_stars": 50}
+    ]}"#;
+
+    fn names(list: &DescriptionList) -> Vec<&str> {
+        list.plugins.iter().map(|plug| plug.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_index_source() {
+        assert_eq!(
+            IndexSource::Url(DEFAULT_INDEX.to_owned()),
+            IndexSource::new(Some(" "))
+        );
+        assert_eq!(
+            IndexSource::Url("http://plugins.example.com/api".to_owned()),
+            IndexSource::new(Some("http://plugins.example.com/api"))
+        );
+        assert_eq!(
+            IndexSource::File(PathBuf::from("/srv/plugins.json")),
+            IndexSource::new(Some("file:///srv/plugins.json"))
+        );
+    }
+
+    #[test]
+    fn test_search_file() {
+        let dir = TempDir::new("index");
+        let path = dir.join("index.json");
+        fs::write(&path, INDEX).unwrap();
+        let source = IndexSource::File(path.clone());
+
+        let list = source.search(None, None).unwrap();
+        assert_eq!(vec!["vim-fugitive", "rust.vim", "vim-rhubarb"], names(&list));
+        let list = source.search(Some("tim GIT"), None).unwrap();
+        assert_eq!(vec!["vim-fugitive", "vim-rhubarb"], names(&list));
+
+        fs::remove_file(&path).unwrap();
+        assert!(source.search(None, None).is_err());
+    }
+
+    #[test]
+    fn test_search_url() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/plugins", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        // answers a single request, later searches must be served from the cache
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                INDEX.len(),
+                INDEX
+            ).unwrap();
+            tx.send(String::from_utf8_lossy(&request).into_owned())
+                .unwrap();
+        });
+
+        let dir = TempDir::new("vimawesome");
+        let cache = Cache::new(dir.join("cache"));
+        let source = IndexSource::Url(url);
+
+        let list = source.search(Some("git & co"), Some(&cache)).unwrap();
+        assert_eq!(3, list.plugins.len());
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("GET /api/plugins?query=git%20%26%20co&page=1 "));
+
+        let list = source.search(Some("git & co"), Some(&cache)).unwrap();
+        assert_eq!(3, list.plugins.len());
+    }
+
+    #[test]
+    fn test_raw_readme_url() {
+        assert_eq!(
+            Some("https://raw.githubusercontent.com/tpope/vim-fugitive/HEAD/README.md".to_owned()),
+            raw_readme_url("https://github.com/tpope/vim-fugitive.git")
+        );
+        assert_eq!(None, raw_readme_url("https://gitlab.com/user/plugin"));
+    }
+
+    #[test]
+    fn test_extract_snippet() {
+        let readme = "# fugitive.vim\n\n\
+                      [![Build](https://example.com/badge.svg)](https://example.com)\n\n\
+                      Fugitive is the premier Vim plugin for Git.\n\n\
+                      ## Installation\n\n\
+                      Install with your favorite plugin manager.\n";
+        assert_eq!(
+            "Fugitive is the premier Vim plugin for Git.\n\n\
+             Install with your favorite plugin manager.",
+            extract_snippet(readme)
+        );
+
+        let long = "word ".repeat(200);
+        let snippet = extract_snippet(&long);
+        assert_eq!(SNIPPET_MAX_LEN + 1, snippet.chars().count());
+        assert!(snippet.ends_with('…'));
+    }
 }
@@ -14,6 +14,7 @@ use color::ColorModel;
 use nvim::{self, ErrorReport, CompleteItem};
 use shell;
 use input;
+use keymap::Keymap;
 
 const MAX_VISIBLE_ROWS: i32 = 10;
 
@@ -234,7 +235,7 @@ pub struct PopupMenu {
 }
 
 impl PopupMenu {
-    pub fn new(drawing: &gtk::DrawingArea) -> PopupMenu {
+    pub fn new(drawing: &gtk::DrawingArea, keymap: Rc<Keymap>) -> PopupMenu {
         let state = State::new();
         let popover = gtk::Popover::new(Some(drawing));
         popover.set_modal(false);
@@ -282,7 +283,7 @@ impl PopupMenu {
             let state = state_ref.borrow();
             let nvim = state.nvim.as_ref().unwrap().nvim();
             if let Some(mut nvim) = nvim {
-                input::gtk_key_press(&mut *nvim, ev)
+                input::gtk_key_press(&mut *nvim, ev, &keymap)
             } else {
                 Inhibit(false)
             }
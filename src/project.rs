@@ -1,23 +1,36 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::path::Path;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
 
 use pango;
+use glib;
 use gtk;
 use gtk::prelude::*;
-use gtk::{TreeView, ScrolledWindow, PolicyType, ListStore, TreeViewColumn, CellRendererText,
+use gtk::{TreeView, ScrolledWindow, PolicyType, TreeStore, TreeViewColumn, CellRendererText,
           CellRendererPixbuf, CellRendererToggle, Type, Orientation, TreeModel, TreeIter, Popover};
 
-use neovim_lib::{Neovim, NeovimApi, Value};
+use neovim_lib::{Neovim, NeovimApi, NeovimApiAsync, Value};
+use dirs;
+use git;
+use misc::escape_filename;
 use nvim::ErrorReport;
 use shell::Shell;
+use ui::UiMutex;
 
 use htmlescape::encode_minimal;
 
 const MAX_VISIBLE_ROWS: usize = 5;
+const MAX_RECENT_PROJECTS: usize = 10;
+
+/// Files or directories that mark the root directory of a project.
+const PROJECT_MARKERS: [&str; 4] = [".git", "Cargo.toml", "package.json", ".hg"];
 
 const BOOKMARKED_PIXBUF: &str = "user-bookmarks";
 const CURRENT_DIR_PIXBUF: &str = "folder";
+const RECENT_PROJECT_PIXBUF: &str = "document-open-recent";
 const PLAIN_FILE_PIXBUF: &str = "text-x-generic";
 
 enum ProjectViewColumns {
@@ -27,9 +40,10 @@ enum ProjectViewColumns {
     Pixbuf,
     Project,
     ProjectStored,
+    Session,
 }
 
-const COLUMN_COUNT: usize = 6;
+const COLUMN_COUNT: usize = 7;
 const COLUMN_TYPES: [Type; COLUMN_COUNT] = [
     Type::String,
     Type::String,
@@ -37,6 +51,7 @@ const COLUMN_TYPES: [Type; COLUMN_COUNT] = [
     Type::String,
     Type::Bool,
     Type::Bool,
+    Type::Bool,
 ];
 const COLUMN_IDS: [u32; COLUMN_COUNT] = [
     ProjectViewColumns::Name as u32,
@@ -45,17 +60,20 @@ const COLUMN_IDS: [u32; COLUMN_COUNT] = [
     ProjectViewColumns::Pixbuf as u32,
     ProjectViewColumns::Project as u32,
     ProjectViewColumns::ProjectStored as u32,
+    ProjectViewColumns::Session as u32,
 ];
 
 pub struct Projects {
     shell: Rc<RefCell<Shell>>,
     popup: Popover,
+    search_box: gtk::Entry,
     tree: TreeView,
     scroll: ScrolledWindow,
     store: Option<EntryStore>,
     name_renderer: CellRendererText,
     path_renderer: CellRendererText,
     toggle_renderer: CellRendererToggle,
+    session_renderer: CellRendererToggle,
 }
 
 impl Projects {
@@ -63,12 +81,14 @@ impl Projects {
         let projects = Projects {
             shell,
             popup: Popover::new(Some(ref_widget)),
+            search_box: gtk::Entry::new(),
             tree: TreeView::new(),
             scroll: ScrolledWindow::new(None, None),
             store: None,
             name_renderer: CellRendererText::new(),
             path_renderer: CellRendererText::new(),
             toggle_renderer: CellRendererToggle::new(),
+            session_renderer: CellRendererToggle::new(),
         };
 
         projects.setup_tree();
@@ -76,11 +96,14 @@ impl Projects {
         projects.tree.set_activate_on_single_click(true);
         projects.tree.set_hover_selection(true);
         projects.tree.set_grid_lines(gtk::TreeViewGridLines::Horizontal);
+        // Recent files are always expanded below their project.
+        projects.tree.set_show_expanders(false);
+        projects.tree.set_level_indentation(20);
 
         let vbox = gtk::Box::new(Orientation::Vertical, 5);
         vbox.set_border_width(5);
 
-        let search_box = gtk::Entry::new();
+        let search_box = projects.search_box.clone();
         search_box.set_icon_from_icon_name(gtk::EntryIconPosition::Primary, "edit-find-symbolic");
 
         vbox.pack_start(&search_box, false, true, 0);
@@ -111,20 +134,12 @@ impl Projects {
         });
 
         let prj_ref = projects.clone();
-        search_box.connect_changed(move |search_box| {
-            let projects = prj_ref.borrow();
-            let list_store = projects.get_list_store();
-
-            list_store.clear();
-            if let Some(ref store) = projects.store {
-                store.populate(&list_store, search_box.get_text().as_ref());
-            }
-        });
+        search_box.connect_changed(move |_| prj_ref.borrow().populate());
 
         let prj_ref = projects.clone();
         search_box.connect_activate(move |_| {
             let model = prj_ref.borrow().tree.get_model().unwrap();
-            if let Some(iter) = model.get_iter_first() {
+            if let Some(iter) = first_entry(&model) {
                 prj_ref.borrow().open_uri(&model, &iter);
                 let popup = prj_ref.borrow().popup.clone();
                 popup.popdown();
@@ -134,16 +149,18 @@ impl Projects {
         let prj_ref = projects.clone();
         projects.borrow().tree.connect_row_activated(
             move |tree, _, column| {
-                // Don't activate if the user clicked the checkbox.
+                // Don't activate if the user clicked one of the checkboxes.
                 let toggle_column = tree.get_column(2).unwrap();
-                if *column == toggle_column {
+                let session_column = tree.get_column(3).unwrap();
+                if *column == toggle_column || *column == session_column {
                     return;
                 }
                 let selection = tree.get_selection();
                 if let Some((model, iter)) = selection.get_selected() {
-                    prj_ref.borrow().open_uri(&model, &iter);
-                    let popup = prj_ref.borrow().popup.clone();
-                    popup.popdown();
+                    if prj_ref.borrow().open_uri(&model, &iter) {
+                        let popup = prj_ref.borrow().popup.clone();
+                        popup.popdown();
+                    }
                 }
             },
         );
@@ -166,71 +183,230 @@ impl Projects {
                 prj_ref.borrow_mut().toggle_stored(&path)
             },
         );
+
+        let prj_ref = projects.clone();
+        projects.borrow().session_renderer.connect_toggled(
+            move |_, path| {
+                prj_ref.borrow_mut().toggle_session(&path)
+            },
+        );
+
+        projects.borrow().tree.set_has_tooltip(true);
+        projects.borrow().tree.connect_query_tooltip(
+            |tree, x, y, keyboard_mode, tooltip| {
+                if keyboard_mode {
+                    return false;
+                }
+                let (x, y) = tree.convert_widget_to_bin_window_coords(x, y);
+                let (path, column) = match tree.get_path_at_pos(x, y) {
+                    Some((Some(path), Some(column), _, _)) => (path, column),
+                    _ => return false,
+                };
+                let text = if Some(&column) == tree.get_column(2).as_ref() {
+                    "Bookmark project"
+                } else if Some(&column) == tree.get_column(3).as_ref() {
+                    "Restore open files and window layout when opening the project"
+                } else {
+                    return false;
+                };
+                tooltip.set_text(text);
+                tree.set_tooltip_row(tooltip, &path);
+                true
+            },
+        );
         projects
     }
 
     fn toggle_stored(&mut self, path: &gtk::TreePath) {
-        let list_store = self.get_list_store();
-        if let Some(iter) = list_store.get_iter(path) {
-            let value: bool = list_store
+        let tree_store = self.get_tree_store();
+        if let Some(iter) = tree_store.get_iter(path) {
+            let value: bool = tree_store
                 .get_value(&iter, ProjectViewColumns::ProjectStored as i32)
                 .get()
                 .unwrap();
 
-            list_store.set_value(
+            tree_store.set_value(
                 &iter,
                 ProjectViewColumns::ProjectStored as u32,
                 &ToValue::to_value(&!value),
             );
 
-            let pixbuf = if value {
-                CURRENT_DIR_PIXBUF
-            } else {
-                BOOKMARKED_PIXBUF
+            let uri_value = tree_store.get_value(&iter, ProjectViewColumns::Uri as i32);
+            let uri: String = uri_value.get().unwrap();
+
+            let store = self.store.as_mut().unwrap();
+            let pixbuf = match store.find_mut(&uri) {
+                Some(entry) => {
+                    entry.set_stored(!value);
+                    entry.pixbuf
+                }
+                None => CURRENT_DIR_PIXBUF,
             };
 
-            list_store.set_value(
+            tree_store.set_value(
                 &iter,
                 ProjectViewColumns::Pixbuf as u32,
                 &ToValue::to_value(pixbuf),
             );
 
-            let uri_value = list_store.get_value(&iter, ProjectViewColumns::Uri as i32);
-            let uri: String = uri_value.get().unwrap();
+            store.changed();
+        }
 
-            let store = self.store.as_mut().unwrap();
-            if let Some(entry) = store.find_mut(&uri) {
-                entry.stored = !value;
+    }
+
+    fn toggle_session(&mut self, path: &gtk::TreePath) {
+        let tree_store = self.get_tree_store();
+        if let Some(iter) = tree_store.get_iter(path) {
+            let value: bool = tree_store
+                .get_value(&iter, ProjectViewColumns::Session as i32)
+                .get()
+                .unwrap();
+
+            tree_store.set_value(
+                &iter,
+                ProjectViewColumns::Session as u32,
+                &ToValue::to_value(&!value),
+            );
+
+            let uri: String = tree_store
+                .get_value(&iter, ProjectViewColumns::Uri as i32)
+                .get()
+                .unwrap();
+
+            {
+                let store = self.store.as_mut().unwrap();
+                if let Some(entry) = store.find_mut(&uri) {
+                    entry.session = !value;
+                }
+                store.changed();
             }
 
-            store.changed();
+            if value {
+                // Forget the session.
+                if let Err(err) = session_file(&uri).and_then(|file| {
+                    fs::remove_file(file).or_else(|err| match err.kind() {
+                        io::ErrorKind::NotFound => Ok(()),
+                        _ => Err(format!("{}", err)),
+                    })
+                }) {
+                    error!("Can't remove session of {}: {}", uri, err);
+                }
+            }
+
+            let shell = self.shell.borrow();
+            let state = shell.state.borrow();
+            if let Some(mut nvim) = state.nvim() {
+                if get_cwd(&mut nvim).map_or(false, |cwd| cwd == uri) {
+                    set_session_autocmds(&mut nvim, &uri, !value);
+                }
+            }
+        }
+    }
+
+    fn session_enabled(&self, uri: &str) -> bool {
+        match self.store {
+            Some(ref store) => store
+                .projects
+                .iter()
+                .chain(store.recent_projects.iter())
+                .any(|e| e.stored && e.session && e.uri == uri),
+            None => ProjectSettings::load()
+                .projects
+                .iter()
+                .any(|p| p.session && p.path == uri),
+        }
+    }
+
+    /// Saves the session of the current directory, if it is a bookmarked project.
+    pub fn save_session(&self) {
+        let shell = self.shell.borrow();
+        let state = shell.state.borrow();
+        let mut nvim = match state.nvim() {
+            Some(nvim) => nvim,
+            None => return,
+        };
+
+        let cwd = match get_cwd(&mut nvim) {
+            Some(cwd) => cwd,
+            None => return,
+        };
+        if !self.session_enabled(&cwd) {
+            return;
         }
 
+        match session_file(&cwd) {
+            Ok(file) => {
+                if let Some(dir) = file.parent() {
+                    if let Err(err) = fs::create_dir_all(dir) {
+                        error!("Can't create session directory: {}", err);
+                        return;
+                    }
+                }
+                nvim.command(&format!("mksession! {}", escape_filename(&file.to_string_lossy())))
+                    .report_err();
+            }
+            Err(err) => error!("Can't save session: {}", err),
+        }
+    }
+
+    /// Restores the session of a project, returns `false` if there is none.
+    fn restore_session(&self, uri: &str) -> bool {
+        let enabled = self.session_enabled(uri);
+        let shell = self.shell.borrow();
+        let state = shell.state.borrow();
+        let mut nvim = match state.nvim() {
+            Some(nvim) => nvim,
+            None => return false,
+        };
+
+        // Also save the session when nvim is closed from inside.
+        set_session_autocmds(&mut nvim, uri, enabled);
+
+        let file = match session_file(uri) {
+            Ok(ref file) if enabled && file.is_file() => file.to_string_lossy().into_owned(),
+            _ => return false,
+        };
+        nvim.command_async(&format!("silent! %bdelete | source {}", escape_filename(&file)))
+            .cb(|r| r.report_err())
+            .call();
+        true
     }
 
 
-    fn open_uri(&self, model: &TreeModel, iter: &TreeIter) {
+    /// Opens the file or project of a row, returns `false` for section headers.
+    fn open_uri(&self, model: &TreeModel, iter: &TreeIter) -> bool {
         let uri: String = model
             .get_value(iter, ProjectViewColumns::Uri as i32)
             .get()
             .unwrap();
+        if uri.is_empty() {
+            return false;
+        }
         let project: bool = model
             .get_value(iter, ProjectViewColumns::Project as i32)
             .get()
             .unwrap();
 
-        let shell = self.shell.borrow();
         if project {
-            shell.cd(&uri);
+            self.save_session();
+            // change directory first, so the session and files are opened relative to the project
+            self.shell.borrow().cd(&uri);
+            if !self.restore_session(&uri) {
+                self.shell.borrow().open_file(&uri);
+            }
+            // project configuration is applied last, so the session doesn't close its files
+            self.shell.borrow().apply_project_config(&uri);
+        } else {
+            self.shell.borrow().open_file(&uri);
         }
-        shell.open_file(&uri);
+        true
     }
 
-    fn get_list_store(&self) -> ListStore {
+    fn get_tree_store(&self) -> TreeStore {
         self.tree
             .get_model()
             .unwrap()
-            .downcast::<ListStore>()
+            .downcast::<TreeStore>()
             .unwrap()
     }
 
@@ -260,31 +436,61 @@ impl Projects {
         dlg.destroy();
     }
 
-    pub fn show(&mut self) {
-        self.load_oldfiles();
+    pub fn show(projects: &Rc<RefCell<Projects>>) {
+        Projects::load_oldfiles(projects);
 
-        self.popup.popup();
+        projects.borrow().popup.popup();
     }
 
-    fn load_oldfiles(&mut self) {
-        let shell_borrow = self.shell.borrow();
-        let shell_state = shell_borrow.state.borrow_mut();
+    /// Loads the entries in the background, as finding project roots reads the file system.
+    fn load_oldfiles(projects: &Rc<RefCell<Projects>>) {
+        let (cwd, old_files) = {
+            let projects = projects.borrow();
+            let shell = projects.shell.borrow();
+            let state = shell.state.borrow();
+            match state.nvim() {
+                Some(mut nvim) => (get_cwd(&mut nvim), list_old_files(&mut nvim)),
+                None => return,
+            }
+        };
+
+        let projects = UiMutex::new(projects.clone());
+        thread::spawn(move || {
+            let mut store = Some(EntryStore::load(cwd, &old_files));
+
+            glib::idle_add(move || {
+                let projects = projects.borrow();
+                {
+                    let mut projects = projects.borrow_mut();
+                    // Otherwise the popover was closed, or reopened and loaded meanwhile.
+                    if projects.store.is_some() || !projects.popup.is_visible() {
+                        return glib::Continue(false);
+                    }
+                    projects.store = store.take();
+                }
+                projects.borrow().populate();
+                glib::Continue(false)
+            });
+        });
+    }
 
-        let nvim = shell_state.nvim();
-        if let Some(mut nvim) = nvim {
-            let store = EntryStore::load(&mut nvim);
-            store.populate(&self.get_list_store(), None);
-            self.store = Some(store);
+    /// Fills the tree with the entries that match the search text.
+    fn populate(&self) {
+        let tree_store = self.get_tree_store();
+        tree_store.clear();
+        if let Some(ref store) = self.store {
+            store.populate(&tree_store, self.search_box.get_text().as_ref());
         }
+        self.tree.expand_all();
     }
 
     pub fn clear(&mut self) {
         self.store.take().map(|s| s.save());
-        self.get_list_store().clear();
+        self.get_tree_store().clear();
     }
 
     fn setup_tree(&self) {
-        self.tree.set_model(Some(&ListStore::new(&COLUMN_TYPES)));
+        self.tree.set_model(Some(&TreeStore::new(&COLUMN_TYPES)));
         self.tree.set_headers_visible(false);
 
         let image_column = TreeViewColumn::new();
@@ -355,6 +561,24 @@ impl Projects {
         );
 
         self.tree.append_column(&toggle_column);
+
+        let session_column = TreeViewColumn::new();
+        self.session_renderer.set_activatable(true);
+        self.session_renderer.set_padding(10, 0);
+
+        session_column.pack_start(&self.session_renderer, true);
+        session_column.add_attribute(
+            &self.session_renderer,
+            "visible",
+            ProjectViewColumns::ProjectStored as i32,
+        );
+        session_column.add_attribute(
+            &self.session_renderer,
+            "active",
+            ProjectViewColumns::Session as i32,
+        );
+
+        self.tree.append_column(&session_column);
     }
 
 
@@ -390,6 +614,12 @@ fn on_treeview_allocate(projects: Rc<RefCell<Projects>>) {
 }
 
 
+fn get_cwd(nvim: &mut Neovim) -> Option<String> {
+    nvim.call_function("getcwd", vec![])
+        .ok_and_report()
+        .and_then(|cwd| cwd.as_str().map(str::to_owned))
+}
+
 fn list_old_files(nvim: &mut Neovim) -> Vec<String> {
 
     let oldfiles_var = nvim.get_vvar("oldfiles");
@@ -415,41 +645,89 @@ fn list_old_files(nvim: &mut Neovim) -> Vec<String> {
     }
 }
 
+/// First row that is not a section header.
+fn first_entry(model: &TreeModel) -> Option<TreeIter> {
+    let iter = model.get_iter_first()?;
+    loop {
+        let uri: String = model
+            .get_value(&iter, ProjectViewColumns::Uri as i32)
+            .get()
+            .unwrap();
+        if !uri.is_empty() {
+            return Some(iter);
+        }
+        if !model.iter_next(&iter) {
+            return None;
+        }
+    }
+}
+
+/// Inserts a section header, it has no uri so it can't be opened.
+fn insert_section(tree_store: &TreeStore, title: &str) {
+    tree_store.insert_with_values(
+        None,
+        None,
+        &[
+            ProjectViewColumns::Name as u32,
+            ProjectViewColumns::Path as u32,
+            ProjectViewColumns::Uri as u32,
+        ],
+        &[&title, &"", &""],
+    );
+}
+
 pub struct EntryStore {
-    entries: Vec<Entry>,
+    /// Bookmarked projects and the current directory.
+    projects: Vec<Entry>,
+    recent_projects: Vec<Entry>,
+    /// Recent files outside of the listed projects.
+    recent_files: Vec<Entry>,
     changed: bool,
 }
 
 impl EntryStore {
     pub fn find_mut(&mut self, uri: &str) -> Option<&mut Entry> {
-        self.entries.iter_mut().find(|e| e.project && e.uri == uri)
+        self.projects
+            .iter_mut()
+            .chain(self.recent_projects.iter_mut())
+            .find(|e| e.uri == uri)
     }
 
-    pub fn load(nvim: &mut Neovim) -> EntryStore {
-        let mut entries = Vec::new();
-
-        for project in ProjectSettings::load().projects {
-            entries.push(project.to_entry());
+    /// Builds the entries of the current directory `cwd` and the recently used `old_files`.
+    ///
+    /// Reads the file system to find project roots, so it shouldn't run on the UI thread.
+    pub fn load(cwd: Option<String>, old_files: &[String]) -> EntryStore {
+        let mut projects: Vec<Entry> = ProjectSettings::load()
+            .projects
+            .iter()
+            .map(ProjectEntrySettings::to_entry)
+            .collect();
+
+        if let Some(cwd) = cwd {
+            if !projects.iter().any(|e| e.uri == cwd) {
+                projects.insert(0, Entry::new_current_project(&cwd));
+            }
         }
 
-        match nvim.call_function("getcwd", vec![]) {
-            Ok(pwd) => {
-                if let Some(pwd) = pwd.as_str() {
-                    if entries.iter().find(|e| e.project && e.uri == pwd).is_none() {
-                        entries.insert(0, Entry::new_current_project(pwd));
-                    }
-                } else {
-                    println!("Error get current directory");
-                }
+        let (roots, mut other_files) = recent_projects(old_files);
+        let mut recent_projects = Vec::new();
+        for (root, files) in roots {
+            let uri = root.to_string_lossy().into_owned();
+            if let Some(project) = projects.iter_mut().find(|e| e.uri == uri) {
+                project.files = files.iter().map(|f| Entry::new_from_path(f)).collect();
+            } else if recent_projects.len() < MAX_RECENT_PROJECTS {
+                let mut project = Entry::new_recent_project(&uri);
+                project.files = files.iter().map(|f| Entry::new_from_path(f)).collect();
+                recent_projects.push(project);
+            } else {
+                other_files.extend(files);
             }
-            err @ Err(_) => err.report_err(),
         }
 
-        let old_files = list_old_files(nvim);
-        entries.extend(old_files.iter().map(|p| Entry::new_from_path(p)));
-
         EntryStore {
-            entries,
+            projects,
+            recent_projects,
+            recent_files: other_files.iter().map(|f| Entry::new_from_path(f)).collect(),
             changed: false,
         }
     }
@@ -457,26 +735,35 @@ impl EntryStore {
     pub fn save(&self) {
         if self.changed {
             ProjectSettings::new(
-                self.entries
+                self.projects
                     .iter()
-                    .filter(|e| e.project && e.stored)
+                    .chain(self.recent_projects.iter())
+                    .filter(|e| e.stored)
                     .map(|p| p.to_entry_settings())
                     .collect(),
             ).save();
         }
     }
 
-    pub fn populate(&self, list_store: &ListStore, filter: Option<&String>) {
-        for file in &self.entries {
-            if match filter.map(|f| f.to_uppercase()) {
-                Some(ref filter) => {
-                    file.file_name.to_uppercase().contains(filter) ||
-                        file.path.to_uppercase().contains(filter)
-                }
-                None => true,
+    pub fn populate(&self, tree_store: &TreeStore, filter: Option<&String>) {
+        let filter = filter.map(|f| f.to_uppercase());
+        let filter = filter.as_ref().map(String::as_str);
+
+        for project in &self.projects {
+            project.insert(tree_store, None, filter);
+        }
+
+        if self.recent_projects.iter().any(|p| p.is_visible(filter)) {
+            insert_section(tree_store, "Recent Projects");
+            for project in &self.recent_projects {
+                project.insert(tree_store, None, filter);
             }
-            {
-                list_store.insert_with_values(None, &COLUMN_IDS, &file.to_values());
+        }
+
+        if self.recent_files.iter().any(|f| f.is_visible(filter)) {
+            insert_section(tree_store, "Recent Files");
+            for file in &self.recent_files {
+                file.insert(tree_store, None, filter);
             }
         }
     }
@@ -492,30 +779,41 @@ pub struct Entry {
     file_name: String,
     name: String,
     pixbuf: &'static str,
+    /// Icon shown when the project is not bookmarked.
+    unstored_pixbuf: &'static str,
     project: bool,
     stored: bool,
+    session: bool,
+    /// Recent files of a project.
+    files: Vec<Entry>,
 }
 
 impl Entry {
-    fn new_project(name: &str, uri: &str) -> Entry {
-        let path = Path::new(uri);
-
+    fn new_project(name: &str, uri: &str, session: bool) -> Entry {
         Entry {
             uri: uri.to_owned(),
-            path: path.parent()
-                .map(|s| {
-                    format!("<small>{}</small>", encode_minimal(&s.to_string_lossy()))
-                })
-                .unwrap_or_else(|| "".to_owned()),
+            path: project_path_markup(Path::new(uri)),
             file_name: encode_minimal(name),
             name: name.to_owned(),
             pixbuf: BOOKMARKED_PIXBUF,
+            unstored_pixbuf: CURRENT_DIR_PIXBUF,
             project: true,
             stored: true,
+            session,
+            files: Vec::new(),
         }
     }
 
     fn new_current_project(uri: &str) -> Entry {
+        Entry::new_unstored_project(uri, CURRENT_DIR_PIXBUF)
+    }
+
+    /// Project root found from the recent files.
+    fn new_recent_project(uri: &str) -> Entry {
+        Entry::new_unstored_project(uri, RECENT_PROJECT_PIXBUF)
+    }
+
+    fn new_unstored_project(uri: &str, pixbuf: &'static str) -> Entry {
         let path = Path::new(uri);
         let name = path.file_name()
             .map(|f| f.to_string_lossy().as_ref().to_owned())
@@ -523,16 +821,15 @@ impl Entry {
 
         Entry {
             uri: uri.to_owned(),
-            path: path.parent()
-                .map(|s| {
-                    format!("<small>{}</small>", encode_minimal(&s.to_string_lossy()))
-                })
-                .unwrap_or_else(|| "".to_owned()),
+            path: project_path_markup(path),
             file_name: encode_minimal(&name),
             name,
-            pixbuf: CURRENT_DIR_PIXBUF,
+            pixbuf,
+            unstored_pixbuf: pixbuf,
             project: true,
             stored: false,
+            session: false,
+            files: Vec::new(),
         }
     }
 
@@ -552,8 +849,47 @@ impl Entry {
             file_name: encode_minimal(&name),
             name,
             pixbuf: PLAIN_FILE_PIXBUF,
+            unstored_pixbuf: PLAIN_FILE_PIXBUF,
             project: false,
             stored: false,
+            session: false,
+            files: Vec::new(),
+        }
+    }
+
+    fn set_stored(&mut self, stored: bool) {
+        self.stored = stored;
+        self.pixbuf = if stored {
+            BOOKMARKED_PIXBUF
+        } else {
+            self.unstored_pixbuf
+        };
+    }
+
+    /// Whether the entry matches the upper case `filter`.
+    fn matches(&self, filter: &str) -> bool {
+        self.file_name.to_uppercase().contains(filter) || self.path.to_uppercase().contains(filter)
+    }
+
+    /// Whether the entry or one of its files matches the upper case `filter`.
+    fn is_visible(&self, filter: Option<&str>) -> bool {
+        filter.map_or(true, |filter| {
+            self.matches(filter) || self.files.iter().any(|file| file.matches(filter))
+        })
+    }
+
+    /// Inserts the entry with its files, all files are shown if the entry itself matches.
+    fn insert(&self, tree_store: &TreeStore, parent: Option<&TreeIter>, filter: Option<&str>) {
+        if !self.is_visible(filter) {
+            return;
+        }
+
+        let iter = tree_store.insert_with_values(parent, None, &COLUMN_IDS, &self.to_values());
+        let all_files = filter.map_or(true, |filter| self.matches(filter));
+        for file in &self.files {
+            if all_files || file.is_visible(filter) {
+                file.insert(tree_store, Some(&iter), None);
+            }
         }
     }
 
@@ -566,12 +902,13 @@ impl Entry {
                 &self.pixbuf,
                 &self.project,
                 &self.stored,
+                &self.session,
             ],
         )
     }
 
     fn to_entry_settings(&self) -> ProjectEntrySettings {
-        ProjectEntrySettings::new(&self.name, &self.uri)
+        ProjectEntrySettings::new(&self.name, &self.uri, self.session)
     }
 }
 
@@ -589,18 +926,21 @@ struct ProjectSettings {
 struct ProjectEntrySettings {
     name: String,
     path: String,
+    #[serde(default)]
+    session: bool,
 }
 
 impl ProjectEntrySettings {
-    fn new(name: &str, path: &str) -> ProjectEntrySettings {
+    fn new(name: &str, path: &str, session: bool) -> ProjectEntrySettings {
         ProjectEntrySettings {
             name: name.to_owned(),
             path: path.to_owned(),
+            session,
         }
     }
 
     fn to_entry(&self) -> Entry {
-        Entry::new_project(&self.name, &self.path)
+        Entry::new_project(&self.name, &self.path, self.session)
     }
 }
 
@@ -621,3 +961,345 @@ impl ProjectSettings {
         ProjectSettings { projects }
     }
 }
+
+// ----- Project roots
+//
+/// Path markup of a project, with repository name and branch if it is under version control.
+fn project_path_markup(path: &Path) -> String {
+    let mut markup = path.parent()
+        .map(|s| {
+            format!("<small>{}</small>", encode_minimal(&s.to_string_lossy()))
+        })
+        .unwrap_or_else(|| "".to_owned());
+    if let Some((repo, branch)) = vcs_info(path) {
+        markup.push_str(&format!(
+            " <small>· {} <b>{}</b></small>",
+            encode_minimal(&repo),
+            encode_minimal(&branch)
+        ));
+    }
+    markup
+}
+
+fn is_project_root(dir: &Path) -> bool {
+    PROJECT_MARKERS.iter().any(|marker| dir.join(marker).exists())
+}
+
+/// Nearest directory above `file` that contains one of the `PROJECT_MARKERS`.
+fn find_project_root(file: &Path) -> Option<PathBuf> {
+    let mut dir = file.parent();
+    while let Some(current) = dir {
+        if is_project_root(current) {
+            return Some(current.to_path_buf());
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+/// Roots of the projects that contain `old_files` with their files, ranked by frecency,
+/// and the files that are not part of a project.
+///
+/// `old_files` are ordered from the most recently used one, so each file adds a score that is
+/// higher the more recently it was used.
+fn recent_projects(old_files: &[String]) -> (Vec<(PathBuf, Vec<String>)>, Vec<String>) {
+    let mut projects: Vec<(PathBuf, usize, Vec<String>)> = Vec::new();
+    let mut other_files = Vec::new();
+    for (idx, file) in old_files.iter().enumerate() {
+        match find_project_root(Path::new(file)) {
+            Some(root) => {
+                let score = old_files.len() - idx;
+                match projects.iter().position(|&(ref project, _, _)| *project == root) {
+                    Some(pos) => {
+                        projects[pos].1 += score;
+                        projects[pos].2.push(file.clone());
+                    }
+                    None => projects.push((root, score, vec![file.clone()])),
+                }
+            }
+            None => other_files.push(file.clone()),
+        }
+    }
+    projects.sort_by(|&(_, lhs, _), &(_, rhs, _)| rhs.cmp(&lhs));
+    let projects = projects
+        .into_iter()
+        .map(|(root, _, files)| (root, files))
+        .collect();
+    (projects, other_files)
+}
+
+/// Repository name and current branch of a project root.
+fn vcs_info(root: &Path) -> Option<(String, String)> {
+    let dir_name = || root.file_name().map(|name| name.to_string_lossy().into_owned());
+
+    if let Some(git_dir) = git_dir(root) {
+        let branch = fs::read_to_string(git_dir.join("HEAD"))
+            .ok()
+            .and_then(|head| parse_git_head(&head))?;
+        let repo = fs::read_to_string(git_common_dir(&git_dir).join("config"))
+            .ok()
+            .and_then(|config| git::origin_url(&config))
+            .and_then(|url| repo_name(&url))
+            .or_else(dir_name)?;
+        return Some((repo, branch));
+    }
+
+    let hg_dir = root.join(".hg");
+    if hg_dir.is_dir() {
+        let branch = fs::read_to_string(hg_dir.join("branch"))
+            .map(|branch| branch.trim().to_owned())
+            .unwrap_or_else(|_| "default".to_owned());
+        return Some((dir_name()?, branch));
+    }
+
+    None
+}
+
+/// Git directory of a repository, following the `gitdir:` file of worktrees and submodules.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let git_dir = contents.trim().trim_left_matches("gitdir:").trim();
+    Some(root.join(git_dir))
+}
+
+/// Directory shared by the worktrees of a repository, it contains the config.
+fn git_common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Branch name from the contents of `HEAD`, or the abbreviated commit if it is detached.
+fn parse_git_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    if head.starts_with("ref:") {
+        let reference = head.trim_left_matches("ref:").trim();
+        Some(reference.trim_left_matches("refs/heads/").to_owned())
+    } else if head.len() >= 7 {
+        head.get(..7).map(str::to_owned)
+    } else {
+        None
+    }
+}
+
+/// Repository name from the url of a git remote.
+fn repo_name(url: &str) -> Option<String> {
+    let url = url.trim_right_matches('/');
+    let name = url.rsplit(|c| c == '/' || c == ':').next()?;
+    let name = name.trim_right_matches(".git");
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+// ----- Sessions
+//
+const SESSION_AUGROUP: &str = "NvimGtkSession";
+
+/// File that stores the session of the project in directory `path`.
+fn session_file(path: &str) -> Result<PathBuf, String> {
+    let mut file = dirs::get_app_config_dir()?;
+    file.push("sessions");
+    file.push(session_file_name(path));
+    Ok(file)
+}
+
+/// Flattens a path into a file name, the same way Vim names undo files.
+fn session_file_name(path: &str) -> String {
+    let name: String = path.trim_right_matches(|c| c == '/' || c == '\\')
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '%',
+            c => c,
+        })
+        .collect();
+    name + ".vim"
+}
+
+/// Commands that save the session to `session_file` when nvim exits, or only clear the
+/// autocommand.
+fn session_autocmds(session_file: Option<&str>) -> Vec<String> {
+    // `au!` can't be followed by `|`, so the commands are executed one by one.
+    let mut commands = vec![
+        format!("augroup {}", SESSION_AUGROUP),
+        "au!".to_owned(),
+        "augroup END".to_owned(),
+    ];
+    if let Some(session_file) = session_file {
+        commands.push(format!(
+            "au {} VimLeavePre * mksession! {}",
+            SESSION_AUGROUP,
+            escape_filename(session_file)
+        ));
+    }
+    commands
+}
+
+fn set_session_autocmds(nvim: &mut Neovim, uri: &str, enabled: bool) {
+    let file = if enabled {
+        session_file(uri)
+            .ok()
+            .map(|file| file.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+    for command in session_autocmds(file.as_ref().map(String::as_str)) {
+        nvim.command_async(&command)
+            .cb(|r| r.report_err())
+            .call();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_util::TempDir;
+
+    fn path_str(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let dir = TempDir::new("project-root");
+        fs::create_dir_all(dir.join("repo").join(".git")).unwrap();
+        let lib = dir.join("repo").join("crates").join("lib");
+        fs::create_dir_all(lib.join("src")).unwrap();
+        fs::write(lib.join("Cargo.toml"), "").unwrap();
+        fs::create_dir_all(dir.join("plain")).unwrap();
+
+        assert_eq!(
+            Some(dir.join("repo")),
+            find_project_root(&dir.join("repo").join("README.md"))
+        );
+        // The nearest marker wins.
+        assert_eq!(
+            Some(lib.clone()),
+            find_project_root(&lib.join("src").join("lib.rs"))
+        );
+        assert_eq!(None, find_project_root(&dir.join("plain").join("notes.txt")));
+    }
+
+    #[test]
+    fn test_recent_projects() {
+        let dir = TempDir::new("recent-projects");
+        fs::create_dir_all(dir.join("web").join(".hg")).unwrap();
+        fs::create_dir_all(dir.join("app")).unwrap();
+        fs::write(dir.join("app").join("package.json"), "{}").unwrap();
+
+        let old_files = vec![
+            path_str(&dir.join("web").join("index.html")),
+            path_str(&dir.join("app").join("a.js")),
+            path_str(&dir.join("app").join("b.js")),
+            path_str(&dir.join("notes.txt")),
+            path_str(&dir.join("app").join("c.js")),
+        ];
+        // web: 5, app: 4 + 3 + 1
+        let (projects, other_files) = recent_projects(&old_files);
+        assert_eq!(
+            vec![
+                (
+                    dir.join("app"),
+                    vec![old_files[1].clone(), old_files[2].clone(), old_files[4].clone()],
+                ),
+                (dir.join("web"), vec![old_files[0].clone()]),
+            ],
+            projects
+        );
+        assert_eq!(vec![old_files[3].clone()], other_files);
+    }
+
+    #[test]
+    fn test_vcs_info() {
+        let dir = TempDir::new("vcs-info");
+        let git_dir = dir.join("checkout").join(".git");
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/tabs\n").unwrap();
+        fs::write(
+            git_dir.join("config"),
+            "[core]\n\tbare = false\n[remote \"origin\"]\n\turl = https://github.com/daa84/neovim-gtk.git\n",
+        ).unwrap();
+        assert_eq!(
+            Some(("neovim-gtk".to_owned(), "feature/tabs".to_owned())),
+            vcs_info(&dir.join("checkout"))
+        );
+
+        // The config of a worktree is in the common git directory.
+        let worktree_git_dir = git_dir.join("worktrees").join("worktree");
+        fs::create_dir_all(&worktree_git_dir).unwrap();
+        fs::write(worktree_git_dir.join("HEAD"), "ref: refs/heads/fix\n").unwrap();
+        fs::write(worktree_git_dir.join("commondir"), "../..\n").unwrap();
+        let worktree = dir.join("worktree");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../checkout/.git/worktrees/worktree\n",
+        ).unwrap();
+        assert_eq!(
+            Some(("neovim-gtk".to_owned(), "fix".to_owned())),
+            vcs_info(&worktree)
+        );
+
+        fs::create_dir_all(dir.join("hg").join(".hg")).unwrap();
+        assert_eq!(
+            Some(("hg".to_owned(), "default".to_owned())),
+            vcs_info(&dir.join("hg"))
+        );
+
+        assert_eq!(None, vcs_info(&dir));
+    }
+
+    #[test]
+    fn test_parse_git_head() {
+        assert_eq!(Some("master".to_owned()), parse_git_head("ref: refs/heads/master\n"));
+        assert_eq!(
+            Some("1a2b3c4".to_owned()),
+            parse_git_head("1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d\n")
+        );
+        assert_eq!(None, parse_git_head(""));
+    }
+
+    #[test]
+    fn test_repo_name() {
+        assert_eq!(
+            Some("neovim-gtk".to_owned()),
+            repo_name("git@github.com:daa84/neovim-gtk.git")
+        );
+        assert_eq!(
+            Some("neovim-gtk".to_owned()),
+            repo_name("https://github.com/daa84/neovim-gtk/")
+        );
+        assert_eq!(None, repo_name("git@host:"));
+    }
+
+    #[test]
+    fn test_session_file_name() {
+        assert_eq!("%home%user%project.vim", session_file_name("/home/user/project/"));
+        assert_eq!("C%%work%nvim-gtk.vim", session_file_name("C:\\work\\nvim-gtk"));
+    }
+
+    #[test]
+    fn test_session_autocmds() {
+        assert_eq!(
+            vec!["augroup NvimGtkSession", "au!", "augroup END"],
+            session_autocmds(None)
+        );
+        assert_eq!(
+            vec![
+                "augroup NvimGtkSession",
+                "au!",
+                "augroup END",
+                "au NvimGtkSession VimLeavePre * mksession! /tmp/a\\ b.vim",
+            ],
+            session_autocmds(Some("/tmp/a b.vim"))
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Project-local GUI configuration.
+//!
+//! A project root can contain a `.nvim-gtk.toml` and a `ginit.vim`. As both can run
+//! arbitrary commands, they are only applied after the user trusted them. Trust is bound to
+//! the contents of the files, so changed files are asked for again. The contents that were
+//! trusted are applied, the files aren't read again.
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+use std::sync::Arc;
+
+use gio::prelude::*;
+use gtk;
+use gtk::prelude::*;
+use gtk::{ButtonsType, MessageDialog, MessageType};
+use htmlescape::encode_minimal;
+use toml;
+
+use neovim_lib::{NeovimApiAsync, Value};
+
+use misc::{escape_filename, FnvHasher};
+use nvim::ErrorReport;
+use settings::SettingsLoader;
+use shell::State;
+use ui::UiMutex;
+
+const CONFIG_FILE: &str = ".nvim-gtk.toml";
+const GINIT_FILE: &str = "ginit.vim";
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// Font size in points.
+    font_size: Option<i32>,
+    sidebar: Option<bool>,
+    /// Neovim commands to run after opening the project.
+    #[serde(default)]
+    commands: Vec<String>,
+    /// Files to open, relative to the project root.
+    #[serde(default)]
+    open: Vec<String>,
+}
+
+// Not loaded with `SettingsLoader`, that reads the file again: the configuration has to be
+// parsed from the same bytes the trust digest was computed from.
+impl ProjectConfig {
+    fn from_str(s: &str) -> Result<Self, String> {
+        toml::from_str(&s).map_err(|e| format!("{}", e))
+    }
+}
+
+/// Configuration files found in a project root.
+struct LocalConfig {
+    root: String,
+    /// Whether `.nvim-gtk.toml` exists.
+    has_config: bool,
+    config: ProjectConfig,
+    /// Contents of `ginit.vim`.
+    ginit: Option<String>,
+    digest: String,
+}
+
+impl LocalConfig {
+    /// Loads the configuration of `root`, `None` if there is none.
+    fn load(root: &str) -> Result<Option<LocalConfig>, String> {
+        let config_path = Path::new(root).join(CONFIG_FILE);
+        let ginit_path = Path::new(root).join(GINIT_FILE);
+
+        let config_contents = fs::read(&config_path).ok();
+        let ginit_contents = fs::read(&ginit_path).ok();
+        if config_contents.is_none() && ginit_contents.is_none() {
+            return Ok(None);
+        }
+
+        let config = match config_contents {
+            Some(ref contents) => String::from_utf8(contents.clone())
+                .map_err(|e| format!("{}", e))
+                .and_then(|contents| ProjectConfig::from_str(&contents))
+                .map_err(|e| format!("{}: {}", config_path.display(), e))?,
+            None => ProjectConfig::default(),
+        };
+        let ginit = match ginit_contents {
+            Some(ref contents) => Some(
+                String::from_utf8(contents.clone())
+                    .map_err(|e| format!("{}: {}", ginit_path.display(), e))?,
+            ),
+            None => None,
+        };
+        let digest = digest(&[
+            config_contents.as_ref().map_or(&[][..], Vec::as_slice),
+            ginit_contents.as_ref().map_or(&[][..], Vec::as_slice),
+        ]);
+
+        Ok(Some(LocalConfig {
+            root: root.to_owned(),
+            has_config: config_contents.is_some(),
+            config,
+            ginit,
+            digest,
+        }))
+    }
+
+    fn files(&self) -> Vec<&str> {
+        let mut files = Vec::new();
+        if self.has_config {
+            files.push(CONFIG_FILE);
+        }
+        if self.ginit.is_some() {
+            files.push(GINIT_FILE);
+        }
+        files
+    }
+
+    fn apply(&self, state_ref: &Arc<UiMutex<State>>) {
+        if let Some(size) = self.config.font_size {
+            state_ref.borrow_mut().set_font_size(size);
+        }
+
+        let state = state_ref.borrow();
+        if let Some(show) = self.config.sidebar {
+            let action = state
+                .window()
+                .and_then(|window| window.get_application())
+                .and_then(|app| app.lookup_action("show-sidebar"));
+            if let Some(action) = action {
+                action.change_state(&show.to_variant());
+            }
+        }
+
+        if let Some(mut nvim) = state.nvim() {
+            if let Some(ref ginit) = self.ginit {
+                let lines = script_lines(ginit).into_iter().map(Value::from).collect();
+                nvim.call_function_async("execute", vec![Value::Array(lines)])
+                    .cb(|r| r.report_err())
+                    .call();
+            }
+            for command in nvim_commands(&self.config) {
+                nvim.command_async(&command)
+                    .cb(|r| r.report_err())
+                    .call();
+            }
+        }
+    }
+}
+
+/// Lines of a Vim script for `execute()`, with continuation lines joined like `:source` does.
+fn script_lines(script: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in script.lines() {
+        let trimmed = line.trim_left();
+        if trimmed.starts_with("\"\\ ") {
+            continue;
+        }
+        if trimmed.starts_with('\\') {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(&trimmed[1..]);
+                continue;
+            }
+        }
+        lines.push(line.to_owned());
+    }
+    lines
+}
+
+/// Neovim commands that apply the configuration, run in the project root.
+fn nvim_commands(config: &ProjectConfig) -> Vec<String> {
+    let mut commands = config.commands.clone();
+
+    let mut files = config.open.iter();
+    if let Some(first) = files.next() {
+        commands.push(format!("e {}", escape_filename(first)));
+    }
+    commands.extend(files.map(|file| format!("badd {}", escape_filename(file))));
+    commands
+}
+
+/// Stable hash of the configuration files.
+fn digest(contents: &[&[u8]]) -> String {
+    let mut hasher = FnvHasher::default();
+    for bytes in contents {
+        hasher.write(bytes);
+        // separate files, so moving content from one file to the other changes the digest
+        hasher.write(&[0xff]);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Applies the configuration of a project root, asking for trust the first time.
+pub fn apply(state_ref: &Arc<UiMutex<State>>, root: &str) {
+    let local_config = match LocalConfig::load(root) {
+        Ok(Some(local_config)) => local_config,
+        Ok(None) => return,
+        Err(err) => {
+            error!("Can't load project configuration: {}", err);
+            return;
+        }
+    };
+
+    match TrustStore::load().decision(root, &local_config.digest) {
+        Some(true) => local_config.apply(state_ref),
+        Some(false) => (),
+        None => ask_trust(state_ref, local_config),
+    }
+}
+
+fn ask_trust(state_ref: &Arc<UiMutex<State>>, local_config: LocalConfig) {
+    let window = state_ref.borrow().window();
+    let flags = gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT;
+    let dlg = MessageDialog::new(
+        window.as_ref(),
+        flags,
+        MessageType::Question,
+        ButtonsType::None,
+        "Apply project configuration?",
+    );
+    dlg.set_property_secondary_use_markup(true);
+    dlg.set_property_secondary_text(Some(
+        format!(
+            "<b>{}</b> contains {}, which can change the GUI and run Neovim commands.",
+            encode_minimal(&local_config.root),
+            local_config.files().join(" and ")
+        ).as_str(),
+    ));
+
+    const TRUST_ID: i32 = 0;
+    const DONT_TRUST_ID: i32 = 1;
+
+    dlg.add_buttons(&[("_Don't Trust", DONT_TRUST_ID), ("_Trust", TRUST_ID)]);
+
+    let state_ref = state_ref.clone();
+    dlg.connect_response(move |dlg, response| {
+        // Closing the dialog decides nothing, so it's asked again next time.
+        if response == TRUST_ID || response == DONT_TRUST_ID {
+            let trusted = response == TRUST_ID;
+            let mut store = TrustStore::load();
+            store.set(&local_config.root, &local_config.digest, trusted);
+            store.save();
+
+            if trusted {
+                local_config.apply(&state_ref);
+            }
+        }
+        dlg.destroy();
+    });
+    dlg.show();
+}
+
+// ----- Store / Load trust decisions
+//
+#[derive(Serialize, Deserialize)]
+struct TrustStore {
+    #[serde(default)]
+    projects: Vec<TrustEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrustEntry {
+    path: String,
+    digest: String,
+    trusted: bool,
+}
+
+impl TrustStore {
+    /// Decision for the given configuration, `None` if the user hasn't decided yet.
+    fn decision(&self, path: &str, digest: &str) -> Option<bool> {
+        self.projects
+            .iter()
+            .find(|entry| entry.path == path && entry.digest == digest)
+            .map(|entry| entry.trusted)
+    }
+
+    fn set(&mut self, path: &str, digest: &str, trusted: bool) {
+        self.projects.retain(|entry| entry.path != path);
+        self.projects.push(TrustEntry {
+            path: path.to_owned(),
+            digest: digest.to_owned(),
+            trusted,
+        });
+    }
+}
+
+impl SettingsLoader for TrustStore {
+    const SETTINGS_FILE: &'static str = "trusted-projects.toml";
+
+    fn empty() -> TrustStore {
+        TrustStore { projects: vec![] }
+    }
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        toml::from_str(&s).map_err(|e| format!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = ProjectConfig::from_str(
+            "font_size = 18\n\
+             commands = [\"set colorcolumn=100\"]\n\
+             open = [\"README.md\", \"src/main.rs\"]\n",
+        ).unwrap();
+        assert_eq!(Some(18), config.font_size);
+        assert_eq!(None, config.sidebar);
+        assert_eq!(vec!["README.md", "src/main.rs"], config.open);
+
+        assert_eq!(ProjectConfig::default(), ProjectConfig::from_str("").unwrap());
+        assert!(ProjectConfig::from_str("font_size = \"big\"").is_err());
+    }
+
+    #[test]
+    fn test_nvim_commands() {
+        let config = ProjectConfig {
+            commands: vec!["set tw=80".to_owned()],
+            open: vec!["a b.txt".to_owned(), "c.txt".to_owned()],
+            ..ProjectConfig::default()
+        };
+        assert_eq!(
+            vec!["set tw=80", "e a\\ b.txt", "badd c.txt"],
+            nvim_commands(&config)
+        );
+        assert!(nvim_commands(&ProjectConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_script_lines() {
+        let script = "set tw=80\n\
+                      let g:list = [\n\
+                      \\   1,\n\
+                      \"\\ comment\n\
+                      \\ 2]\n\
+                      function! F()\n\
+                      endfunction\n";
+        assert_eq!(
+            vec!["set tw=80", "let g:list = [   1, 2]", "function! F()", "endfunction"],
+            script_lines(script)
+        );
+    }
+
+    #[test]
+    fn test_digest() {
+        let digest_of = |config: &str, ginit: &str| digest(&[config.as_bytes(), ginit.as_bytes()]);
+        assert_eq!(digest_of("font_size = 18", ""), digest_of("font_size = 18", ""));
+        assert!(digest_of("font_size = 18", "") != digest_of("font_size = 20", ""));
+        assert!(digest_of("ab", "") != digest_of("a", "b"));
+    }
+
+    #[test]
+    fn test_trust_store() {
+        let mut store = TrustStore::empty();
+        assert_eq!(None, store.decision("/project", "1234"));
+
+        store.set("/project", "1234", true);
+        assert_eq!(Some(true), store.decision("/project", "1234"));
+        // Changed files have to be trusted again.
+        assert_eq!(None, store.decision("/project", "5678"));
+
+        store.set("/project", "5678", false);
+        assert_eq!(Some(false), store.decision("/project", "5678"));
+        assert_eq!(1, store.projects.len());
+    }
+}
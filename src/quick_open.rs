@@ -0,0 +1,308 @@
+//! Quick Open dialog to find files of the current directory by fuzzy matching.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+
+use gdk;
+use glib;
+use gtk;
+use gtk::prelude::*;
+use pango;
+
+use neovim_lib::{Neovim, NeovimApi, NeovimApiAsync, Value};
+
+use file_browser::OpenMode;
+use file_list;
+use fuzzy;
+use nvim::ErrorReport;
+use shell;
+use ui::UiMutex;
+
+const MAX_RESULTS: usize = 200;
+/// Score bonus of open buffers and recent files over other project files.
+const BONUS_RECENT: i64 = 32;
+
+/// Listed buffers with a file name, as absolute paths.
+const BUFFERS_EXPR: &str = "map(filter(range(1, bufnr('$')), \
+                            'buflisted(v:val) && !empty(bufname(v:val)) \
+                            && empty(getbufvar(v:val, \"&buftype\"))'), \
+                            'fnamemodify(bufname(v:val), \":p\")')";
+
+/// Files to choose from, open buffers and recent files first.
+struct Candidates {
+    files: Vec<String>,
+    recent_count: usize,
+}
+
+impl Candidates {
+    /// Merges `recent` files and `project_files` of `cwd` without duplicates.
+    ///
+    /// Files below `cwd` are shown relative to it, others with the absolute path.
+    fn new(cwd: &Path, recent: &[String], project_files: Vec<String>) -> Candidates {
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+
+        for file in recent {
+            let file = Path::new(file)
+                .strip_prefix(cwd)
+                .ok()
+                .and_then(Path::to_str)
+                .unwrap_or(file);
+            if seen.insert(file.to_owned()) {
+                files.push(file.to_owned());
+            }
+        }
+
+        let recent_count = files.len();
+        for file in project_files {
+            if !seen.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        Candidates {
+            files,
+            recent_count,
+        }
+    }
+
+    /// Best `limit` matches of `pattern`, recent files first if the pattern is empty.
+    fn rank(&self, pattern: &str, limit: usize) -> Vec<(&str, fuzzy::Match)> {
+        let recent_count = self.recent_count;
+        let bonus = |idx| if idx < recent_count { BONUS_RECENT } else { 0 };
+        fuzzy::rank_by(pattern, &self.files, limit, |file| file.as_str(), bonus)
+            .into_iter()
+            .map(|(idx, m)| (self.files[idx].as_str(), m))
+            .collect()
+    }
+}
+
+/// Shows the Quick Open dialog for the current directory of nvim.
+pub fn show(shell_state_ref: &Arc<UiMutex<shell::State>>) {
+    let (window, cwd, recent) = {
+        let state = shell_state_ref.borrow();
+        let mut nvim = match state.nvim() {
+            Some(nvim) => nvim,
+            None => return,
+        };
+        let cwd = match get_cwd(&mut nvim) {
+            Some(cwd) => cwd,
+            None => return,
+        };
+        (state.window(), cwd, recent_files(&mut nvim))
+    };
+
+    let dlg = gtk::Dialog::new();
+    dlg.set_title("Quick Open");
+    dlg.set_transient_for(window.as_ref());
+    dlg.set_modal(true);
+    dlg.set_destroy_with_parent(true);
+    dlg.set_default_size(600, 400);
+
+    let search_entry = gtk::SearchEntry::new();
+    search_entry.set_placeholder_text("Loading files…");
+
+    let store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::String]);
+    let view = gtk::TreeView::new_with_model(&store);
+    view.set_headers_visible(false);
+    view.set_can_focus(false);
+
+    let renderer = gtk::CellRendererText::new();
+    renderer.set_property_ellipsize(pango::EllipsizeMode::Start);
+    let column = gtk::TreeViewColumn::new();
+    column.pack_start(&renderer, true);
+    column.add_attribute(&renderer, "markup", 0);
+    view.append_column(&column);
+
+    let scroll = gtk::ScrolledWindow::new(None, None);
+    scroll.set_vexpand(true);
+    scroll.add(&view);
+
+    let content = dlg.get_content_area();
+    content.set_border_width(6);
+    content.set_spacing(6);
+    content.pack_start(&search_entry, false, true, 0);
+    content.pack_start(&scroll, true, true, 0);
+
+    let candidates = Rc::new(RefCell::new(None));
+
+    search_entry.connect_search_changed(clone!(candidates, store, view => move |entry| {
+        update_results(&candidates, entry, &store, &view);
+    }));
+
+    search_entry.connect_key_press_event(
+        clone!(shell_state_ref, dlg, store, view => move |_, ev| {
+            let ctrl = ev.get_state().contains(gdk::ModifierType::CONTROL_MASK);
+            match ev.get_keyval() {
+                gdk::enums::key::Down => file_list::move_selection(&view, &store, 1),
+                gdk::enums::key::Up => file_list::move_selection(&view, &store, -1),
+                gdk::enums::key::v if ctrl => {
+                    open_selected(&shell_state_ref, &dlg, &view, &store, OpenMode::VSplit)
+                }
+                gdk::enums::key::t if ctrl => {
+                    open_selected(&shell_state_ref, &dlg, &view, &store, OpenMode::Tab)
+                }
+                _ => return Inhibit(false),
+            }
+            Inhibit(true)
+        }),
+    );
+
+    search_entry.connect_activate(clone!(shell_state_ref, dlg, store, view => move |_| {
+        open_selected(&shell_state_ref, &dlg, &view, &store, OpenMode::Edit);
+    }));
+
+    view.connect_row_activated(clone!(shell_state_ref, dlg, store => move |view, path, _| {
+        view.get_selection().select_path(path);
+        open_selected(&shell_state_ref, &dlg, view, &store, OpenMode::Edit);
+    }));
+
+    search_entry.connect_stop_search(clone!(dlg => move |_| dlg.destroy()));
+    dlg.connect_response(|dlg, _| dlg.destroy());
+
+    dlg.show_all();
+
+    load_candidates(cwd, recent, &candidates, &search_entry, &store, &view);
+}
+
+fn update_results(
+    candidates: &Rc<RefCell<Option<Candidates>>>,
+    entry: &gtk::SearchEntry,
+    store: &gtk::ListStore,
+    view: &gtk::TreeView,
+) {
+    store.clear();
+    let candidates = candidates.borrow();
+    let candidates = match *candidates {
+        Some(ref candidates) => candidates,
+        None => return,
+    };
+
+    let pattern = entry.get_text().unwrap_or_default();
+    for (file, m) in candidates.rank(&pattern, MAX_RESULTS) {
+        let markup = fuzzy::highlight_markup(file, &m.positions);
+        store.insert_with_values(None, &[0, 1], &[&markup, &file]);
+    }
+    file_list::move_selection(view, store, 0);
+}
+
+/// Reads the project files in background and shows them once loaded.
+fn load_candidates(
+    cwd: String,
+    mut recent: Vec<String>,
+    candidates: &Rc<RefCell<Option<Candidates>>>,
+    entry: &gtk::SearchEntry,
+    store: &gtk::ListStore,
+    view: &gtk::TreeView,
+) {
+    let widgets = UiMutex::new((candidates.clone(), entry.clone(), store.clone(), view.clone()));
+    thread::spawn(move || {
+        // v:oldfiles keeps deleted files
+        recent.retain(|file| Path::new(file).is_file());
+        let cwd = Path::new(&cwd);
+        let project_files = file_list::project_files(cwd, false);
+        let mut result = Some(Candidates::new(cwd, &recent, project_files));
+
+        glib::idle_add(move || {
+            let widgets = widgets.borrow();
+            let (ref candidates, ref entry, ref store, ref view) = *widgets;
+            *candidates.borrow_mut() = result.take();
+            entry.set_placeholder_text("Search files");
+            update_results(candidates, entry, store, view);
+            glib::Continue(false)
+        });
+    });
+}
+
+fn open_selected(
+    shell_state_ref: &Arc<UiMutex<shell::State>>,
+    dlg: &gtk::Dialog,
+    view: &gtk::TreeView,
+    store: &gtk::ListStore,
+    mode: OpenMode,
+) {
+    let file = view.get_selection()
+        .get_selected()
+        .map(|(_, iter)| iter)
+        .or_else(|| store.get_iter_first())
+        .and_then(|iter| store.get_value(&iter, 1).get::<String>());
+
+    if let Some(file) = file {
+        if let Some(mut nvim) = shell_state_ref.borrow().nvim() {
+            nvim.command_async(&mode.command(&file))
+                .cb(|r| r.report_err())
+                .call();
+        }
+        dlg.destroy();
+    }
+}
+
+fn get_cwd(nvim: &mut Neovim) -> Option<String> {
+    nvim.call_function("getcwd", vec![])
+        .ok_and_report()
+        .and_then(|cwd| cwd.as_str().map(str::to_owned))
+}
+
+/// Open buffers followed by `v:oldfiles`.
+fn recent_files(nvim: &mut Neovim) -> Vec<String> {
+    let buffers = nvim.eval(BUFFERS_EXPR).ok_and_report();
+    let oldfiles = nvim.get_vvar("oldfiles").ok_and_report();
+
+    buffers
+        .iter()
+        .chain(oldfiles.iter())
+        .filter_map(Value::as_array)
+        .flat_map(|files| files.iter().filter_map(Value::as_str))
+        .filter(|file| !file.starts_with("term:"))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(ranked: Vec<(&str, fuzzy::Match)>) -> Vec<&str> {
+        ranked.into_iter().map(|(file, _)| file).collect()
+    }
+
+    #[test]
+    fn test_candidates() {
+        let candidates = Candidates::new(
+            Path::new("/project"),
+            &[
+                "/project/src/main.rs".to_owned(),
+                "/other/notes.md".to_owned(),
+                "/project/src/main.rs".to_owned(),
+            ],
+            vec!["README.md".to_owned(), "src/main.rs".to_owned()],
+        );
+        assert_eq!(
+            vec!["src/main.rs", "/other/notes.md", "README.md"],
+            candidates.files
+        );
+        assert_eq!(2, candidates.recent_count);
+    }
+
+    #[test]
+    fn test_rank_prefers_recent() {
+        let candidates = Candidates::new(
+            Path::new("/project"),
+            &["/project/src/ui_model/mod.rs".to_owned()],
+            vec!["src/ui.rs".to_owned(), "src/ui_model/mod.rs".to_owned()],
+        );
+        assert_eq!(
+            vec!["src/ui_model/mod.rs", "src/ui.rs"],
+            files(candidates.rank("ui", 10))
+        );
+        assert_eq!(
+            vec!["src/ui_model/mod.rs", "src/ui.rs"],
+            files(candidates.rank("", 10))
+        );
+        assert_eq!(vec!["src/ui.rs"], files(candidates.rank("ui.rs", 1)));
+    }
+}
@@ -53,6 +53,29 @@ impl Context {
     }
 }
 
+/// Font description scaled by `zoom` steps of one point (or pixel for absolute sizes).
+/// Size never goes below one step.
+pub fn zoom_font_desc(font_desc: &pango::FontDescription, zoom: i32) -> pango::FontDescription {
+    let mut zoomed = font_desc.clone();
+
+    if zoom == 0 {
+        return zoomed;
+    }
+
+    let size = font_desc.get_size();
+    // size is not set in description, so start from default one
+    let size = if size > 0 { size } else { 12 * pango::SCALE };
+    let size = (size + zoom * pango::SCALE).max(pango::SCALE);
+
+    if font_desc.get_size_is_absolute() {
+        zoomed.set_absolute_size(size as f64);
+    } else {
+        zoomed.set_size(size);
+    }
+
+    zoomed
+}
+
 struct ContextState {
     pango_context: pango::Context,
     cell_metrics: CellMetrics,
@@ -119,3 +142,26 @@ impl CellMetrics {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_font_desc() {
+        let font_desc = pango::FontDescription::from_string("Monospace 12");
+
+        let zoomed = zoom_font_desc(&font_desc, 2);
+        assert_eq!(14 * pango::SCALE, zoomed.get_size());
+        assert_eq!("Monospace", zoomed.get_family().unwrap());
+
+        let zoomed = zoom_font_desc(&font_desc, -3);
+        assert_eq!(9 * pango::SCALE, zoomed.get_size());
+
+        let zoomed = zoom_font_desc(&font_desc, -20);
+        assert_eq!(pango::SCALE, zoomed.get_size());
+
+        let zoomed = zoom_font_desc(&font_desc, 0);
+        assert_eq!(12 * pango::SCALE, zoomed.get_size());
+    }
+}
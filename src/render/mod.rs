@@ -4,6 +4,7 @@ mod model_clip_iterator;
 
 pub use self::context::Context;
 pub use self::context::CellMetrics;
+pub use self::context::zoom_font_desc;
 use self::model_clip_iterator::{RowView, ModelClipIteratorFactory};
 
 use mode;
@@ -35,6 +36,7 @@ pub fn render(
     let cell_metrics = font_ctx.cell_metrics();
     let &CellMetrics { char_width, .. } = cell_metrics;
     let (cursor_row, cursor_col) = ui_model.get_cursor();
+    let cursor_moving = cursor.is_moving();
 
     for cell_view in ui_model.get_clip_iterator(ctx, cell_metrics) {
         let mut line_x = 0.0;
@@ -51,29 +53,49 @@ pub fn render(
 
         for (col, cell) in line.line.iter().enumerate() {
 
-            draw_cell(&cell_view, color_model, cell, col, line_x);
+            draw_cell(&cell_view, color_model.actual_cell_fg(cell), col, line_x);
 
             draw_underline(&cell_view, color_model, cell, line_x);
 
-            if row == cursor_row && col == cursor_col {
+            if row == cursor_row && col == cursor_col && !cursor_moving {
                 let double_width = line.line.get(col + 1).map_or(
                     false,
                     |c| c.attrs.double_width,
                 );
                 ctx.move_to(line_x, line_y);
-                cursor.draw(
+                let cursor_fg = cursor.draw(
                     ctx,
                     font_ctx,
                     mode,
                     line_y,
                     double_width,
+                    color_model,
                     color_model.actual_cell_bg(cell),
                 );
+
+                // text under cursor in cursor highlight color
+                if let Some(ref cursor_fg) = cursor_fg {
+                    let cells = if double_width { 2.0 } else { 1.0 };
+                    ctx.save();
+                    ctx.rectangle(
+                        line_x,
+                        line_y,
+                        char_width * cells,
+                        cell_metrics.line_height,
+                    );
+                    ctx.clip();
+                    draw_cell(&cell_view, cursor_fg, col, line_x);
+                    ctx.restore();
+                }
             }
 
             line_x += char_width;
         }
     }
+
+    if cursor_moving {
+        cursor.draw_movement(ctx, font_ctx, mode, color_model);
+    }
 }
 
 fn draw_underline(
@@ -162,8 +184,7 @@ fn draw_cell_bg(
 
 fn draw_cell(
     cell_view: &RowView,
-    color_model: &color::ColorModel,
-    cell: &ui_model::Cell,
+    fg: &color::Color,
     col: usize,
     line_x: f64,
 ) {
@@ -181,8 +202,6 @@ fn draw_cell(
 
     if let Some(item) = line.item_line[col].as_ref() {
         if let Some(ref glyphs) = item.glyphs {
-            let fg = color_model.actual_cell_fg(cell);
-
             ctx.move_to(line_x, line_y + ascent);
             ctx.set_source_rgb(fg.0, fg.1, fg.2);
 
@@ -6,13 +6,15 @@ use std::thread;
 use std::time::Duration;
 
 use cairo;
-use pango::{FontDescription, LayoutExt};
+use pango::{self, FontDescription, LayoutExt};
 use gdk;
-use gdk::{EventButton, EventMotion, EventScroll, EventType, ModifierType};
+use gdk::{EventButton, EventMotion, EventScroll, ModifierType};
 use gdk_sys;
 use glib;
+use glib::StaticType;
 use gtk;
 use gtk::prelude::*;
+use gio::prelude::*;
 use pangocairo;
 
 use neovim_lib::{Neovim, NeovimApi, NeovimApiAsync, Value};
@@ -25,11 +27,13 @@ use color::{Color, ColorModel, COLOR_BLACK, COLOR_RED, COLOR_WHITE};
 use nvim::{self, CompleteItem, ErrorReport, GuiApi, NeovimClient, NeovimClientAsync, NeovimRef,
            RedrawEvents, RepaintMode};
 use input;
-use input::keyval_to_input_string;
-use cursor::Cursor;
+use gesture;
+use keymap::{KeyBinding, Keymap};
+use cursor::{Animation, Blink, Cursor};
 use ui::UiMutex;
 use popup_menu::PopupMenu;
-use tabline::Tabline;
+use project_config;
+use tabline::{Tabline, TablineMode};
 use error;
 use mode;
 use render;
@@ -39,6 +43,11 @@ use subscriptions::{SubscriptionHandle, Subscriptions};
 const DEFAULT_FONT_NAME: &str = "DejaVu Sans Mono 12";
 pub const MINIMUM_SUPPORTED_NVIM_VERSION: &str = "0.2.2";
 
+/// Target info of files dropped from other applications.
+const DROP_OTHER_APP: u32 = 0;
+/// Target info of files dropped from the file browser.
+const DROP_SAME_APP: u32 = 1;
+
 macro_rules! idle_cb_call {
     ($state:ident.$cb:ident($( $x:expr ),*)) => (
             glib::idle_add(move || {
@@ -58,9 +67,14 @@ pub struct State {
     mouse_enabled: bool,
     nvim: Rc<NeovimClient>,
     pub font_ctx: render::Context,
+    font_desc: FontDescription,
+    font_zoom: i32,
     cursor: Option<Cursor>,
+    /// cursor position at the end of last redraw, used for movement animation
+    cursor_pos: (usize, usize),
     popup_menu: RefCell<PopupMenu>,
     settings: Rc<RefCell<Settings>>,
+    keymap: Rc<Keymap>,
 
     resize_request: (i64, i64),
     resize_timer: Rc<Cell<Option<glib::SourceId>>>,
@@ -74,6 +88,7 @@ pub struct State {
     pub drawing_area: gtk::DrawingArea,
     tabs: Tabline,
     im_context: gtk::IMMulticontext,
+    preedit: RefCell<Option<Preedit>>,
     error_area: error::ErrorArea,
 
     options: ShellOptions,
@@ -87,8 +102,10 @@ pub struct State {
 impl State {
     pub fn new(settings: Rc<RefCell<Settings>>, options: ShellOptions) -> State {
         let drawing_area = gtk::DrawingArea::new();
-        let popup_menu = RefCell::new(PopupMenu::new(&drawing_area));
-        let font_ctx = render::Context::new(FontDescription::from_string(DEFAULT_FONT_NAME));
+        let keymap = Rc::new(Keymap::load());
+        let popup_menu = RefCell::new(PopupMenu::new(&drawing_area, keymap.clone()));
+        let font_desc = FontDescription::from_string(DEFAULT_FONT_NAME);
+        let font_ctx = render::Context::new(font_desc.clone());
 
         State {
             model: UiModel::empty(),
@@ -97,9 +114,13 @@ impl State {
             cur_attrs: None,
             mouse_enabled: true,
             font_ctx,
+            font_desc,
+            font_zoom: 0,
             cursor: None,
+            cursor_pos: (0, 0),
             popup_menu,
             settings,
+            keymap,
 
             resize_request: (-1, -1),
             resize_timer: Rc::new(Cell::new(None)),
@@ -114,6 +135,7 @@ impl State {
             drawing_area,
             tabs: Tabline::new(),
             im_context: gtk::IMMulticontext::new(),
+            preedit: RefCell::new(None),
             error_area: error::ErrorArea::new(),
 
             options,
@@ -176,13 +198,67 @@ impl State {
         self.font_ctx.font_description()
     }
 
+    /// Set window font, current zoom is applied on top of it
     pub fn set_font_desc(&mut self, desc: &str) {
-        self.font_ctx.update(FontDescription::from_string(desc));
+        self.font_desc = FontDescription::from_string(desc);
+        self.update_font();
+    }
+
+    /// Increase (or decrease for negative values) font size by given step in points
+    pub fn set_font_zoom(&mut self, step: i32) {
+        self.font_zoom += step;
+        self.update_font();
+    }
+
+    /// Set font size in points, applied as zoom on top of the window font
+    pub fn set_font_size(&mut self, size: i32) {
+        let base_size = self.font_desc.get_size();
+        // size is not set in description, so start from default one
+        let base_size = if base_size > 0 { base_size } else { 12 * pango::SCALE };
+        self.font_zoom = size - base_size / pango::SCALE;
+        self.update_font();
+    }
+
+    pub fn reset_font_zoom(&mut self) {
+        self.font_zoom = 0;
+        self.update_font();
+    }
+
+    fn update_font(&mut self) {
+        self.font_ctx
+            .update(render::zoom_font_desc(&self.font_desc, self.font_zoom));
         self.model.clear_glyphs();
         self.try_nvim_resize();
         self.on_redraw(&RepaintMode::All);
     }
 
+    pub fn set_cursor_animation(&mut self, animation: Option<Animation>) {
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.set_animation(animation);
+        }
+    }
+
+    /// Animate cursor movement if cursor position changed since last call
+    pub fn animate_cursor_move(&mut self) {
+        let cursor_pos = self.model.get_cursor();
+        if cursor_pos == self.cursor_pos {
+            return;
+        }
+        let prev_pos = self.cursor_pos;
+        self.cursor_pos = cursor_pos;
+
+        let &CellMetrics {
+            line_height,
+            char_width,
+            ..
+        } = self.font_ctx.cell_metrics();
+        let point = |(row, col): (usize, usize)| (col as f64 * char_width, row as f64 * line_height);
+
+        if let Some(cursor) = self.cursor.as_mut() {
+            cursor.move_to(point(prev_pos), point(cursor_pos), &self.drawing_area);
+        }
+    }
+
     pub fn open_file(&self, path: &str) {
         if let Some(mut nvim) = self.nvim() {
             nvim.command_async(&format!("e {}", path))
@@ -199,6 +275,13 @@ impl State {
         }
     }
 
+    /// Window that contains the shell
+    pub fn window(&self) -> Option<gtk::Window> {
+        self.drawing_area
+            .get_toplevel()
+            .and_then(|window| window.downcast::<gtk::Window>().ok())
+    }
+
     pub fn clipboard_clipboard_set(&self, text: &str) {
         self.clipboard_clipboard.set_text(text);
     }
@@ -243,12 +326,63 @@ impl State {
         render::shape_dirty(&self.font_ctx, &mut self.model, &self.color_model);
     }
 
+    /// GTK action bound to the key in keymap
+    fn key_action(&self, ev: &gdk::EventKey) -> Option<String> {
+        match self.keymap.lookup(ev) {
+            Some(&KeyBinding::Action(ref action)) => Some(action.clone()),
+            _ => None,
+        }
+    }
+
+    /// Activate GTK action, state must not be borrowed as actions like zoom change it
+    fn activate_action(drawing_area: &gtk::DrawingArea, action: &str) {
+        let window = drawing_area
+            .get_toplevel()
+            .and_then(|w| w.downcast::<gtk::ApplicationWindow>().ok());
+
+        if let Some(window) = window {
+            if action.starts_with("app.") {
+                if let Some(app) = window.get_application() {
+                    app.activate_action(&action[4..], None);
+                }
+            } else if action.starts_with("win.") {
+                window.activate_action(&action[4..], None);
+            } else {
+                error!("Unknown action group in key binding {}", action);
+            }
+        }
+    }
+
     fn im_commit(&self, ch: &str) {
         if let Some(mut nvim) = self.nvim() {
             input::im_input(&mut nvim, ch);
         }
     }
 
+    fn im_preedit_changed(&mut self) {
+        let (text, attrs, cursor_pos) = self.im_context.get_preedit_string();
+
+        self.mode.set_im_active(!text.is_empty());
+
+        *self.preedit.borrow_mut() = if text.is_empty() {
+            None
+        } else {
+            Some(Preedit {
+                text,
+                attrs,
+                cursor_pos,
+            })
+        };
+
+        // preedit is drawn over the cursor line, starting at cursor
+        let (row, col) = self.model.get_cursor();
+        if row < self.model.rows && col < self.model.columns {
+            let (x, y, width, height) = ModelRect::new(row, row, col, self.model.columns - 1)
+                .to_area(self.font_ctx.cell_metrics());
+            self.drawing_area.queue_draw_area(x, y, width, height);
+        }
+    }
+
     fn calc_nvim_size(&self) -> (usize, usize) {
         let &CellMetrics {
             line_height,
@@ -271,10 +405,9 @@ impl State {
     }
 
     fn set_im_location(&self) {
-        let (row, col) = self.model.get_cursor();
-
-        let (x, y, width, height) =
-            ModelRect::point(col, row).to_area(self.font_ctx.cell_metrics());
+        let (x, y, width, height) = self.model
+            .cur_cell_rect()
+            .to_area(self.font_ctx.cell_metrics());
 
         self.im_context.set_cursor_location(&gdk::Rectangle {
             x,
@@ -283,7 +416,10 @@ impl State {
             height,
         });
 
-        self.im_context.reset();
+        // don't drop text that is composed right now
+        if self.preedit.borrow().is_none() {
+            self.im_context.reset();
+        }
     }
 
     fn try_nvim_resize(&mut self) {
@@ -348,6 +484,34 @@ impl State {
             .subscribe(event_name, args, cb)
     }
 
+    pub fn set_tabline_mode(&self, mode: TablineMode) {
+        self.tabs.set_mode(mode);
+    }
+
+    /// Changes the tabline mode through the "Buffer Tabline" action, so its menu check and
+    /// the saved window state follow `GuiTabline`.
+    ///
+    /// The action is activated later, as its handler borrows the state.
+    pub fn change_tabline_mode(&self, mode: TablineMode) {
+        let action = self.window()
+            .and_then(|window| window.get_application())
+            .and_then(|app| app.lookup_action("buffer-tabline"));
+        match action {
+            Some(action) => {
+                let buffers = mode == TablineMode::Buffers;
+                gtk::idle_add(move || {
+                    action.change_state(&buffers.to_variant());
+                    Continue(false)
+                });
+            }
+            None => self.set_tabline_mode(mode),
+        }
+    }
+
+    pub fn init_tabline(&self) {
+        self.tabs.init_subscriptions(self);
+    }
+
     pub fn set_autocmds(&self) {
         self.subscriptions
             .borrow()
@@ -365,16 +529,39 @@ impl State {
     }
 }
 
+/// Text that is composed by input method, but not commited yet
+struct Preedit {
+    text: String,
+    attrs: pango::AttrList,
+    /// cursor position in chars
+    cursor_pos: i32,
+}
+
+impl Preedit {
+    fn cursor_index(&self) -> i32 {
+        self.text
+            .char_indices()
+            .nth(self.cursor_pos as usize)
+            .map_or(self.text.len(), |(idx, _)| idx) as i32
+    }
+}
+
 pub struct UiState {
-    mouse_pressed: bool,
+    /// number of the pressed mouse button
+    mouse_pressed: Option<u32>,
+    click_counter: input::ClickCounter,
     scroll_delta: (f64, f64),
+    /// font zoom and font size in points on pinch begin
+    pinch_start: Option<(i32, f64)>,
 }
 
 impl UiState {
     pub fn new() -> UiState {
         UiState {
-            mouse_pressed: false,
+            mouse_pressed: None,
+            click_counter: input::ClickCounter::default(),
             scroll_delta: (0.0, 0.0),
+            pinch_start: None,
         }
     }
 }
@@ -405,6 +592,7 @@ pub struct Shell {
     ui_state: Rc<RefCell<UiState>>,
 
     widget: gtk::Box,
+    gestures: Vec<gtk::Gesture>,
 }
 
 impl Shell {
@@ -414,6 +602,7 @@ impl Shell {
             ui_state: Rc::new(RefCell::new(UiState::new())),
 
             widget: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            gestures: Vec::new(),
         };
 
         let shell_ref = Arc::downgrade(&shell.state);
@@ -433,7 +622,7 @@ impl Shell {
         state.drawing_area.set_vexpand(true);
         state.drawing_area.set_can_focus(true);
 
-        state.im_context.set_use_preedit(false);
+        state.im_context.set_use_preedit(true);
 
         let nvim_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
@@ -450,7 +639,7 @@ impl Shell {
             .set_events(
                 (gdk_sys::GDK_BUTTON_RELEASE_MASK | gdk_sys::GDK_BUTTON_PRESS_MASK
                     | gdk_sys::GDK_BUTTON_MOTION_MASK | gdk_sys::GDK_SCROLL_MASK
-                    | gdk_sys::GDK_SMOOTH_SCROLL_MASK)
+                    | gdk_sys::GDK_SMOOTH_SCROLL_MASK | gdk_sys::GDK_TOUCH_MASK)
                     .bits() as i32,
             );
 
@@ -502,13 +691,18 @@ impl Shell {
                 .unwrap()
                 .reset_state();
 
-            if ref_state.borrow().im_context.filter_keypress(ev) {
+            let action = ref_state.borrow().key_action(ev);
+            if let Some(action) = action {
+                let drawing_area = ref_state.borrow().drawing_area.clone();
+                State::activate_action(&drawing_area, &action);
+                Inhibit(true)
+            } else if ref_state.borrow().im_context.filter_keypress(ev) {
                 Inhibit(true)
             } else {
                 let state = ref_state.borrow();
                 let nvim = state.nvim();
                 if let Some(mut nvim) = nvim {
-                    input::gtk_key_press(&mut nvim, ev)
+                    input::gtk_key_press(&mut nvim, ev, &state.keymap)
                 } else {
                     Inhibit(false)
                 }
@@ -530,6 +724,8 @@ impl Shell {
             )
         });
 
+        self.gestures = init_gestures(&self.state, &self.ui_state, &state.drawing_area);
+
         let ref_state = self.state.clone();
         state
             .drawing_area
@@ -557,6 +753,26 @@ impl Shell {
             .im_context
             .connect_commit(move |_, ch| ref_state.borrow().im_commit(ch));
 
+        // preedit signals can be emitted while state is borrowed,
+        // for example on focus change, so handle them later
+        let ref_state = self.state.clone();
+        state.im_context.connect_preedit_changed(move |_| {
+            let ref_state = ref_state.clone();
+            glib::idle_add(move || {
+                ref_state.borrow_mut().im_preedit_changed();
+                Continue(false)
+            });
+        });
+
+        let ref_state = self.state.clone();
+        state.im_context.connect_preedit_end(move |_| {
+            let ref_state = ref_state.clone();
+            glib::idle_add(move || {
+                ref_state.borrow_mut().im_preedit_changed();
+                Continue(false)
+            });
+        });
+
         let ref_state = self.state.clone();
         state.drawing_area.connect_configure_event(move |_, ev| {
             debug!("configure_event {:?}", ev.get_size());
@@ -571,23 +787,32 @@ impl Shell {
 
         let ref_state = self.state.clone();
         let targets = vec![
-            gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::OTHER_APP, 0),
+            gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::OTHER_APP, DROP_OTHER_APP),
+            gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::SAME_APP, DROP_SAME_APP),
         ];
         state
             .drawing_area
             .drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::COPY);
         state
             .drawing_area
-            .connect_drag_data_received(move |_, _, _, _, s, _, _| {
+            .connect_drag_data_received(move |_, _, x, y, s, info, _| {
                 let uris = s.get_uris();
-                let command = uris.iter().filter_map(|uri| decode_uri(uri)).fold(
-                    ":ar".to_owned(),
-                    |command, filename| {
+                let filenames = uris.iter().filter_map(|uri| decode_uri(uri));
+                let state = ref_state.borrow_mut();
+                let command = if info == DROP_SAME_APP {
+                    // Files dragged from the file browser are opened in the window below the
+                    // pointer.
+                    let (x, y) = (x as f64, y as f64);
+                    let pos = ModelRect::from_area(state.font_ctx.cell_metrics(), x, y, x, y);
+                    filenames.fold(goto_window_command(pos.top, pos.left), |command, filename| {
+                        command + " | e " + &escape_filename(&filename)
+                    })
+                } else {
+                    filenames.fold(":ar".to_owned(), |command, filename| {
                         let filename = escape_filename(&filename);
                         command + " " + &filename
-                    },
-                );
-                let state = ref_state.borrow_mut();
+                    })
+                };
                 let mut nvim = state.nvim().unwrap();
                 nvim.command_async(&command).cb(|r| r.report_err()).call()
             });
@@ -598,6 +823,14 @@ impl Shell {
         self.state.borrow_mut().set_font_desc(font_name);
     }
 
+    pub fn font_zoom(&self, step: i32) {
+        self.state.borrow_mut().set_font_zoom(step);
+    }
+
+    pub fn font_zoom_reset(&self) {
+        self.state.borrow_mut().reset_font_zoom();
+    }
+
     pub fn grab_focus(&self) {
         self.state.borrow().drawing_area.grab_focus();
     }
@@ -606,10 +839,19 @@ impl Shell {
         self.state.borrow().open_file(path);
     }
 
+    /// Change directory of nvim.
+    ///
+    /// The project configuration isn't applied, projects opened from the Open popover
+    /// call `apply_project_config` after their session is restored.
     pub fn cd(&self, path: &str) {
         self.state.borrow().cd(path);
     }
 
+    /// Applies the project-local configuration of `path`, once it is trusted.
+    pub fn apply_project_config(&self, path: &str) {
+        project_config::apply(&self.state, path);
+    }
+
     pub fn detach_ui(&mut self) {
         let state = self.state.borrow();
 
@@ -666,6 +908,63 @@ impl Deref for Shell {
     }
 }
 
+fn init_gestures(
+    state: &Arc<UiMutex<State>>,
+    ui_state: &Rc<RefCell<UiState>>,
+    drawing_area: &gtk::DrawingArea,
+) -> Vec<gtk::Gesture> {
+    let zoom = gtk::GestureZoom::new(drawing_area);
+    let ref_state = state.clone();
+    let ref_ui_state = ui_state.clone();
+    zoom.connect_begin(move |_, _| {
+        let state = ref_state.borrow();
+        let font_size = state.font_ctx.font_description().get_size() as f64 / pango::SCALE as f64;
+        ref_ui_state.borrow_mut().pinch_start = Some((state.font_zoom, font_size));
+    });
+    let ref_state = state.clone();
+    let ref_ui_state = ui_state.clone();
+    zoom.connect_scale_changed(move |_, scale| {
+        if let Some((start_zoom, font_size)) = ref_ui_state.borrow().pinch_start {
+            let mut state = ref_state.borrow_mut();
+            let zoom = start_zoom + gesture::pinch_zoom_steps(font_size, scale);
+            if zoom != state.font_zoom {
+                let step = zoom - state.font_zoom;
+                state.set_font_zoom(step);
+            }
+        }
+    });
+    let ref_ui_state = ui_state.clone();
+    zoom.connect_end(move |_, _| ref_ui_state.borrow_mut().pinch_start = None);
+
+    // two finger swipe, n-points can be set on construction only
+    let swipe = glib::Object::new(
+        gtk::GestureSwipe::static_type(),
+        &[("widget", drawing_area), ("n-points", &2u32)],
+    ).expect("Can't create swipe gesture")
+        .downcast::<gtk::GestureSwipe>()
+        .unwrap();
+    let ref_state = state.clone();
+    swipe.connect_swipe(move |_, velocity_x, velocity_y| {
+        if let Some(offset) = gesture::swipe_tab_offset(velocity_x, velocity_y) {
+            ref_state.borrow().tabs.switch_page_by(offset);
+        }
+    });
+
+    let long_press = gtk::GestureLongPress::new(drawing_area);
+    long_press.set_touch_only(true);
+    let ref_state = state.clone();
+    long_press.connect_pressed(move |_, x, y| {
+        let mut state = ref_state.borrow_mut();
+        if state.mouse_enabled {
+            let modifiers = ModifierType::empty();
+            mouse_input(&mut *state, "RightMouse", 1, modifiers, (x, y));
+            mouse_input(&mut *state, "RightRelease", 1, modifiers, (x, y));
+        }
+    });
+
+    vec![zoom.upcast(), swipe.upcast(), long_press.upcast()]
+}
+
 fn gtk_focus_in(state: &mut State) -> Inhibit {
     if let Some(mut nvim) = state.nvim() {
         nvim.command_async("if exists('#FocusGained') | doautocmd FocusGained | endif")
@@ -696,6 +995,10 @@ fn gtk_focus_out(state: &mut State) -> Inhibit {
 }
 
 fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll) -> Inhibit {
+    if state.keymap.ctrl_scroll_zoom() && ev.get_state().contains(ModifierType::CONTROL_MASK) {
+        return gtk_zoom_scroll_event(state, ui_state, ev);
+    }
+
     if !state.mouse_enabled {
         return Inhibit(false);
     }
@@ -704,16 +1007,16 @@ fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll)
 
     match ev.get_direction() {
         gdk::ScrollDirection::Right => {
-            mouse_input(state, "ScrollWheelRight", ev.get_state(), ev.get_position())
+            mouse_input(state, "ScrollWheelRight", 1, ev.get_state(), ev.get_position())
         }
         gdk::ScrollDirection::Left => {
-            mouse_input(state, "ScrollWheelLeft", ev.get_state(), ev.get_position())
+            mouse_input(state, "ScrollWheelLeft", 1, ev.get_state(), ev.get_position())
         }
         gdk::ScrollDirection::Up => {
-            mouse_input(state, "ScrollWheelUp", ev.get_state(), ev.get_position())
+            mouse_input(state, "ScrollWheelUp", 1, ev.get_state(), ev.get_position())
         }
         gdk::ScrollDirection::Down => {
-            mouse_input(state, "ScrollWheelDown", ev.get_state(), ev.get_position())
+            mouse_input(state, "ScrollWheelDown", 1, ev.get_state(), ev.get_position())
         }
         gdk::ScrollDirection::Smooth => {
             // Remember and accumulate scroll deltas, so slow scrolling still
@@ -724,16 +1027,16 @@ fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll)
             let x = ui_state.scroll_delta.0 as isize;
             let y = ui_state.scroll_delta.1 as isize;
             for _ in 0..x {
-                mouse_input(state, "ScrollWheelRight", ev.get_state(), ev.get_position())
+                mouse_input(state, "ScrollWheelRight", 1, ev.get_state(), ev.get_position())
             }
             for _ in 0..-x {
-                mouse_input(state, "ScrollWheelLeft", ev.get_state(), ev.get_position())
+                mouse_input(state, "ScrollWheelLeft", 1, ev.get_state(), ev.get_position())
             }
             for _ in 0..y {
-                mouse_input(state, "ScrollWheelDown", ev.get_state(), ev.get_position())
+                mouse_input(state, "ScrollWheelDown", 1, ev.get_state(), ev.get_position())
             }
             for _ in 0..-y {
-                mouse_input(state, "ScrollWheelUp", ev.get_state(), ev.get_position())
+                mouse_input(state, "ScrollWheelUp", 1, ev.get_state(), ev.get_position())
             }
             // Subtract performed scroll deltas.
             ui_state.scroll_delta.0 -= x as f64;
@@ -744,34 +1047,89 @@ fn gtk_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll)
     Inhibit(false)
 }
 
+fn gtk_zoom_scroll_event(state: &mut State, ui_state: &mut UiState, ev: &EventScroll) -> Inhibit {
+    match ev.get_direction() {
+        gdk::ScrollDirection::Up => state.set_font_zoom(1),
+        gdk::ScrollDirection::Down => state.set_font_zoom(-1),
+        gdk::ScrollDirection::Smooth => {
+            ui_state.scroll_delta.1 += ev.as_ref().delta_y;
+            let y = ui_state.scroll_delta.1 as i32;
+            if y != 0 {
+                state.set_font_zoom(-y);
+                ui_state.scroll_delta.1 -= y as f64;
+            }
+        }
+        _ => (),
+    }
+    Inhibit(true)
+}
+
+/// Double click time from GTK settings in milliseconds
+fn double_click_time() -> u32 {
+    gtk::Settings::get_default()
+        .and_then(|settings| settings.get_property("gtk-double-click-time").ok())
+        .and_then(|value| value.get::<i32>())
+        .map_or(400, |time| time.max(0) as u32)
+}
+
 fn gtk_button_press(shell: &mut State, ui_state: &mut UiState, ev: &EventButton) -> Inhibit {
-    if ev.get_event_type() != EventType::ButtonPress {
+    // synthetic 2BUTTON_PRESS and 3BUTTON_PRESS events follow the plain presses,
+    // clicks are counted from the plain presses instead
+    if ev.get_event_type() != gdk::EventType::ButtonPress {
         return Inhibit(false);
     }
 
     if shell.mouse_enabled {
-        ui_state.mouse_pressed = true;
+        if let Some(button) = input::mouse_button_name(ev.get_button()) {
+            ui_state.mouse_pressed = Some(ev.get_button());
+
+            let click_count = ui_state.click_counter.press(
+                ev.get_button(),
+                ev.get_time(),
+                mouse_cell(shell, ev.get_position()),
+                double_click_time(),
+            );
 
-        match ev.get_button() {
-            1 => mouse_input(shell, "LeftMouse", ev.get_state(), ev.get_position()),
-            2 => mouse_input(shell, "MiddleMouse", ev.get_state(), ev.get_position()),
-            3 => mouse_input(shell, "RightMouse", ev.get_state(), ev.get_position()),
-            _ => (),
+            mouse_input(
+                shell,
+                &format!("{}Mouse", button),
+                click_count,
+                ev.get_state(),
+                ev.get_position(),
+            );
         }
     }
     Inhibit(false)
 }
 
-fn mouse_input(shell: &mut State, input: &str, state: ModifierType, position: (f64, f64)) {
+/// Column and row of the cell at the position
+fn mouse_cell(shell: &State, position: (f64, f64)) -> (u64, u64) {
     let &CellMetrics {
         line_height,
         char_width,
         ..
     } = shell.font_ctx.cell_metrics();
     let (x, y) = position;
-    let col = (x / char_width).trunc() as u64;
-    let row = (y / line_height).trunc() as u64;
-    let input_str = format!("{}<{},{}>", keyval_to_input_string(input, state), col, row);
+    (
+        (x / char_width).trunc() as u64,
+        (y / line_height).trunc() as u64,
+    )
+}
+
+fn mouse_input(
+    shell: &mut State,
+    input: &str,
+    click_count: u32,
+    state: ModifierType,
+    position: (f64, f64),
+) {
+    let (col, row) = mouse_cell(shell, position);
+    let input_str = format!(
+        "{}<{},{}>",
+        input::mouse_input_string(input, click_count, state),
+        col,
+        row
+    );
 
     let nvim = shell.nvim();
     if let Some(mut nvim) = nvim {
@@ -781,14 +1139,19 @@ fn mouse_input(shell: &mut State, input: &str, state: ModifierType, position: (f
 }
 
 fn gtk_button_release(shell: &mut State, ui_state: &mut UiState, ev: &EventButton) -> Inhibit {
-    ui_state.mouse_pressed = false;
+    if ui_state.mouse_pressed == Some(ev.get_button()) {
+        ui_state.mouse_pressed = None;
+    }
 
     if shell.mouse_enabled {
-        match ev.get_button() {
-            1 => mouse_input(shell, "LeftRelease", ev.get_state(), ev.get_position()),
-            2 => mouse_input(shell, "MiddleRelease", ev.get_state(), ev.get_position()),
-            3 => mouse_input(shell, "RightRelease", ev.get_state(), ev.get_position()),
-            _ => (),
+        if let Some(button) = input::mouse_button_name(ev.get_button()) {
+            mouse_input(
+                shell,
+                &format!("{}Release", button),
+                1,
+                ev.get_state(),
+                ev.get_position(),
+            );
         }
     }
 
@@ -796,12 +1159,36 @@ fn gtk_button_release(shell: &mut State, ui_state: &mut UiState, ev: &EventButto
 }
 
 fn gtk_motion_notify(shell: &mut State, ui_state: &mut UiState, ev: &EventMotion) -> Inhibit {
-    if shell.mouse_enabled && ui_state.mouse_pressed {
-        mouse_input(shell, "LeftDrag", ev.get_state(), ev.get_position());
+    if shell.mouse_enabled {
+        if let Some(button) = ui_state.mouse_pressed.and_then(input::mouse_button_name) {
+            mouse_input(
+                shell,
+                &format!("{}Drag", button),
+                1,
+                ev.get_state(),
+                ev.get_position(),
+            );
+        }
     }
     Inhibit(false)
 }
 
+/// Command that moves to the window of the current tab page at the given grid position.
+///
+/// The current window stays active if the position is outside of all windows.
+fn goto_window_command(row: usize, col: usize) -> String {
+    let (row, col) = (row + 1, col + 1);
+    format!(
+        "call win_gotoid(get(map(filter(getwininfo(), '\
+         v:val.tabnr == tabpagenr() && \
+         v:val.winrow <= {row} && {row} <= v:val.winrow + v:val.height && \
+         v:val.wincol <= {col} && {col} <= v:val.wincol + v:val.width'), \
+         'v:val.winid'), 0, win_getid()))",
+        row = row,
+        col = col,
+    )
+}
+
 fn gtk_draw(state_arc: &Arc<UiMutex<State>>, ctx: &cairo::Context) -> Inhibit {
     let state = state_arc.borrow();
     if state.nvim.is_initialized() {
@@ -813,6 +1200,10 @@ fn gtk_draw(state_arc: &Arc<UiMutex<State>>, ctx: &cairo::Context) -> Inhibit {
             &state.color_model,
             &state.mode,
         );
+
+        if let Some(ref preedit) = *state.preedit.borrow() {
+            draw_preedit(&*state, preedit, ctx);
+        }
     } else if state.nvim.is_initializing() {
         draw_initializing(&*state, ctx);
     }
@@ -967,10 +1358,44 @@ fn draw_initializing(state: &State, ctx: &cairo::Context) {
         &state.mode,
         y,
         false,
+        &state.color_model,
         &state.color_model.bg_color,
     );
 }
 
+fn draw_preedit(state: &State, preedit: &Preedit, ctx: &cairo::Context) {
+    let cell_metrics = state.font_ctx.cell_metrics();
+    let (row, col) = state.model.get_cursor();
+    let (x, y, _, height) = ModelRect::point(col, row).to_area(cell_metrics);
+    let (x, y) = (x as f64, y as f64);
+
+    let layout = state.font_ctx.create_layout();
+    layout.set_text(&preedit.text);
+    layout.set_attributes(Some(&preedit.attrs));
+    let (width, _) = layout.get_pixel_size();
+
+    let bg = &state.color_model.bg_color;
+    let fg = &state.color_model.fg_color;
+
+    ctx.set_source_rgb(bg.0, bg.1, bg.2);
+    ctx.rectangle(x, y, width as f64, height as f64);
+    ctx.fill();
+
+    ctx.move_to(x, y);
+    ctx.set_source_rgb(fg.0, fg.1, fg.2);
+    pangocairo::functions::update_layout(ctx, &layout);
+    pangocairo::functions::show_layout(ctx, &layout);
+
+    let (cursor_rect, _) = layout.get_cursor_pos(preedit.cursor_index());
+    ctx.rectangle(
+        x + cursor_rect.x as f64 / pango::SCALE as f64,
+        y,
+        cell_metrics.char_width / 5.0,
+        height as f64,
+    );
+    ctx.fill();
+}
+
 fn init_nvim(state_ref: &Arc<UiMutex<State>>) {
     let mut state = state_ref.borrow_mut();
     if state.start_nvim_initialization() {
@@ -1106,6 +1531,10 @@ impl RedrawEvents for State {
 
     fn on_mode_change(&mut self, mode: &str, idx: u64) -> RepaintMode {
         self.mode.update(mode, idx as usize);
+        self.cursor
+            .as_mut()
+            .unwrap()
+            .set_blink(Blink::from_mode(&self.mode));
         RepaintMode::Area(self.model.cur_point())
     }
 
@@ -1165,7 +1594,16 @@ impl RedrawEvents for State {
         cursor_style_enabled: bool,
         mode_info: Vec<nvim::ModeInfo>,
     ) -> RepaintMode {
+        if let Some(mut nvim) = self.nvim.nvim() {
+            self.color_model
+                .theme
+                .update_mode_cursor(&mut *nvim, &mode_info);
+        }
         self.mode.set_info(cursor_style_enabled, mode_info);
+        self.cursor
+            .as_mut()
+            .unwrap()
+            .set_blink(Blink::from_mode(&self.mode));
         RepaintMode::Nothing
     }
 }
@@ -1179,4 +1617,12 @@ impl GuiApi for State {
 
         self.set_font_desc(font_desc);
     }
+
+    fn font_zoom(&mut self, step: i64) {
+        self.set_font_zoom(step as i32);
+    }
+
+    fn font_zoom_reset(&mut self) {
+        self.reset_font_zoom();
+    }
 }
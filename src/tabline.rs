@@ -1,58 +1,337 @@
 use std::ops::Deref;
+use std::path::Path;
 use std::rc::Rc;
 use std::cell::RefCell;
 
+use gdk;
 use gtk;
 use gtk::prelude::*;
 
+use gio;
 use glib;
 use glib::signal;
 
 use pango;
 
+use serde_json;
+
 use neovim_lib::{NeovimApi, NeovimApiAsync};
 use neovim_lib::neovim_api::Tabpage;
 
 use nvim;
 use nvim::ErrorReport;
+use shell;
+
+/// Vim expression that returns windows of every tab page as json
+const TAB_INFO_EXPR: &str = "json_encode(map(range(1, tabpagenr('$')), \
+     {_, t -> {'current': tabpagewinnr(t) - 1, 'windows': map(tabpagebuflist(t), \
+     {_, b -> {'name': bufname(b), 'modified': getbufvar(b, '&modified'), \
+     'filetype': getbufvar(b, '&filetype')}})}}))";
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct WinInfo {
+    name: String,
+    modified: u8,
+    filetype: String,
+}
+
+impl WinInfo {
+    fn display_name(&self) -> &str {
+        if self.name.is_empty() {
+            "[No Name]"
+        } else {
+            &self.name
+        }
+    }
+}
+
+/// Windows and buffers shown in tab page
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct TabInfo {
+    /// index of current window of the tab
+    current: usize,
+    windows: Vec<WinInfo>,
+}
+
+impl TabInfo {
+    fn is_modified(&self) -> bool {
+        self.windows.iter().any(|win| win.modified != 0)
+    }
+
+    fn current_window(&self) -> Option<&WinInfo> {
+        self.windows.get(self.current)
+    }
+
+    fn tooltip(&self) -> String {
+        self.windows
+            .iter()
+            .map(|win| if win.modified != 0 {
+                format!("{} [+]", win.display_name())
+            } else {
+                win.display_name().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn parse_tab_info(json: &str) -> Result<Vec<TabInfo>, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+/// Vim expression that returns listed buffers as json
+const BUF_LIST_EXPR: &str = "json_encode(map(filter(nvim_list_bufs(), 'buflisted(v:val)'), \
+     {_, b -> {'nr': b, 'name': bufname(b), 'modified': getbufvar(b, '&modified')}}))";
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct BufInfo {
+    nr: u64,
+    name: String,
+    modified: u8,
+}
+
+impl BufInfo {
+    fn title(&self) -> &str {
+        Path::new(&self.name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("[No Name]")
+    }
+}
+
+/// Parse listed buffers, `deleted` buffer is still listed while `BufDelete` is triggered
+fn parse_buf_list(json: &str, deleted: Option<u64>) -> Result<Vec<BufInfo>, String> {
+    let buffers: Vec<BufInfo> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    Ok(buffers
+        .into_iter()
+        .filter(|buf| Some(buf.nr) != deleted)
+        .collect())
+}
+
+/// What notebook pages represent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TablineMode {
+    Tabs,
+    Buffers,
+}
+
+impl TablineMode {
+    pub fn from_name(name: &str) -> Result<TablineMode, String> {
+        match name {
+            "tabs" => Ok(TablineMode::Tabs),
+            "buffers" => Ok(TablineMode::Buffers),
+            _ => Err(format!("Unknown tabline mode {}", name)),
+        }
+    }
+}
+
+/// Widgets of one notebook page
+struct Page {
+    page: gtk::Widget,
+    label: gtk::EventBox,
+    icon: gtk::Image,
+    title: gtk::Label,
+    modified: gtk::Label,
+}
+
+impl Page {
+    fn update_info(&self, info: Option<&TabInfo>) {
+        match info {
+            Some(info) => {
+                self.modified.set_visible(info.is_modified());
+                self.label.set_tooltip_text(info.tooltip().as_str());
+
+                let icon = info.current_window()
+                    .and_then(|win| file_icon(&win.name, &win.filetype));
+                match icon {
+                    Some(icon) => self.icon.set_from_gicon(&icon, gtk::IconSize::Menu.into()),
+                    None => self.icon
+                        .set_from_icon_name("text-x-generic", gtk::IconSize::Menu.into()),
+                }
+            }
+            None => {
+                self.modified.hide();
+                self.label.set_tooltip_text(None);
+            }
+        }
+    }
+
+    fn update_buf_info(&self, buf: &BufInfo) {
+        self.title.set_text(buf.title());
+        self.modified.set_visible(buf.modified != 0);
+        self.label.set_tooltip_text(buf.name.as_str());
+
+        match file_icon(&buf.name, "") {
+            Some(icon) => self.icon.set_from_gicon(&icon, gtk::IconSize::Menu.into()),
+            None => self.icon
+                .set_from_icon_name("text-x-generic", gtk::IconSize::Menu.into()),
+        }
+    }
+}
+
+/// Themed icon guessed from file name
+fn file_icon(name: &str, filetype: &str) -> Option<gio::Icon> {
+    if name.is_empty() && filetype.is_empty() {
+        return None;
+    }
+
+    let (content_type, _) = gio::content_type_guess(name, &[]);
+    gio::content_type_get_icon(&content_type)
+}
 
 struct State {
+    mode: TablineMode,
     data: Vec<Tabpage>,
+    titles: Vec<String>,
     selected: Option<Tabpage>,
+    buffers: Vec<BufInfo>,
+    current_buf: u64,
     nvim: Option<Rc<nvim::NeovimClient>>,
+    /// notebook pages in nvim tab (or buffer) order
+    pages: Vec<Page>,
+    info: Vec<TabInfo>,
+    /// tab index the context menu was opened for
+    menu_tab: u32,
 }
 
 impl State {
     pub fn new() -> Self {
         State {
+            mode: TablineMode::Tabs,
             data: Vec::new(),
+            titles: Vec::new(),
             selected: None,
+            buffers: Vec::new(),
+            current_buf: 0,
             nvim: None,
+            pages: Vec::new(),
+            info: Vec::new(),
+            menu_tab: 0,
+        }
+    }
+
+    fn page_count(&self) -> usize {
+        match self.mode {
+            TablineMode::Tabs => self.data.len(),
+            TablineMode::Buffers => self.buffers.len(),
+        }
+    }
+
+    fn selected_idx(&self) -> Option<usize> {
+        match self.mode {
+            TablineMode::Tabs => self.selected
+                .as_ref()
+                .and_then(|selected| self.data.iter().position(|tab| tab == selected)),
+            TablineMode::Buffers => self.buffers
+                .iter()
+                .position(|buf| buf.nr == self.current_buf),
+        }
+    }
+
+    fn update_labels(&self) {
+        for (idx, page) in self.pages.iter().enumerate() {
+            match self.mode {
+                TablineMode::Tabs => {
+                    page.title.set_text(&self.titles[idx]);
+                    page.update_info(self.info.get(idx));
+                }
+                TablineMode::Buffers => page.update_buf_info(&self.buffers[idx]),
+            }
         }
     }
 
     fn switch_page(&self, idx: u32) {
-        let target = &self.data[idx as usize];
-        if Some(target) != self.selected.as_ref() {
-            if let Some(mut nvim) = self.nvim.as_ref().unwrap().nvim() {
-                nvim.set_current_tabpage(target).report_err();
+        match self.mode {
+            TablineMode::Tabs => {
+                let target = &self.data[idx as usize];
+                if Some(target) != self.selected.as_ref() {
+                    if let Some(mut nvim) = self.nvim() {
+                        nvim.set_current_tabpage(target).report_err();
+                    }
+                }
+            }
+            TablineMode::Buffers => {
+                let target = &self.buffers[idx as usize];
+                if target.nr != self.current_buf {
+                    self.command(&format!(":buffer {}", target.nr));
+                }
+            }
+        }
+    }
+
+    fn switch_page_by(&self, offset: i32) {
+        if let Some(current) = self.selected_idx() {
+            if let Some(idx) = relative_page_idx(current, self.page_count(), offset) {
+                self.switch_page(idx as u32);
             }
         }
     }
 
     fn close_tab(&self, idx: u32) {
-        if let Some(mut nvim) = self.nvim.as_ref().unwrap().nvim() {
-            nvim.command_async(&format!(":tabc {}", idx + 1))
-                .cb(|r| r.report_err())
-                .call();
+        match self.mode {
+            TablineMode::Tabs => self.command(&format!(":tabc {}", idx + 1)),
+            TablineMode::Buffers => {
+                self.command(&format!(":bdelete {}", self.buffers[idx as usize].nr))
+            }
+        }
+    }
+
+    fn close_other_tabs(&self, idx: u32) {
+        self.command(&format!(":tabnext {} | tabonly", idx + 1));
+    }
+
+    fn close_tabs_to_right(&self, idx: u32) {
+        if let Some(cmd) = close_right_command(idx, self.data.len()) {
+            self.command(&cmd);
+        }
+    }
+
+    fn duplicate_tab(&self, idx: u32) {
+        self.command(&format!(":tabnext {} | tab split", idx + 1));
+    }
+
+    /// Page was dragged to new position in notebook, move nvim tab accordingly
+    fn page_reordered(&mut self, page: &gtk::Widget, new_idx: u32) {
+        if self.mode != TablineMode::Tabs {
+            return;
+        }
+
+        if let Some(old_idx) = self.pages.iter().position(|p| p.page == *page) {
+            let page = self.pages.remove(old_idx);
+            self.pages.insert(new_idx as usize, page);
+
+            if old_idx as u32 != new_idx {
+                self.command(&move_command(old_idx as u32, new_idx));
+            }
+        }
+    }
+
+    fn update_info(&mut self, info: Vec<TabInfo>) {
+        if self.mode == TablineMode::Tabs {
+            for (idx, page) in self.pages.iter().enumerate() {
+                page.update_info(info.get(idx));
+            }
+        }
+        self.info = info;
+    }
+
+    fn nvim(&self) -> Option<nvim::NeovimRef> {
+        self.nvim.as_ref().and_then(|nvim| nvim.nvim())
+    }
+
+    fn command(&self, cmd: &str) {
+        if let Some(mut nvim) = self.nvim() {
+            nvim.command_async(cmd).cb(|r| r.report_err()).call();
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Tabline {
     tabs: gtk::Notebook,
     state: Rc<RefCell<State>>,
-    switch_handler_id: glib::SignalHandlerId,
+    switch_handler_id: Rc<glib::SignalHandlerId>,
+    context_menu: gtk::Menu,
 }
 
 impl Tabline {
@@ -72,10 +351,27 @@ impl Tabline {
         let switch_handler_id =
             tabs.connect_switch_page(move |_, _, idx| state_ref.borrow().switch_page(idx));
 
+        let state_ref = state.clone();
+        tabs.connect_page_reordered(move |_, page, idx| {
+            state_ref.borrow_mut().page_reordered(page, idx)
+        });
+
+        let new_tab_btn =
+            gtk::Button::new_from_icon_name("list-add-symbolic", gtk::IconSize::Menu.into());
+        new_tab_btn.set_relief(gtk::ReliefStyle::None);
+        new_tab_btn.set_focus_on_click(false);
+        new_tab_btn.set_tooltip_text("Open a new tab");
+        new_tab_btn.set_action_name("win.new-tab");
+        new_tab_btn.show();
+        tabs.set_action_widget(&new_tab_btn, gtk::PackType::End);
+
+        let context_menu = create_context_menu(&state);
+
         Tabline {
             tabs,
             state,
-            switch_handler_id,
+            switch_handler_id: Rc::new(switch_handler_id),
+            context_menu,
         }
     }
 
@@ -94,6 +390,9 @@ impl Tabline {
         state.selected = Some(selected.clone());
 
         state.data = tabs.iter().map(|item| item.0.clone()).collect();
+        state.titles = tabs.iter()
+            .map(|item| item.1.clone().unwrap_or_else(|| "??".to_owned()))
+            .collect();
     }
 
     pub fn update_tabs(
@@ -102,26 +401,45 @@ impl Tabline {
         selected: &Tabpage,
         tabs: &[(Tabpage, Option<String>)],
     ) {
-        if tabs.len() <= 1 {
+        self.update_state(nvim, selected, tabs);
+
+        if self.state.borrow().mode == TablineMode::Tabs {
+            self.update_pages();
+        }
+    }
+
+    /// Show tab pages or listed buffers
+    pub fn set_mode(&self, mode: TablineMode) {
+        self.state.borrow_mut().mode = mode;
+        self.update_pages();
+    }
+
+    fn update_pages(&self) {
+        let (count, selected) = {
+            let state = self.state.borrow();
+            (state.page_count(), state.selected_idx())
+        };
+
+        if count <= 1 {
             self.tabs.hide();
             return;
         } else {
             self.tabs.show();
         }
 
-        self.update_state(nvim, selected, tabs);
-
 
         signal::signal_handler_block(&self.tabs, &self.switch_handler_id);
 
-        let count = self.tabs.get_n_pages() as usize;
-        if count < tabs.len() {
-            for _ in count..tabs.len() {
+        let page_count = self.tabs.get_n_pages() as usize;
+        if page_count < count {
+            for _ in page_count..count {
                 let empty = gtk::Box::new(gtk::Orientation::Vertical, 0);
                 empty.show_all();
+                let icon = gtk::Image::new();
                 let title = gtk::Label::new(None);
                 title.set_ellipsize(pango::EllipsizeMode::Middle);
                 title.set_width_chars(25);
+                let modified = gtk::Label::new("\u{25CF}");
                 let close_btn = gtk::Button::new_from_icon_name(
                     "window-close-symbolic",
                     gtk::IconSize::Menu.into(),
@@ -130,54 +448,213 @@ impl Tabline {
                 close_btn.get_style_context().unwrap().add_class("small-button");
                 close_btn.set_focus_on_click(false);
                 let label_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+                label_box.pack_start(&icon, false, false, 0);
                 label_box.pack_start(&title, true, false, 0);
+                label_box.pack_start(&modified, false, false, 0);
                 label_box.pack_start(&close_btn, false, false, 0);
-                title.show();
-                close_btn.show();
-                self.tabs.append_page(&empty, Some(&label_box));
+                // event box to catch middle and right clicks on tab
+                let label_events = gtk::EventBox::new();
+                label_events.set_visible_window(false);
+                label_events.add(&label_box);
+                label_events.show_all();
+                modified.hide();
+                self.tabs.append_page(&empty, Some(&label_events));
+                self.state.borrow_mut().pages.push(Page {
+                    page: empty.upcast(),
+                    label: label_events.clone(),
+                    icon,
+                    title,
+                    modified,
+                });
 
                 let tabs = self.tabs.clone();
                 let state_ref = Rc::clone(&self.state);
                 close_btn.connect_clicked(move |btn| {
-                    let current_label = btn
-                        .get_parent().unwrap();
-                    for i in 0..tabs.get_n_pages() {
-                        let page = tabs.get_nth_page(Some(i)).unwrap();
-                        let label = tabs.get_tab_label(&page).unwrap();
-                        if label == current_label {
-                            state_ref.borrow().close_tab(i);
+                    let current_label = btn.get_parent().and_then(|w| w.get_parent()).unwrap();
+                    if let Some(idx) = tab_label_idx(&tabs, &current_label) {
+                        state_ref.borrow().close_tab(idx);
+                    }
+                });
+
+                let tabs = self.tabs.clone();
+                let state_ref = Rc::clone(&self.state);
+                let context_menu = self.context_menu.clone();
+                label_events.connect_button_press_event(move |label, ev| {
+                    if ev.get_event_type() != gdk::EventType::ButtonPress {
+                        return Inhibit(false);
+                    }
+
+                    let idx = match tab_label_idx(&tabs, &label.clone().upcast()) {
+                        Some(idx) => idx,
+                        None => return Inhibit(false),
+                    };
+
+                    match ev.get_button() {
+                        2 => {
+                            state_ref.borrow().close_tab(idx);
+                            Inhibit(true)
+                        }
+                        3 if state_ref.borrow().mode == TablineMode::Tabs => {
+                            state_ref.borrow_mut().menu_tab = idx;
+                            context_menu.popup_at_pointer(&**ev);
+                            Inhibit(true)
                         }
+                        _ => Inhibit(false),
                     }
                 });
             }
-        } else if count > tabs.len() {
-            for _ in tabs.len()..count {
+        } else if page_count > count {
+            for _ in count..page_count {
                 self.tabs.remove_page(None);
+                self.state.borrow_mut().pages.pop();
             }
         }
 
-        for (idx, tab) in tabs.iter().enumerate() {
-            let tab_child = self.tabs.get_nth_page(Some(idx as u32));
-            let tab_label = self.tabs
-                .get_tab_label(&tab_child.unwrap())
-                .unwrap()
-                .downcast::<gtk::Box>()
-                .unwrap()
-                .get_children()
-                .into_iter()
-                .next()
-                .unwrap()
-                .downcast::<gtk::Label>()
-                .unwrap();
-            tab_label.set_text(tab.1.as_ref().unwrap_or(&"??".to_owned()));
-
-            if *selected == tab.0 {
-                self.tabs.set_current_page(Some(idx as u32));
+        {
+            let state = self.state.borrow();
+            state.update_labels();
+
+            let reorderable = state.mode == TablineMode::Tabs;
+            for page in &state.pages {
+                self.tabs.set_tab_reorderable(&page.page, reorderable);
             }
         }
 
+        if let Some(idx) = selected {
+            self.tabs.set_current_page(Some(idx as u32));
+        }
+
         signal::signal_handler_unblock(&self.tabs, &self.switch_handler_id);
     }
+
+    /// Track modified state and windows of tabs and listed buffers
+    pub fn init_subscriptions(&self, shell_state: &shell::State) {
+        self.state.borrow_mut().nvim = Some(shell_state.nvim_clone());
+
+        let state_ref = Rc::clone(&self.state);
+        // InsertLeave instead of TextChangedI, so typing doesn't evaluate the tab info
+        let subscription = shell_state.subscribe(
+            "BufEnter,BufWritePost,TextChanged,InsertLeave,WinEnter,TabEnter,TabClosed",
+            &[TAB_INFO_EXPR],
+            move |args| match parse_tab_info(&args[0]) {
+                Ok(info) => state_ref.borrow_mut().update_info(info),
+                Err(err) => error!("Can't parse tab info: {}", err),
+            },
+        );
+        shell_state.run_now(&subscription);
+
+        let tabline = self.clone();
+        let buffers_subscription = shell_state.subscribe(
+            "BufAdd,BufEnter,BufWritePost,TextChanged,InsertLeave",
+            &[BUF_LIST_EXPR, "bufnr('%')"],
+            move |args| tabline.update_buffers(&args, None),
+        );
+        shell_state.run_now(&buffers_subscription);
+
+        let tabline = self.clone();
+        shell_state.subscribe(
+            "BufDelete",
+            &[BUF_LIST_EXPR, "bufnr('%')", "expand('<abuf>')"],
+            move |args| tabline.update_buffers(&args, args[2].parse().ok()),
+        );
+    }
+
+    fn update_buffers(&self, args: &[String], deleted: Option<u64>) {
+        let buffers = match parse_buf_list(&args[0], deleted) {
+            Ok(buffers) => buffers,
+            Err(err) => {
+                error!("Can't parse buffer list: {}", err);
+                return;
+            }
+        };
+
+        let mode = {
+            let mut state = self.state.borrow_mut();
+            state.buffers = buffers;
+            state.current_buf = args[1].parse().unwrap_or(0);
+            state.mode
+        };
+
+        if mode == TablineMode::Buffers {
+            self.update_pages();
+        }
+    }
+
+    /// Switch to the tab `offset` pages away from selected one, wrapping around
+    pub fn switch_page_by(&self, offset: i32) {
+        self.state.borrow().switch_page_by(offset);
+    }
+}
+
+fn create_context_menu(state: &Rc<RefCell<State>>) -> gtk::Menu {
+    let menu = gtk::Menu::new();
+
+    let close_others = gtk::MenuItem::new_with_label("Close others");
+    let state_ref = Rc::clone(state);
+    close_others.connect_activate(move |_| {
+        let state = state_ref.borrow();
+        state.close_other_tabs(state.menu_tab);
+    });
+    menu.append(&close_others);
+
+    let close_right = gtk::MenuItem::new_with_label("Close to the right");
+    let state_ref = Rc::clone(state);
+    close_right.connect_activate(move |_| {
+        let state = state_ref.borrow();
+        state.close_tabs_to_right(state.menu_tab);
+    });
+    menu.append(&close_right);
+
+    let duplicate = gtk::MenuItem::new_with_label("Duplicate");
+    let state_ref = Rc::clone(state);
+    duplicate.connect_activate(move |_| {
+        let state = state_ref.borrow();
+        state.duplicate_tab(state.menu_tab);
+    });
+    menu.append(&duplicate);
+
+    menu.show_all();
+    menu
+}
+
+/// Index of the page with given tab label widget
+fn tab_label_idx(tabs: &gtk::Notebook, label: &gtk::Widget) -> Option<u32> {
+    (0..tabs.get_n_pages()).find(|&i| {
+        tabs.get_nth_page(Some(i))
+            .and_then(|page| tabs.get_tab_label(&page))
+            .map_or(false, |tab_label| tab_label == *label)
+    })
+}
+
+/// Command that closes all tabs after tab `idx`
+fn close_right_command(idx: u32, count: usize) -> Option<String> {
+    let cmds: Vec<_> = (idx as usize + 2..count + 1)
+        .rev()
+        .map(|tab| format!("tabclose {}", tab))
+        .collect();
+
+    if cmds.is_empty() {
+        None
+    } else {
+        Some(format!(":{}", cmds.join(" | ")))
+    }
+}
+
+/// Command that moves tab from `old_idx` to `new_idx` position
+/// `tabmove N` moves the page after page N counted before the move,
+/// so moving to the right has to skip the moved page itself.
+fn move_command(old_idx: u32, new_idx: u32) -> String {
+    let target = if new_idx > old_idx { new_idx + 1 } else { new_idx };
+    format!(":tabnext {} | tabmove {}", old_idx + 1, target)
+}
+
+fn relative_page_idx(current: usize, count: usize, offset: i32) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+
+    let count = count as i32;
+    Some((((current as i32 + offset) % count + count) % count) as usize)
 }
 
 impl Deref for Tabline {
@@ -187,3 +664,61 @@ impl Deref for Tabline {
         &self.tabs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_page_idx() {
+        assert_eq!(Some(1), relative_page_idx(0, 3, 1));
+        assert_eq!(Some(0), relative_page_idx(2, 3, 1));
+        assert_eq!(Some(2), relative_page_idx(0, 3, -1));
+        assert_eq!(None, relative_page_idx(0, 0, 1));
+    }
+
+    #[test]
+    fn test_close_right_command() {
+        assert_eq!(
+            Some(":tabclose 4 | tabclose 3".to_owned()),
+            close_right_command(1, 4)
+        );
+        assert_eq!(None, close_right_command(3, 4));
+    }
+
+    #[test]
+    fn test_parse_tab_info() {
+        let info = parse_tab_info(
+            r#"[{"current": 1, "windows": [
+                {"name": "src/main.rs", "modified": 0, "filetype": "rust"},
+                {"name": "", "modified": 1, "filetype": ""}]}]"#,
+        ).unwrap();
+
+        assert_eq!(1, info.len());
+        assert!(info[0].is_modified());
+        assert_eq!("", info[0].current_window().unwrap().name);
+        assert_eq!("src/main.rs\n[No Name] [+]", info[0].tooltip());
+    }
+
+    #[test]
+    fn test_parse_buf_list() {
+        let json = r#"[{"nr": 1, "name": "/tmp/a.rs", "modified": 0},
+            {"nr": 3, "name": "", "modified": 1}]"#;
+
+        let buffers = parse_buf_list(json, None).unwrap();
+        assert_eq!(2, buffers.len());
+        assert_eq!("a.rs", buffers[0].title());
+        assert_eq!("[No Name]", buffers[1].title());
+
+        let buffers = parse_buf_list(json, Some(1)).unwrap();
+        assert_eq!(1, buffers.len());
+        assert_eq!(3, buffers[0].nr);
+    }
+
+    #[test]
+    fn test_move_command() {
+        assert_eq!(":tabnext 1 | tabmove 3", move_command(0, 2));
+        assert_eq!(":tabnext 3 | tabmove 0", move_command(2, 0));
+        assert_eq!(":tabnext 4 | tabmove 1", move_command(3, 1));
+    }
+}
@@ -0,0 +1,34 @@
+//! Helpers shared by the unit tests.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Empty temporary directory, removed on drop, so failing tests don't leave it behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates the directory, `name` must be unique among the tests.
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("nvim-gtk-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -1,22 +1,90 @@
 use std::collections::HashMap;
 
-use neovim_lib::{Value, Neovim, NeovimApi};
+use neovim_lib::{CallError, Value, Neovim, NeovimApi};
 
-use nvim::ErrorReport;
+use nvim::{self, ErrorReport};
 use color::Color;
+use mode;
 use value::ValueMapExt;
 
 pub struct Theme {
     pub pmenu: Option<Pmenu>,
+    cursor: HlColors,
+    term_cursor: HlColors,
+    cursor_im: HlColors,
+    /// cursor colors by highlight group id from mode info
+    mode_cursor: HashMap<u64, HlColors>,
 }
 
 impl Theme {
     pub fn new() -> Self {
-        Theme { pmenu: None }
+        Theme {
+            pmenu: None,
+            cursor: HlColors::default(),
+            term_cursor: HlColors::default(),
+            cursor_im: HlColors::default(),
+            mode_cursor: HashMap::new(),
+        }
     }
 
     pub fn update(&mut self, nvim: &mut Neovim) {
         self.pmenu = Some(Pmenu::new(nvim));
+        self.cursor = HlColors::from(nvim.get_hl_by_name("Cursor", true));
+        self.term_cursor = HlColors::from(nvim.get_hl_by_name("TermCursor", true));
+        self.cursor_im = HlColors::from(nvim.get_hl_by_name("CursorIM", true));
+    }
+
+    pub fn update_mode_cursor(&mut self, nvim: &mut Neovim, mode_info: &[nvim::ModeInfo]) {
+        self.mode_cursor.clear();
+
+        let hl_ids = mode_info
+            .iter()
+            .map(|info| info.hl_id(false))
+            .filter(|&id| id > 0);
+        for hl_id in hl_ids {
+            if !self.mode_cursor.contains_key(&hl_id) {
+                let colors = HlColors::from(nvim.get_hl_by_id(hl_id as i64, true));
+                self.mode_cursor.insert(hl_id, colors);
+            }
+        }
+    }
+
+    /// Cursor colors for current mode: `TermCursor` in terminal mode, `CursorIM` while an
+    /// input method composes text and the group is set, highlight group from `guicursor`
+    /// or `Cursor` otherwise.
+    ///
+    /// nvim doesn't tell UIs whether `:lmap` mappings are active, so `lCursor` isn't used.
+    pub fn cursor(&self, mode: &mode::Mode) -> &HlColors {
+        if mode.is(&mode::NvimMode::Terminal) {
+            return &self.term_cursor;
+        }
+        if mode.im_active() && self.cursor_im.is_set() {
+            return &self.cursor_im;
+        }
+
+        mode.mode_info()
+            .and_then(|info| self.mode_cursor.get(&info.hl_id(false)))
+            .unwrap_or(&self.cursor)
+    }
+}
+
+#[derive(Default)]
+pub struct HlColors {
+    pub bg: Option<Color>,
+    pub fg: Option<Color>,
+}
+
+impl HlColors {
+    /// Whether the highlight group has colors.
+    fn is_set(&self) -> bool {
+        self.bg.is_some() || self.fg.is_some()
+    }
+}
+
+impl From<Result<Vec<(Value, Value)>, CallError>> for HlColors {
+    fn from(hl: Result<Vec<(Value, Value)>, CallError>) -> Self {
+        let (bg, fg) = hl_colors(hl);
+        HlColors { bg, fg }
     }
 }
 
@@ -54,8 +122,11 @@ fn get_hl_color(map: &HashMap<&str, &Value>, color_name: &str) -> Option<Color>
 }
 
 fn get_hl_colors(nvim: &mut Neovim, hl: &str) -> (Option<Color>, Option<Color>) {
-    nvim.get_hl_by_name(hl, true)
-        .ok_and_report()
+    hl_colors(nvim.get_hl_by_name(hl, true))
+}
+
+fn hl_colors(hl: Result<Vec<(Value, Value)>, CallError>) -> (Option<Color>, Option<Color>) {
+    hl.ok_and_report()
         .and_then(|m| if let Some(m) = m.to_attrs_map_report() {
             let reverse = m.get("reverse").and_then(|v| v.as_bool()).unwrap_or(false);
             let bg = get_hl_color(&m, "background");
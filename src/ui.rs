@@ -15,9 +15,12 @@ use toml;
 use settings::{Settings, SettingsLoader};
 use shell::{self, Shell, ShellOptions};
 use shell_dlg;
+use command_palette;
 use project::Projects;
+use quick_open;
 use plug_manager;
 use file_browser::FileBrowserWidget;
+use tabline::TablineMode;
 use subscriptions::SubscriptionHandle;
 
 macro_rules! clone {
@@ -187,6 +190,30 @@ impl Ui {
         });
         app.add_action(&show_sidebar_action);
 
+        let buffer_tabline_action =
+            SimpleAction::new_stateful("buffer-tabline", None, &false.to_variant());
+        let shell_ref = self.shell.clone();
+        let comps_ref = self.comps.clone();
+        buffer_tabline_action.connect_change_state(move |action, value| {
+            if let Some(ref value) = *value {
+                action.set_state(value);
+                let is_active = value.get::<bool>().unwrap();
+                let mode = if is_active {
+                    TablineMode::Buffers
+                } else {
+                    TablineMode::Tabs
+                };
+                shell_ref.borrow().state.borrow().set_tabline_mode(mode);
+                comps_ref.borrow_mut().window_state.buffer_tabline = is_active;
+            }
+        });
+        app.add_action(&buffer_tabline_action);
+
+        self.create_window_actions(&window);
+        app.set_accels_for_action("win.zoom-in", &["<Primary>equal", "<Primary>plus"]);
+        app.set_accels_for_action("win.zoom-out", &["<Primary>minus"]);
+        app.set_accels_for_action("win.zoom-reset", &["<Primary>0"]);
+
         let comps_ref = self.comps.clone();
         window.connect_size_allocate(clone!(main => move |window, _| {
             gtk_window_size_allocate(
@@ -223,6 +250,9 @@ impl Ui {
             show_sidebar_action.change_state(&show_sidebar.to_variant());
         }
 
+        let buffer_tabline = self.comps.borrow().window_state.buffer_tabline;
+        buffer_tabline_action.change_state(&buffer_tabline.to_variant());
+
         let comps_ref = self.comps.clone();
         let update_title = shell.state.borrow().subscribe(
             "BufEnter,DirChanged",
@@ -249,7 +279,8 @@ impl Ui {
 
         let comps_ref = self.comps.clone();
         let shell_ref = self.shell.clone();
-        window.connect_delete_event(move |_, _| gtk_delete(&*comps_ref, &*shell_ref));
+        let projects = self.projects.clone();
+        window.connect_delete_event(move |_, _| gtk_delete(&*comps_ref, &*shell_ref, &projects));
 
         shell.grab_focus();
 
@@ -270,7 +301,8 @@ impl Ui {
             plug_manager_ref
                 .borrow_mut()
                 .init_nvim_client(state_ref.borrow().nvim_clone());
-            file_browser_ref.borrow_mut().init(&state);
+            file_browser_ref.borrow_mut().init(&state_ref);
+            state.init_tabline();
             state.set_autocmds();
             state.run_now(&update_title);
             if let Some(ref update_subtitle) = update_subtitle {
@@ -279,6 +311,50 @@ impl Ui {
         }));
     }
 
+    /// Window actions, that can be bound to keys in keymap
+    fn create_window_actions(&self, window: &gtk::ApplicationWindow) {
+        let paste_action = SimpleAction::new("paste", None);
+        let shell = self.shell.clone();
+        paste_action.connect_activate(move |_, _| shell.borrow().edit_paste());
+        window.add_action(&paste_action);
+
+        let save_all_action = SimpleAction::new("save-all", None);
+        let shell = self.shell.clone();
+        save_all_action.connect_activate(move |_, _| shell.borrow().edit_save_all());
+        window.add_action(&save_all_action);
+
+        let new_tab_action = SimpleAction::new("new-tab", None);
+        let shell = self.shell.clone();
+        new_tab_action.connect_activate(move |_, _| shell.borrow().new_tab());
+        window.add_action(&new_tab_action);
+
+        let zoom_in_action = SimpleAction::new("zoom-in", None);
+        let shell = self.shell.clone();
+        zoom_in_action.connect_activate(move |_, _| shell.borrow().font_zoom(1));
+        window.add_action(&zoom_in_action);
+
+        let zoom_out_action = SimpleAction::new("zoom-out", None);
+        let shell = self.shell.clone();
+        zoom_out_action.connect_activate(move |_, _| shell.borrow().font_zoom(-1));
+        window.add_action(&zoom_out_action);
+
+        let zoom_reset_action = SimpleAction::new("zoom-reset", None);
+        let shell = self.shell.clone();
+        zoom_reset_action.connect_activate(move |_, _| shell.borrow().font_zoom_reset());
+        window.add_action(&zoom_reset_action);
+
+        let quick_open_action = SimpleAction::new("quick-open", None);
+        let shell = self.shell.clone();
+        quick_open_action.connect_activate(move |_, _| quick_open::show(&shell.borrow().state));
+        window.add_action(&quick_open_action);
+
+        let command_palette_action = SimpleAction::new("command-palette", None);
+        let shell = self.shell.clone();
+        command_palette_action
+            .connect_activate(move |_, _| command_palette::show(&shell.borrow().state));
+        window.add_action(&command_palette_action);
+    }
+
     fn create_header_bar(&self) -> SubscriptionHandle {
         let header_bar = HeaderBar::new();
         let comps = self.comps.borrow();
@@ -288,7 +364,7 @@ impl Ui {
         header_bar.pack_start(&comps.open_btn);
         comps
             .open_btn
-            .connect_clicked(move |_| projects.borrow_mut().show());
+            .connect_clicked(move |_| Projects::show(&projects));
 
         let new_tab_btn =
             Button::new_from_icon_name("tab-new-symbolic", gtk::IconSize::SmallToolbar.into());
@@ -339,6 +415,7 @@ impl Ui {
 
         let section = Menu::new();
         section.append_item(&MenuItem::new("Sidebar", "app.show-sidebar"));
+        section.append_item(&MenuItem::new("Buffer Tabline", "app.buffer-tabline"));
         menu.append_section(None, &section);
 
         let section = Menu::new();
@@ -382,12 +459,17 @@ fn on_help_about(window: &gtk::ApplicationWindow) {
     about.show();
 }
 
-fn gtk_delete(comps: &UiMutex<Components>, shell: &RefCell<Shell>) -> Inhibit {
+fn gtk_delete(
+    comps: &UiMutex<Components>,
+    shell: &RefCell<Shell>,
+    projects: &RefCell<Projects>,
+) -> Inhibit {
     if !shell.borrow().is_nvim_initialized() {
         return Inhibit(false);
     }
 
     Inhibit(if shell_dlg::can_close_window(comps, shell) {
+        projects.borrow().save_session();
         let comps = comps.borrow();
         comps.close_window();
         shell.borrow_mut().detach_ui();
@@ -425,6 +507,8 @@ struct WindowState {
     is_maximized: bool,
     show_sidebar: bool,
     sidebar_width: i32,
+    #[serde(default)]
+    buffer_tabline: bool,
 }
 
 impl WindowState {
@@ -435,6 +519,7 @@ impl WindowState {
             is_maximized: false,
             show_sidebar: false,
             sidebar_width: DEFAULT_SIDEBAR_WIDTH,
+            buffer_tabline: false,
         }
     }
 }
@@ -69,6 +69,20 @@ impl UiModel {
         ModelRect::point(self.cur_col, self.cur_row)
     }
 
+    /// Cursor cell, including the second half of a double width char
+    pub fn cur_cell_rect(&self) -> ModelRect {
+        let double_width = self.model
+            .get(self.cur_row)
+            .and_then(|line| line.line.get(self.cur_col + 1))
+            .map_or(false, |cell| cell.attrs.double_width);
+
+        if double_width {
+            ModelRect::new(self.cur_row, self.cur_row, self.cur_col, self.cur_col + 1)
+        } else {
+            self.cur_point()
+        }
+    }
+
     pub fn set_cursor(&mut self, row: usize, col: usize) -> ModelRectVec {
         // it is possible in some cases that cursor moved out of visible rect
         // see https://github.com/daa84/neovim-gtk/issues/20
@@ -273,6 +287,28 @@ mod tests {
         assert_eq!(5, rect.list[1].right);
     }
 
+    #[test]
+    fn test_cur_cell_rect_double_width() {
+        let mut model = UiModel::new(10, 20);
+
+        model.set_cursor(1, 1);
+        model.put("あ", None);
+        model.put("", None);
+        model.set_cursor(1, 1);
+
+        let rect = model.cur_cell_rect();
+        assert_eq!(1, rect.top);
+        assert_eq!(1, rect.left);
+        assert_eq!(1, rect.bot);
+        assert_eq!(2, rect.right);
+
+        model.set_cursor(1, 3);
+
+        let rect = model.cur_cell_rect();
+        assert_eq!(3, rect.left);
+        assert_eq!(3, rect.right);
+    }
+
     #[test]
     fn test_eol_clear_area() {
         let mut model = UiModel::new(10, 20);